@@ -25,7 +25,7 @@ use embedded_graphics::{
     pixelcolor::{Rgb565, RgbColor},
 };
 use embedded_hal::{
-    adc::{Channel, OneShot},
+    adc::OneShot,
     blocking::delay::DelayUs,
     digital::v2::{InputPin, OutputPin},
     spi::MODE_0,
@@ -33,7 +33,7 @@ use embedded_hal::{
 use fugit::RateExtU32;
 pub use hal::pac;
 use hal::{
-    adc::Adc,
+    adc::{Adc, AdcPin},
     gpio::{
         bank0::{
             Gpio0, Gpio1, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17, Gpio18, Gpio19, Gpio2,
@@ -267,7 +267,7 @@ impl PicoExplorer {
         }
     }
 
-    pub fn get_adc<Pin: Channel<Adc, ID = u8>>(&mut self, channel: &mut Pin) -> f32 {
+    pub fn get_adc<Pin: AdcPin>(&mut self, channel: &mut Pin) -> f32 {
         // scale raw 12-bit adc value to 0 .. 1 float
         let adc_value: u16 = self.adc.read(channel).unwrap();
         let mut result: f32 = f32::from(adc_value) / f32::from(1u16 << 12);
@@ -37,6 +37,25 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     .into()
 }
 
+#[proc_macro_attribute]
+pub fn ram_function(args: TokenStream, input: TokenStream) -> TokenStream {
+    let f = parse_macro_input!(input as ItemFn);
+
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    // cortex-m-rt copies .data and its subsections to RAM at startup, code included
+    quote!(
+        #[inline(never)]
+        #[link_section = ".data.ram_func"]
+        #f
+    )
+    .into()
+}
+
 /// Insert new statements after initial block of statics
 fn insert_after_static(stmts: impl IntoIterator<Item = Stmt>, insert: Stmt) -> Vec<Stmt> {
     let mut istmts = stmts.into_iter();
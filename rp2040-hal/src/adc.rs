@@ -34,23 +34,104 @@
 //! let temperature_adc_counts: u16 = adc.read(&mut temperature_sensor).unwrap();
 //! ```
 //!
+//! Read the temperature sensor in degrees Celsius
+//! ```no_run
+//! use rp2040_hal::{adc::{Adc, DEFAULT_VREF}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut temperature_sensor = adc.enable_temp_sensor();
+//! let celsius: f32 = adc.read_temperature(&mut temperature_sensor, DEFAULT_VREF).unwrap();
+//! ```
+//!
+//! Sample several channels in round-robin mode, reading the results, tagged with their channel,
+//! from the FIFO.
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! // Enable adc
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut adc_pin_0 = pins.gpio26.into_floating_input();
+//! let mut adc_pin_1 = pins.gpio27.into_floating_input();
+//! let temperature_sensor = adc.enable_temp_sensor();
+//! // Cycle through both pins and the temperature sensor
+//! let mut fifo = adc
+//!     .build_fifo()
+//!     .set_channel(&mut adc_pin_0)
+//!     .round_robin((&adc_pin_0, &adc_pin_1, &temperature_sensor))
+//!     .start();
+//! loop {
+//!     if let Some((channel, counts)) = fifo.read_tagged() {
+//!         // `channel` is 0, 1 or 4, `counts` the corresponding reading
+//!     }
+//! }
+//! ```
+//!
+//! ## Input multiplexer
+//!
+//! All inputs share a single sample-and-hold capacitor. After switching to a
+//! different input, the first conversion can still be influenced by the charge
+//! left from the previous input, especially with high impedance sources. The
+//! one-shot reads ([`OneShot`], [`Adc::read_temperature`], [`OversampledAdc`])
+//! therefore discard a conversion whenever they switch inputs, so hopping
+//! between channels returns fresh readings at the cost of one extra conversion
+//! (2µs). [`Adc::select_channel`] does the same ahead of time. In round-robin
+//! mode, inputs are switched by the hardware without such a delay, so sources
+//! should be buffered or have a low impedance.
+//!
+//! All readings are relative to the shared reference voltage, ADC_AVDD. Noise
+//! on this supply shows up on every channel: on the Pico, it is filtered from
+//! the 3.3V rail, whose ripple can be reduced by setting GPIO23 high, which
+//! switches the regulator to PWM mode. When measuring absolute voltages, pass
+//! the real reference voltage to conversions like [`TempSense::counts_to_celsius`]
+//! instead of [`DEFAULT_VREF`].
+//!
+//! With the `async` feature, conversions can be awaited instead of polling for
+//! their completion. Call [`Adc::on_interrupt`] from the `ADC_IRQ_FIFO` interrupt handler:
+//! ```ignore
+//! #[interrupt]
+//! fn ADC_IRQ_FIFO() {
+//!     rp2040_hal::adc::Adc::on_interrupt();
+//! }
+//!
+//! async fn sample(adc: &mut Adc, pin: &mut Pin<Gpio26, FloatingInput>) -> u16 {
+//!     adc.read_async(pin).await.unwrap()
+//! }
+//! ```
+//!
 //! See [examples/adc.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/adc.rs) and
 //! [pimoroni_pico_explorer_showcase.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pimoroni_pico_explorer/examples/pimoroni_pico_explorer_showcase.rs) for more complete examples
 
+use fugit::HertzU32;
 use hal::adc::{Channel, OneShot};
 use pac::{ADC, RESETS};
 
 use crate::{
+    dma::{
+        Channel as DmaChannel, ChannelConfig, ChannelIndex, DoubleBuffer, TransferSize, DREQ_ADC,
+    },
     gpio::Pin,
     gpio::{
         bank0::{Gpio26, Gpio27, Gpio28, Gpio29},
-        FloatingInput,
+        FloatingDisabled, FloatingInput,
     },
     resets::SubsystemReset,
 };
 
 const TEMPERATURE_SENSOR_CHANNEL: u8 = 4;
 
+/// The ADC reference voltage of most boards, in volts
+///
+/// On boards like the Pico, VREF is connected to the 3.3V supply via a filter.
+pub const DEFAULT_VREF: f32 = 3.3;
+
+/// The number of ADC clock cycles taken by a single conversion
+const CONVERSION_CYCLES: u64 = 96;
+
+/// The number of inputs selectable by the ADC multiplexer
+const NUM_CHANNELS: u8 = 5;
+
 /// Adc
 pub struct Adc {
     device: ADC,
@@ -68,11 +149,14 @@ impl Adc {
         // Wait for adc ready
         while !device.cs.read().ready().bit_is_set() {}
 
+        crate::sleep::peripheral_enabled::<ADC>();
+
         Self { device }
     }
 
     /// Free underlying register block
     pub fn free(self) -> ADC {
+        crate::sleep::peripheral_disabled::<ADC>();
         self.device
     }
 
@@ -92,17 +176,628 @@ impl Adc {
     pub fn disable_temp_sensor(&mut self, _: TempSense) {
         self.device.cs.modify(|_, w| w.ts_en().clear_bit());
     }
+
+    /// Read the temperature sensor and convert the reading to degrees Celsius
+    ///
+    /// `vref` is the ADC reference voltage in volts, see [`DEFAULT_VREF`].
+    pub fn read_temperature(&mut self, _sensor: &mut TempSense, vref: f32) -> Result<f32, Error> {
+        let counts = self.convert(TEMPERATURE_SENSOR_CHANNEL)?;
+        Ok(TempSense::counts_to_celsius(counts, vref))
+    }
+
+    /// Returns true if any conversion failed since the flag was last cleared
+    pub fn has_sticky_error(&self) -> bool {
+        self.device.cs.read().err_sticky().bit_is_set()
+    }
+
+    /// Clear the flag returned by [`has_sticky_error`](Self::has_sticky_error)
+    pub fn clear_sticky_error(&mut self) {
+        self.device.cs.modify(|_, w| w.err_sticky().set_bit());
+    }
+
+    /// Set the pacing divider for free-running conversions
+    ///
+    /// A conversion is started every `1 + int + frac / 256` cycles of the ADC
+    /// clock. As a conversion takes 96 cycles, values below 95 result in
+    /// back-to-back conversions. With both parts set to 0, conversions run
+    /// back-to-back as well.
+    pub fn set_divider(&mut self, int: u16, frac: u8) {
+        self.device
+            .div
+            .write(|w| unsafe { w.int().bits(int).frac().bits(frac) });
+    }
+
+    /// Set the rate of free-running conversions
+    ///
+    /// `adc_clock_freq` is the frequency of the ADC clock, usually 48MHz. The
+    /// rate is limited to what the hardware can do: at most one conversion
+    /// every 96 cycles, and at least one every 65536 cycles. Returns the rate
+    /// actually configured, which can differ slightly due to the resolution
+    /// of the divider.
+    pub fn set_sample_rate(&mut self, adc_clock_freq: HertzU32, rate: HertzU32) -> HertzU32 {
+        let clock = u64::from(adc_clock_freq.to_Hz());
+        // Length of a sample period, in 1/256 cycles of the ADC clock
+        let period = match rate.to_Hz() {
+            0 => u64::MAX,
+            rate => clock * 256 / u64::from(rate),
+        };
+        if period <= CONVERSION_CYCLES * 256 {
+            self.set_divider(0, 0);
+            return HertzU32::from_raw((clock / CONVERSION_CYCLES) as u32);
+        }
+        let div = core::cmp::min(period - 256, 0xff_ffff);
+        self.set_divider((div >> 8) as u16, div as u8);
+        HertzU32::from_raw((clock * 256 / (div + 256)) as u32)
+    }
+
+    /// Connect the given input to the ADC, see [Input multiplexer](self#input-multiplexer)
+    ///
+    /// If the ADC was connected to a different input, a conversion is run and
+    /// discarded, so the next conversion is not influenced by the previous input.
+    /// The one-shot reads of this driver do this automatically.
+    pub fn select_channel<PIN: AdcPin>(&mut self, _pin: &mut PIN) {
+        self.select_input(PIN::channel());
+    }
+
+    fn select_input(&mut self, chan: u8) {
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+        if self.device.cs.read().ainsel().bits() == chan {
+            return;
+        }
+
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+    }
+
+    /// Run a single conversion on the given channel
+    fn convert(&mut self, chan: u8) -> Result<u16, Error> {
+        self.select_input(chan);
+
+        self.device.cs.modify(|_, w| w.start_once().set_bit());
+
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        if self.device.cs.read().err().bit_is_set() {
+            return Err(Error::ConversionFailed);
+        }
+        Ok(self.device.result.read().result().bits())
+    }
+
+    /// Configure sampling into the ADC FIFO
+    ///
+    /// The returned builder selects the channel(s) to sample. Calling
+    /// [`AdcFifoBuilder::start`] starts free-running conversions into the FIFO.
+    pub fn build_fifo(&mut self) -> AdcFifoBuilder<'_> {
+        AdcFifoBuilder { adc: self }
+    }
+}
+
+/// Error of an ADC conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The ADC reported the conversion as failed
+    ///
+    /// This can happen if the input changes while it is being sampled. The
+    /// value of the conversion must not be used.
+    ConversionFailed,
+}
+
+/// Bit set in a raw FIFO sample if its conversion failed
+///
+/// Samples read by DMA, e.g. in the buffers of an [`AdcCapture`], are raw
+/// FIFO values: the 12 bit result plus this flag. Use [`check_sample`] to
+/// separate them.
+pub const SAMPLE_ERROR_FLAG: u16 = 1 << 15;
+
+/// Check a raw FIFO sample for the [`SAMPLE_ERROR_FLAG`] and extract its value
+pub fn check_sample(raw: u16) -> Result<u16, Error> {
+    if raw & SAMPLE_ERROR_FLAG != 0 {
+        Err(Error::ConversionFailed)
+    } else {
+        Ok(raw & 0xfff)
+    }
+}
+
+/// A set of ADC channels sampled one after the other in round-robin mode
+///
+/// It can be built from a single channel or from a tuple of references to up to
+/// five channels, e.g. `(&adc_pin_0, &adc_pin_1, &temperature_sensor)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoundRobin(u8);
+
+impl RoundRobin {
+    /// An empty set, which disables round-robin sampling
+    pub const fn empty() -> Self {
+        RoundRobin(0)
+    }
+
+    /// Add a channel to the set
+    pub fn add<PIN: AdcPin>(self, _pin: &PIN) -> Self {
+        RoundRobin(self.0 | (1 << PIN::channel()))
+    }
+
+    /// Check whether the channel with the given number is part of the set
+    pub fn contains(&self, channel: u8) -> bool {
+        channel < NUM_CHANNELS && self.0 & (1 << channel) != 0
+    }
+
+    /// Returns true if the set contains no channels
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The channel sampled after `channel`, wrapping around to the lowest channel of the set
+    ///
+    /// Returns `channel` if the set is empty.
+    fn next_after(&self, channel: u8) -> u8 {
+        (1..=NUM_CHANNELS)
+            .map(|offset| (channel + offset) % NUM_CHANNELS)
+            .find(|&c| self.contains(c))
+            .unwrap_or(channel)
+    }
+
+    /// The lowest channel of the set, if any
+    fn first(&self) -> Option<u8> {
+        (0..NUM_CHANNELS).find(|&c| self.contains(c))
+    }
+}
+
+impl<PIN: AdcPin> From<&PIN> for RoundRobin {
+    fn from(pin: &PIN) -> Self {
+        RoundRobin::empty().add(pin)
+    }
+}
+
+macro_rules! round_robin_from_tuple {
+    ($($pin:ident $name:ident),+) => {
+        impl<$($pin),+> From<($(&$pin),+)> for RoundRobin
+        where
+            $($pin: AdcPin),+
+        {
+            fn from(($($name),+): ($(&$pin),+)) -> Self {
+                RoundRobin::empty()$(.add($name))+
+            }
+        }
+    };
+}
+
+round_robin_from_tuple!(A a, B b);
+round_robin_from_tuple!(A a, B b, C c);
+round_robin_from_tuple!(A a, B b, C c, D d);
+round_robin_from_tuple!(A a, B b, C c, D d, E e);
+
+/// Builder for FIFO based sampling, created by [`Adc::build_fifo`]
+pub struct AdcFifoBuilder<'a> {
+    adc: &'a mut Adc,
+}
+
+impl<'a> AdcFifoBuilder<'a> {
+    /// Select the channel sampled first
+    ///
+    /// Without round-robin mode, this is the only channel sampled.
+    pub fn set_channel<PIN: AdcPin>(self, _pin: &mut PIN) -> Self {
+        self.adc
+            .device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(PIN::channel()) });
+        self
+    }
+
+    /// Sample the given channels one after the other
+    ///
+    /// After each conversion the ADC advances to the next channel of the set.
+    /// If the channel selected with [`set_channel`](Self::set_channel) is not
+    /// part of the set, sampling starts at the lowest channel of the set instead.
+    pub fn round_robin<T: Into<RoundRobin>>(self, channels: T) -> Self {
+        let RoundRobin(bits) = channels.into();
+        self.adc
+            .device
+            .cs
+            .modify(|_, w| unsafe { w.rrobin().bits(bits) });
+        self
+    }
+
+    /// Set the pacing divider for free-running conversions, see [`Adc::set_divider`]
+    pub fn clock_divider(self, int: u16, frac: u8) -> Self {
+        self.adc.set_divider(int, frac);
+        self
+    }
+
+    /// Set the rate of free-running conversions, see [`Adc::set_sample_rate`]
+    pub fn sample_rate(self, adc_clock_freq: HertzU32, rate: HertzU32) -> Self {
+        self.adc.set_sample_rate(adc_clock_freq, rate);
+        self
+    }
+
+    /// Enable the FIFO interrupt
+    ///
+    /// The interrupt is asserted while the FIFO holds at least `threshold`
+    /// samples (1 to 4). It is level triggered, so it is cleared by reading
+    /// samples from the FIFO until fewer than `threshold` remain.
+    pub fn enable_interrupt(self, threshold: u8) -> Self {
+        self.adc
+            .device
+            .fcs
+            .modify(|_, w| unsafe { w.thresh().bits(threshold) });
+        self.adc.device.inte.write(|w| w.fifo().set_bit());
+        self
+    }
+
+    /// Start capturing blocks of `N` samples into two buffers using DMA
+    ///
+    /// The first DMA channel fills the buffers in turn, and the second one
+    /// restarts it on the other buffer each time one is full: while one buffer
+    /// is filled, the other one can be processed. See [`AdcCapture`].
+    pub fn start_capture<CH1, CH2, const N: usize>(
+        self,
+        channels: (DmaChannel<CH1>, DmaChannel<CH2>),
+        buffers: [&'static mut [u16; N]; 2],
+    ) -> AdcCapture<'a, CH1, CH2, N>
+    where
+        CH1: ChannelIndex,
+        CH2: ChannelIndex,
+    {
+        let (ch1, ch2) = channels;
+        let fifo_addr = self.adc.device.fifo.as_ptr() as u32;
+        let config = ChannelConfig {
+            size: TransferSize::HalfWord,
+            incr_read: false,
+            incr_write: true,
+            treq: DREQ_ADC,
+            chain_to: None,
+            read_ring: 0,
+        };
+        // Safety: the buffers are owned by the capture and the FIFO register
+        // is valid for the lifetime of the program.
+        let mut dma = unsafe {
+            DoubleBuffer::new(
+                ch1,
+                ch2,
+                &config,
+                fifo_addr,
+                [buffers[0].as_ptr() as u32, buffers[1].as_ptr() as u32],
+                N as u32,
+            )
+        };
+        dma.start();
+
+        self.adc
+            .device
+            .fcs
+            .modify(|_, w| unsafe { w.dreq_en().set_bit().thresh().bits(1) });
+        AdcCapture {
+            fifo: self.start(),
+            dma,
+            buffers,
+        }
+    }
+
+    /// Enable the FIFO and start free-running conversions
+    pub fn start(self) -> AdcFifo<'a> {
+        let device = &self.adc.device;
+        let round_robin = RoundRobin(device.cs.read().rrobin().bits());
+        let mut channel = device.cs.read().ainsel().bits();
+        if let Some(first) = round_robin.first() {
+            if !round_robin.contains(channel) {
+                channel = first;
+                device.cs.modify(|_, w| unsafe { w.ainsel().bits(channel) });
+            }
+        }
+        device.fcs.modify(|_, w| w.en().set_bit().err().set_bit());
+        device.cs.modify(|_, w| w.start_many().set_bit());
+        AdcFifo {
+            adc: self.adc,
+            round_robin,
+            next_channel: channel,
+        }
+    }
+}
+
+/// Free-running ADC sampling into the FIFO, created by [`AdcFifoBuilder::start`]
+///
+/// The FIFO holds up to four samples. If it is not read fast enough, further
+/// samples are dropped and the overflow flag is raised. As the channel tags
+/// returned by [`read_tagged`](Self::read_tagged) are tracked in software,
+/// they are no longer accurate after an overflow.
+pub struct AdcFifo<'a> {
+    adc: &'a mut Adc,
+    round_robin: RoundRobin,
+    next_channel: u8,
+}
+
+impl<'a> AdcFifo<'a> {
+    /// Number of samples currently held in the FIFO
+    pub fn len(&self) -> u8 {
+        self.adc.device.fcs.read().level().bits()
+    }
+
+    /// Returns true if the FIFO contains no samples
+    pub fn is_empty(&self) -> bool {
+        self.adc.device.fcs.read().empty().bit_is_set()
+    }
+
+    /// Returns true if samples were lost because the FIFO was full
+    pub fn is_over(&self) -> bool {
+        self.adc.device.fcs.read().over().bit_is_set()
+    }
+
+    /// Clear the overflow flag
+    pub fn clear_over(&mut self) {
+        self.adc.device.fcs.modify(|_, w| w.over().set_bit());
+    }
+
+    /// Enable the FIFO interrupt, see [`AdcFifoBuilder::enable_interrupt`]
+    pub fn enable_interrupt(&mut self, threshold: u8) {
+        self.adc
+            .device
+            .fcs
+            .modify(|_, w| unsafe { w.thresh().bits(threshold) });
+        self.adc.device.inte.write(|w| w.fifo().set_bit());
+    }
+
+    /// Disable the FIFO interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.adc.device.inte.write(|w| w.fifo().clear_bit());
+    }
+
+    /// Returns true if the FIFO interrupt is enabled and asserted
+    ///
+    /// Read samples from the FIFO to clear it.
+    pub fn is_interrupt_pending(&self) -> bool {
+        self.adc.device.ints.read().fifo().bit_is_set()
+    }
+
+    /// Read the next sample from the FIFO
+    ///
+    /// Failed conversions are not reported, use [`read_checked`](Self::read_checked)
+    /// to detect them.
+    pub fn read(&mut self) -> Option<u16> {
+        self.read_tagged().map(|(_, value)| value)
+    }
+
+    /// Read the next sample from the FIFO, together with the channel it was taken from
+    ///
+    /// Failed conversions are not reported, use [`read_checked`](Self::read_checked)
+    /// to detect them.
+    pub fn read_tagged(&mut self) -> Option<(u8, u16)> {
+        self.pop()
+            .map(|(channel, raw)| (channel, raw & !SAMPLE_ERROR_FLAG))
+    }
+
+    /// Read the next sample from the FIFO, returning an error if its conversion failed
+    pub fn read_checked(&mut self) -> Option<Result<u16, Error>> {
+        self.pop().map(|(_, raw)| check_sample(raw))
+    }
+
+    fn pop(&mut self) -> Option<(u8, u16)> {
+        if self.is_empty() {
+            return None;
+        }
+        let raw = self.adc.device.fifo.read().bits() as u16;
+        let channel = self.next_channel;
+        self.next_channel = self.round_robin.next_after(channel);
+        Some((channel, raw))
+    }
+
+    /// Stop sampling, disable round-robin mode and empty the FIFO
+    pub fn stop(self) {
+        let device = &self.adc.device;
+        device.cs.modify(|_, w| w.start_many().clear_bit());
+        while !device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+        device.cs.modify(|_, w| unsafe { w.rrobin().bits(0) });
+        device.inte.write(|w| w.fifo().clear_bit());
+        device
+            .fcs
+            .modify(|_, w| w.en().clear_bit().dreq_en().clear_bit());
+        while device.fcs.read().empty().bit_is_clear() {
+            let _ = device.fifo.read();
+        }
+    }
+}
+
+/// Oversampling wrapper, averaging `2^N` conversions into a single result
+///
+/// By summing `4^b` samples and dividing by `2^b`, the resolution grows by `b`
+/// bits, as long as the input carries some noise. With `2^N` samples, results
+/// have a resolution of [`RESOLUTION_BITS`](Self::RESOLUTION_BITS), i.e.
+/// `12 + N / 2` bits. `N` must be even, so that `2^N` is a power of 4, and
+/// not larger than 16, which is checked at compile time:
+///
+/// ```compile_fail
+/// use rp2040_hal::{adc::{Adc, OversampledAdc}, pac};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+/// let oversampled = OversampledAdc::<3>::new(&mut adc);
+/// ```
+///
+/// The samples are taken back-to-back, so a result takes `2^N * 2µs` with
+/// the default 48MHz ADC clock.
+///
+/// ```no_run
+/// use rp2040_hal::{adc::{Adc, OversampledAdc}, gpio::Pins, pac, Sio};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let sio = Sio::new(peripherals.SIO);
+/// let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+/// let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+/// let mut adc_pin_0 = pins.gpio26.into_floating_input();
+/// // Average 256 samples into a 16 bit result
+/// let mut oversampled = OversampledAdc::<8>::new(&mut adc);
+/// let value: u32 = oversampled.read(&mut adc_pin_0).unwrap();
+/// ```
+pub struct OversampledAdc<'a, const N: u8> {
+    adc: &'a mut Adc,
+}
+
+impl<'a, const N: u8> OversampledAdc<'a, N> {
+    const VALID: () = assert!(
+        N <= 16 && N % 2 == 0,
+        "the number of samples must be a power of 4, at most 2^16"
+    );
+
+    /// Resolution of the results, in bits
+    pub const RESOLUTION_BITS: u8 = {
+        let () = Self::VALID;
+        12 + N / 2
+    };
+
+    /// Wrap the ADC
+    pub fn new(adc: &'a mut Adc) -> Self {
+        let () = Self::VALID;
+        Self { adc }
+    }
+
+    /// Release the ADC
+    pub fn free(self) -> &'a mut Adc {
+        self.adc
+    }
+
+    /// Take `2^N` samples of the given channel and return the decimated result
+    ///
+    /// Fails if any of the conversions failed.
+    pub fn read<PIN: AdcPin>(&mut self, pin: &mut PIN) -> Result<u32, Error> {
+        if PIN::channel() == TEMPERATURE_SENSOR_CHANNEL {
+            self.adc.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+        // Conversions are run back-to-back, the pacing divider is restored afterwards.
+        let div = self.adc.device.div.read();
+        let (int, frac) = (div.int().bits(), div.frac().bits());
+        self.adc.set_divider(0, 0);
+        self.adc.select_channel(pin);
+
+        let mut fifo = self.adc.build_fifo().set_channel(pin).start();
+        let mut sum = 0u32;
+        let mut failed = false;
+        let mut remaining = 1u32 << N;
+        while remaining > 0 {
+            if let Some(sample) = fifo.read_checked() {
+                match sample {
+                    Ok(value) => sum += u32::from(value),
+                    Err(_) => failed = true,
+                }
+                remaining -= 1;
+            }
+        }
+        fifo.stop();
+        self.adc.set_divider(int, frac);
+
+        if failed {
+            return Err(Error::ConversionFailed);
+        }
+        Ok(sum >> (N / 2))
+    }
+}
+
+/// Continuous capture of ADC samples into RAM, created by [`AdcFifoBuilder::start_capture`]
+///
+/// Samples are written to two buffers in turn. They are raw FIFO values,
+/// with bit 15 flagging failed conversions, see [`check_sample`]. [`wait`](Self::wait) returns
+/// each buffer once it is full. The buffer is refilled as soon as the other
+/// buffer is full, so it has to be processed within the time it takes to
+/// sample `N` values, otherwise its samples are overwritten by newer ones.
+/// The DMA channels never write outside of the buffers.
+///
+/// ```no_run
+/// use fugit::RateExtU32;
+/// use rp2040_hal::{adc::Adc, dma::DMAExt, gpio::Pins, pac, Sio};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let sio = Sio::new(peripherals.SIO);
+/// let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+/// let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+/// static mut BUFFERS: [[u16; 256]; 2] = [[0; 256]; 2];
+/// // Safety: the buffers are only used by the capture
+/// let [buf_a, buf_b] = unsafe { &mut BUFFERS };
+/// let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+/// let mut adc_pin_0 = pins.gpio26.into_floating_input();
+/// // Sample at 8kHz
+/// let mut capture = adc
+///     .build_fifo()
+///     .set_channel(&mut adc_pin_0)
+///     .sample_rate(48.MHz(), 8.kHz())
+///     .start_capture((dma.ch0, dma.ch1), [buf_a, buf_b]);
+/// loop {
+///     let samples = capture.wait();
+///     // process the samples
+/// }
+/// ```
+pub struct AdcCapture<'a, CH1: ChannelIndex, CH2: ChannelIndex, const N: usize> {
+    fifo: AdcFifo<'a>,
+    dma: DoubleBuffer<CH1, CH2>,
+    buffers: [&'static mut [u16; N]; 2],
 }
 
+impl<'a, CH1: ChannelIndex, CH2: ChannelIndex, const N: usize> AdcCapture<'a, CH1, CH2, N> {
+    /// Returns true if a buffer was filled since the last call to [`wait`](Self::wait)
+    pub fn is_ready(&self) -> bool {
+        self.dma.is_ready()
+    }
+
+    /// Wait for the next full buffer and return its samples
+    ///
+    /// If more than one buffer was filled since the last call, this returns
+    /// the most recent one.
+    pub fn wait(&mut self) -> &[u16; N] {
+        let index = self.dma.wait();
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        &*self.buffers[index]
+    }
+
+    /// Stop capturing and return the DMA channels and buffers
+    #[allow(clippy::type_complexity)]
+    pub fn stop(
+        self,
+    ) -> (
+        (DmaChannel<CH1>, DmaChannel<CH2>),
+        [&'static mut [u16; N]; 2],
+    ) {
+        let channels = self.dma.free();
+        self.fifo.stop();
+        (channels, self.buffers)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// An input of the ADC
+///
+/// This is implemented for the pins GPIO26 to GPIO29 when configured as
+/// floating input, or disabled with neither pull-up nor pull-down, and for
+/// the temperature sensor. Disabled pins are preferred for analog signals, as
+/// their digital input buffer is turned off.
+///
+/// The channel number is derived from the type through
+/// [`Channel::channel`], so only valid inputs can be passed to the ADC.
+pub trait AdcPin: Channel<Adc, ID = u8> + private::Sealed {}
+
 macro_rules! channel {
     ($pin:ident, $channel:expr) => {
-        impl Channel<Adc> for Pin<$pin, FloatingInput> {
+        channel!($pin, FloatingInput, $channel);
+        channel!($pin, FloatingDisabled, $channel);
+    };
+    ($pin:ident, $mode:ident, $channel:expr) => {
+        impl Channel<Adc> for Pin<$pin, $mode> {
             type ID = u8; // ADC channels are identified numerically
 
             fn channel() -> u8 {
                 $channel
             }
         }
+
+        impl private::Sealed for Pin<$pin, $mode> {}
+        impl AdcPin for Pin<$pin, $mode> {}
     };
 }
 
@@ -116,6 +811,19 @@ pub struct TempSense {
     __private: (),
 }
 
+impl TempSense {
+    /// Convert a reading of the temperature sensor to degrees Celsius
+    ///
+    /// Uses the formula given in the datasheet, `T = 27 - (V - 0.706) / 0.001721`,
+    /// where `V` is the sensor voltage calculated from `counts` and the ADC
+    /// reference voltage `vref`, in volts. The sensor is not calibrated, so
+    /// expect an offset of a few degrees.
+    pub fn counts_to_celsius(counts: u16, vref: f32) -> f32 {
+        let voltage = f32::from(counts) * vref / 4096.0;
+        27.0 - (voltage - 0.706) / 0.001721
+    }
+}
+
 impl Channel<Adc> for TempSense {
     type ID = u8; // ADC channels are identified numerically
 
@@ -124,32 +832,107 @@ impl Channel<Adc> for TempSense {
     }
 }
 
+impl private::Sealed for TempSense {}
+impl AdcPin for TempSense {}
+
 impl<WORD, PIN> OneShot<Adc, WORD, PIN> for Adc
 where
     WORD: From<u16>,
-    PIN: Channel<Adc, ID = u8>,
+    PIN: AdcPin,
 {
-    type Error = ();
+    type Error = Error;
 
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
         let chan = PIN::channel();
 
-        if chan == 4 {
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
             self.device.cs.modify(|_, w| w.ts_en().set_bit())
         }
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
+        Ok(self.convert(chan)?.into())
+    }
+}
+
+#[cfg(feature = "async")]
+static WAKER: crate::async_utils::WakerSlot = crate::async_utils::WakerSlot::new();
+
+#[cfg(feature = "async")]
+impl Adc {
+    /// Run a single conversion, waiting for its completion without polling
+    ///
+    /// The conversion result is passed through the FIFO, whose interrupt wakes the
+    /// waiting task. This requires [`Adc::on_interrupt`] to be called from the
+    /// `ADC_IRQ_FIFO` interrupt handler, and the interrupt to be unmasked in the NVIC.
+    pub async fn read_async<PIN: AdcPin>(&mut self, _pin: &mut PIN) -> Result<u16, Error> {
+        let chan = PIN::channel();
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
         }
 
+        self.select_input(chan);
+        while self.device.fcs.read().empty().bit_is_clear() {
+            let _ = self.device.fifo.read();
+        }
         self.device
-            .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
+            .fcs
+            .modify(|_, w| unsafe { w.en().set_bit().err().set_bit().thresh().bits(1) });
+        self.device.cs.modify(|_, w| w.start_once().set_bit());
 
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
+        Conversion { adc: self }.await
+    }
+
+    /// Wake the task waiting in [`Adc::read_async`]
+    ///
+    /// Call this from the `ADC_IRQ_FIFO` interrupt handler. As the interrupt is
+    /// level triggered, it is disabled here and re-enabled when the waiting
+    /// task is polled.
+    pub fn on_interrupt() {
+        // Safety: only the interrupt enable register is written, which the ADC
+        // driver takes care to keep consistent.
+        let device = unsafe { &*ADC::ptr() };
+        device.inte.write(|w| w.fifo().clear_bit());
+        WAKER.wake();
+    }
+}
+
+/// Future of a single conversion started by [`Adc::read_async`]
+#[cfg(feature = "async")]
+struct Conversion<'a> {
+    adc: &'a mut Adc,
+}
+
+#[cfg(feature = "async")]
+impl<'a> core::future::Future for Conversion<'a> {
+    type Output = Result<u16, Error>;
+
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Self::Output> {
+        let device = &self.adc.device;
+        if device.fcs.read().empty().bit_is_set() {
+            WAKER.register(cx.waker());
+            device.inte.write(|w| w.fifo().set_bit());
+            // The conversion may have completed before the interrupt was enabled,
+            // in which case the interrupt fires immediately and wakes the task.
+            return core::task::Poll::Pending;
         }
+        let raw = device.fifo.read().bits() as u16;
+        core::task::Poll::Ready(check_sample(raw))
+    }
+}
 
-        Ok(self.device.result.read().result().bits().into())
+#[cfg(feature = "async")]
+impl<'a> Drop for Conversion<'a> {
+    fn drop(&mut self) {
+        let device = &self.adc.device;
+        device.inte.write(|w| w.fifo().clear_bit());
+        while !device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+        device.fcs.modify(|_, w| w.en().clear_bit());
+        while device.fcs.read().empty().bit_is_clear() {
+            let _ = device.fifo.read();
+        }
     }
 }
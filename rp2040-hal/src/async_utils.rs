@@ -0,0 +1,56 @@
+//! Helpers shared by the drivers supporting the `async` feature
+use core::cell::RefCell;
+use core::task::{Poll, Waker};
+use critical_section::Mutex;
+
+/// Storage for the waker of a task waiting for an interrupt
+///
+/// The driver registers the waker when its future is polled, the interrupt
+/// handler wakes it.
+pub(crate) struct WakerSlot(Mutex<RefCell<Option<Waker>>>);
+
+impl WakerSlot {
+    pub(crate) const fn new() -> Self {
+        WakerSlot(Mutex::new(RefCell::new(None)))
+    }
+
+    /// Store `waker`, replacing any previously registered waker
+    pub(crate) fn register(&self, waker: &Waker) {
+        critical_section::with(|cs| {
+            let mut slot = self.0.borrow(cs).borrow_mut();
+            match &*slot {
+                Some(current) if current.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wake the registered task, if any
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = critical_section::with(|cs| self.0.borrow(cs).borrow_mut().take()) {
+            waker.wake();
+        }
+    }
+}
+
+/// Wait until `ready` returns true
+///
+/// While it doesn't, the waker is stored in `slot` and `enable` is called to unmask
+/// the interrupt which wakes it. The interrupt handler is expected to mask it again.
+/// As the condition is checked before the interrupt is unmasked, a level triggered
+/// interrupt whose condition became true meanwhile fires immediately.
+pub(crate) async fn wait_for(
+    slot: &WakerSlot,
+    mut ready: impl FnMut() -> bool,
+    mut enable: impl FnMut(),
+) {
+    core::future::poll_fn(|cx| {
+        if ready() {
+            return Poll::Ready(());
+        }
+        slot.register(cx.waker());
+        enable();
+        Poll::Pending
+    })
+    .await
+}
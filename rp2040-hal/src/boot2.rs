@@ -0,0 +1,60 @@
+//! Second stage bootloaders
+//!
+//! The first 256 bytes of the flash hold the second stage bootloader, which sets up the QSPI
+//! interface for the flash chip of the board before jumping to the application. This module
+//! re-exports the bootloaders of the [rp2040-boot2](https://docs.rs/rp2040-boot2) crate, one
+//! constant per flash chip, and with one of the `boot2-*` features, provides the one of that
+//! chip as [`BOOT2`]:
+//!
+//! | Feature             | Flash chip                               |
+//! |---------------------|------------------------------------------|
+//! | `boot2-w25q080`     | Winbond W25Q080, e.g. on the Pico        |
+//! | `boot2-w25x10cl`    | Winbond W25X10CL                         |
+//! | `boot2-gd25q64cs`   | GigaDevice GD25Q64CS                     |
+//! | `boot2-at25sf128a`  | Adesto AT25SF128A                        |
+//! | `boot2-is25lp080`   | ISSI IS25LP080                           |
+//! | `boot2-generic-03h` | Any chip, with the slow 03h read command |
+//! | `boot2-ram-memcpy`  | Any chip, copying the program to RAM     |
+//!
+//! The application places it in the `.boot2` section:
+//!
+//! ```ignore
+//! #[link_section = ".boot2"]
+//! #[used]
+//! pub static BOOT2: [u8; 256] = rp2040_hal::boot2::BOOT2;
+//! ```
+//!
+//! Enabling more than one of the `boot2-*` features is an error, as `BOOT2` is then defined
+//! more than once. Without a bootloader matching the flash chip, `boot2-generic-03h` still
+//! boots, and the clock divider and read command can then be tuned at runtime with the
+//! [`ssi`](crate::ssi) module.
+
+pub use rp2040_boot2::*;
+
+/// The bootloader selected by the `boot2-w25q080` feature
+#[cfg(feature = "boot2-w25q080")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_W25Q080;
+
+/// The bootloader selected by the `boot2-w25x10cl` feature
+#[cfg(feature = "boot2-w25x10cl")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_W25X10CL;
+
+/// The bootloader selected by the `boot2-gd25q64cs` feature
+#[cfg(feature = "boot2-gd25q64cs")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_GD25Q64CS;
+
+/// The bootloader selected by the `boot2-at25sf128a` feature
+#[cfg(feature = "boot2-at25sf128a")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_AT25SF128A;
+
+/// The bootloader selected by the `boot2-is25lp080` feature
+#[cfg(feature = "boot2-is25lp080")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_IS25LP080;
+
+/// The bootloader selected by the `boot2-generic-03h` feature
+#[cfg(feature = "boot2-generic-03h")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_GENERIC_03H;
+
+/// The bootloader selected by the `boot2-ram-memcpy` feature
+#[cfg(feature = "boot2-ram-memcpy")]
+pub const BOOT2: [u8; 256] = BOOT_LOADER_RAM_MEMCPY;
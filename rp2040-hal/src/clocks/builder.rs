@@ -0,0 +1,398 @@
+//! Custom clock configurations
+
+use super::*;
+use crate::pll::PLLConfig;
+
+/// Source of a clock configured with [`ClocksBuilder`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SourceSelect {
+    /// PLL_SYS, which has to be enabled with [`ClocksBuilder::pll_sys`]
+    PllSys,
+    /// PLL_USB, which has to be enabled with [`ClocksBuilder::pll_usb`]
+    PllUsb,
+    /// The crystal oscillator
+    Xosc,
+    /// The clock input on GPIO20, which has to be provided with [`ClocksBuilder::gpin0`]
+    GPin0,
+    /// The clock input on GPIO22, which has to be provided with [`ClocksBuilder::gpin1`]
+    GPin1,
+}
+
+/// Builder for a custom configuration of the oscillators, PLLs and clocks
+///
+/// [`ClocksBuilder::new`] starts from the configuration used by [`init_clocks_and_plls`],
+/// which can then be changed clock by clock.
+///
+/// The configuration is checked before any hardware is touched: each clock's source has to be
+/// enabled, and can't run slower than the clock itself. The order in which the clocks are
+/// switched over, which avoids glitches and overspeed, is handled by [`build`](Self::build).
+///
+/// ```no_run
+/// use fugit::RateExtU32;
+/// use rp2040_hal::{clocks::{ClocksBuilder, SourceSelect}, pac, watchdog::Watchdog};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+/// // Run everything from PLL_USB at 48MHz, leaving PLL_SYS off to save power
+/// let clocks = ClocksBuilder::new(12.MHz())
+///     .pll_sys(None)
+///     .system_clock(SourceSelect::PllUsb, 48.MHz())
+///     .build(
+///         peripherals.XOSC,
+///         peripherals.CLOCKS,
+///         peripherals.PLL_SYS,
+///         peripherals.PLL_USB,
+///         &mut peripherals.RESETS,
+///         &mut watchdog,
+///     )
+///     .ok()
+///     .unwrap();
+/// ```
+///
+/// Boards without a crystal can run from an external clock on a clock input instead. The PLLs
+/// need the crystal oscillator as their reference, so they stay off:
+///
+/// ```no_run
+/// use fugit::RateExtU32;
+/// use rp2040_hal::{clocks::{ClocksBuilder, GPin0, SourceSelect}, gpio::Pins, pac, Sio, watchdog::Watchdog};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+/// let sio = Sio::new(peripherals.SIO);
+/// let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+/// // A 24MHz TCXO on GPIO20
+/// let gpin0 = GPin0::new(pins.gpio20.into_mode(), 24.MHz());
+/// let clocks = ClocksBuilder::new(12.MHz())
+///     .xosc_enabled(false)
+///     .pll_sys(None)
+///     .pll_usb(None)
+///     .gpin0(gpin0)
+///     .reference_clock(SourceSelect::GPin0, 12.MHz())
+///     .system_clock(SourceSelect::GPin0, 24.MHz())
+///     .usb_clock(None)
+///     .adc_clock(None)
+///     .rtc_clock(Some((SourceSelect::GPin0, 46875.Hz())))
+///     .build(
+///         peripherals.XOSC,
+///         peripherals.CLOCKS,
+///         peripherals.PLL_SYS,
+///         peripherals.PLL_USB,
+///         &mut peripherals.RESETS,
+///         &mut watchdog,
+///     )
+///     .ok()
+///     .unwrap();
+/// ```
+pub struct ClocksBuilder {
+    xosc_freq: HertzU32,
+    xosc_enabled: bool,
+    xosc_delay_multiplier: u32,
+    pll_sys: Option<PLLConfig>,
+    pll_usb: Option<PLLConfig>,
+    gpin0: Option<GPin0>,
+    gpin1: Option<GPin1>,
+    reference: (SourceSelect, HertzU32),
+    system: (SourceSelect, HertzU32),
+    peripheral: Option<SourceSelect>,
+    usb: Option<(SourceSelect, HertzU32)>,
+    adc: Option<(SourceSelect, HertzU32)>,
+    rtc: Option<(SourceSelect, HertzU32)>,
+}
+
+impl ClocksBuilder {
+    /// Start from the default configuration, with a crystal of frequency `xosc_freq`
+    ///
+    /// - PLL_SYS at 125MHz, PLL_USB at 48MHz
+    /// - clk_ref from the crystal oscillator at its full frequency
+    /// - clk_sys from PLL_SYS at 125MHz, clk_peri from clk_sys
+    /// - clk_usb and clk_adc from PLL_USB at 48MHz
+    /// - clk_rtc from PLL_USB at 46875Hz
+    pub fn new(xosc_freq: HertzU32) -> Self {
+        Self {
+            xosc_freq,
+            xosc_enabled: true,
+            xosc_delay_multiplier: 1,
+            pll_sys: Some(PLL_SYS_125MHZ),
+            pll_usb: Some(PLL_USB_48MHZ),
+            gpin0: None,
+            gpin1: None,
+            reference: (SourceSelect::Xosc, xosc_freq),
+            system: (SourceSelect::PllSys, 125.MHz()),
+            peripheral: None,
+            usb: Some((SourceSelect::PllUsb, 48.MHz())),
+            adc: Some((SourceSelect::PllUsb, 48.MHz())),
+            rtc: Some((SourceSelect::PllUsb, 46875.Hz())),
+        }
+    }
+
+    /// Start the crystal oscillator, the default, or leave it off
+    ///
+    /// Without the crystal oscillator, the PLLs have to stay off too.
+    pub fn xosc_enabled(mut self, enabled: bool) -> Self {
+        self.xosc_enabled = enabled;
+        self
+    }
+
+    /// Make the startup delay of the crystal oscillator `multiplier` times longer than 1ms
+    ///
+    /// A `multiplier` of 0 makes [`build`](Self::build) fail with
+    /// `InitError::XoscErr(BadArgument)`.
+    pub fn xosc_startup_delay_multiplier(mut self, multiplier: u32) -> Self {
+        self.xosc_delay_multiplier = multiplier;
+        self
+    }
+
+    /// Configure PLL_SYS, or leave it off with `None`
+    pub fn pll_sys(mut self, config: Option<PLLConfig>) -> Self {
+        self.pll_sys = config;
+        self
+    }
+
+    /// Configure PLL_USB, or leave it off with `None`
+    pub fn pll_usb(mut self, config: Option<PLLConfig>) -> Self {
+        self.pll_usb = config;
+        self
+    }
+
+    /// Provide the clock input GPIN0, to be used as a source
+    ///
+    /// The pin stays a clock input once the clocks are configured.
+    pub fn gpin0(mut self, gpin: GPin0) -> Self {
+        self.gpin0 = Some(gpin);
+        self
+    }
+
+    /// Provide the clock input GPIN1, to be used as a source
+    ///
+    /// The pin stays a clock input once the clocks are configured.
+    pub fn gpin1(mut self, gpin: GPin1) -> Self {
+        self.gpin1 = Some(gpin);
+        self
+    }
+
+    /// Run clk_ref from `src` at `freq`
+    ///
+    /// clk_ref can't run from PLL_SYS. The watchdog tick is derived from it, so `freq` should
+    /// be a whole number of MHz.
+    pub fn reference_clock(mut self, src: SourceSelect, freq: HertzU32) -> Self {
+        self.reference = (src, freq);
+        self
+    }
+
+    /// Run clk_sys from `src` at `freq`
+    pub fn system_clock(mut self, src: SourceSelect, freq: HertzU32) -> Self {
+        self.system = (src, freq);
+        self
+    }
+
+    /// Run clk_peri from `src` at its full frequency, or from clk_sys with `None`
+    pub fn peripheral_clock(mut self, src: Option<SourceSelect>) -> Self {
+        self.peripheral = src;
+        self
+    }
+
+    /// Run clk_usb from a source at a frequency, or leave it off with `None`
+    ///
+    /// The USB controller needs exactly 48MHz.
+    pub fn usb_clock(mut self, config: Option<(SourceSelect, HertzU32)>) -> Self {
+        self.usb = config;
+        self
+    }
+
+    /// Run clk_adc from a source at a frequency, or leave it off with `None`
+    ///
+    /// The ADC needs 48MHz for its nominal sample rate.
+    pub fn adc_clock(mut self, config: Option<(SourceSelect, HertzU32)>) -> Self {
+        self.adc = config;
+        self
+    }
+
+    /// Run clk_rtc from a source at a frequency, or leave it off with `None`
+    pub fn rtc_clock(mut self, config: Option<(SourceSelect, HertzU32)>) -> Self {
+        self.rtc = config;
+        self
+    }
+
+    fn source_freq(&self, src: SourceSelect) -> Result<HertzU32, ClockError> {
+        let pll_freq = |config: &Option<PLLConfig>| {
+            config
+                .as_ref()
+                .map(|c| c.vco_freq / (c.post_div1 as u32 * c.post_div2 as u32))
+                .ok_or(ClockError::SourceNotConfigured)
+        };
+        match src {
+            SourceSelect::PllSys => pll_freq(&self.pll_sys),
+            SourceSelect::PllUsb => pll_freq(&self.pll_usb),
+            SourceSelect::Xosc if self.xosc_enabled => Ok(self.xosc_freq),
+            SourceSelect::Xosc => Err(ClockError::SourceNotConfigured),
+            SourceSelect::GPin0 => self
+                .gpin0
+                .as_ref()
+                .map(ClockSource::get_freq)
+                .ok_or(ClockError::SourceNotConfigured),
+            SourceSelect::GPin1 => self
+                .gpin1
+                .as_ref()
+                .map(ClockSource::get_freq)
+                .ok_or(ClockError::SourceNotConfigured),
+        }
+    }
+
+    /// Check the configuration without touching the hardware
+    pub fn validate(&self) -> Result<(), ClockError> {
+        if !self.xosc_enabled && (self.pll_sys.is_some() || self.pll_usb.is_some()) {
+            return Err(ClockError::SourceNotConfigured);
+        }
+        if self.reference.0 == SourceSelect::PllSys {
+            return Err(ClockError::UnsupportedSource);
+        }
+        let clocks = [
+            Some(self.reference),
+            Some(self.system),
+            self.usb,
+            self.adc,
+            self.rtc,
+        ];
+        for (src, freq) in clocks.iter().flatten() {
+            if *freq > self.source_freq(*src)? {
+                return Err(ClockError::CantIncreaseFreq);
+            }
+        }
+        if let Some(src) = self.peripheral {
+            self.source_freq(src)?;
+        }
+        Ok(())
+    }
+
+    /// Start the oscillator and PLLs, and configure the clocks
+    ///
+    /// This also starts the watchdog tick from clk_ref, like [`init_clocks_and_plls`].
+    pub fn build(
+        self,
+        xosc_dev: XOSC,
+        clocks_dev: CLOCKS,
+        pll_sys_dev: PLL_SYS,
+        pll_usb_dev: PLL_USB,
+        resets: &mut RESETS,
+        watchdog: &mut Watchdog,
+    ) -> Result<ClocksManager, InitError> {
+        self.validate().map_err(InitError::ClockError)?;
+
+        let xosc = if self.xosc_enabled {
+            Some(
+                setup_xosc_blocking_with_delay_multiplier(
+                    xosc_dev,
+                    self.xosc_freq,
+                    self.xosc_delay_multiplier,
+                )
+                .map_err(InitError::XoscErr)?,
+            )
+        } else {
+            None
+        };
+
+        // Configure watchdog tick generation to tick over every microsecond
+        watchdog.enable_tick_generation(self.reference.1.to_MHz() as u8);
+
+        let mut clocks = ClocksManager::new(clocks_dev);
+
+        // The PLLs are only configured with the XOSC enabled, which validate checked
+        let pll_sys = match (self.pll_sys, &xosc) {
+            (Some(config), Some(xosc)) => Some(
+                setup_pll_blocking(
+                    pll_sys_dev,
+                    xosc.operating_frequency(),
+                    config,
+                    &mut clocks,
+                    resets,
+                )
+                .map_err(InitError::PllError)?,
+            ),
+            _ => None,
+        };
+        let pll_usb = match (self.pll_usb, &xosc) {
+            (Some(config), Some(xosc)) => Some(
+                setup_pll_blocking(
+                    pll_usb_dev,
+                    xosc.operating_frequency(),
+                    config,
+                    &mut clocks,
+                    resets,
+                )
+                .map_err(InitError::PllError)?,
+            ),
+            _ => None,
+        };
+
+        self.configure_clocks(
+            &mut clocks,
+            xosc.as_ref(),
+            pll_sys.as_ref(),
+            pll_usb.as_ref(),
+        )
+        .map_err(InitError::ClockError)?;
+
+        Ok(clocks)
+    }
+
+    fn configure_clocks(
+        &self,
+        clocks: &mut ClocksManager,
+        xosc: Option<&Xosc>,
+        pll_sys: Option<&PllSys>,
+        pll_usb: Option<&PllUsb>,
+    ) -> Result<(), ClockError> {
+        let xosc = xosc.ok_or(ClockError::SourceNotConfigured);
+        let pll_sys = pll_sys.ok_or(ClockError::SourceNotConfigured);
+        let pll_usb = pll_usb.ok_or(ClockError::SourceNotConfigured);
+        let gpin0 = self.gpin0.as_ref().ok_or(ClockError::SourceNotConfigured);
+        let gpin1 = self.gpin1.as_ref().ok_or(ClockError::SourceNotConfigured);
+
+        macro_rules! configure {
+            ($clock:expr, $src:expr, $freq:expr) => {
+                match $src {
+                    SourceSelect::PllSys => $clock.configure_clock(pll_sys?, $freq),
+                    SourceSelect::PllUsb => $clock.configure_clock(pll_usb?, $freq),
+                    SourceSelect::Xosc => $clock.configure_clock(xosc?, $freq),
+                    SourceSelect::GPin0 => $clock.configure_clock(gpin0?, $freq),
+                    SourceSelect::GPin1 => $clock.configure_clock(gpin1?, $freq),
+                }
+            };
+        }
+
+        // clk_ref can't run from PLL_SYS, so it doesn't go through the macro
+        let (src, freq) = self.reference;
+        match src {
+            SourceSelect::PllSys => Err(ClockError::UnsupportedSource),
+            SourceSelect::PllUsb => clocks.reference_clock.configure_clock(pll_usb?, freq),
+            SourceSelect::Xosc => clocks.reference_clock.configure_clock(xosc?, freq),
+            SourceSelect::GPin0 => clocks.reference_clock.configure_clock(gpin0?, freq),
+            SourceSelect::GPin1 => clocks.reference_clock.configure_clock(gpin1?, freq),
+        }?;
+
+        let (src, freq) = self.system;
+        configure!(clocks.system_clock, src, freq)?;
+
+        if let Some((src, freq)) = self.usb {
+            configure!(clocks.usb_clock, src, freq)?;
+        }
+        if let Some((src, freq)) = self.adc {
+            configure!(clocks.adc_clock, src, freq)?;
+        }
+        if let Some((src, freq)) = self.rtc {
+            configure!(clocks.rtc_clock, src, freq)?;
+        }
+
+        match self.peripheral {
+            Some(src) => {
+                let freq = self.source_freq(src)?;
+                configure!(clocks.peripheral_clock, src, freq)
+            }
+            None => {
+                let freq = clocks.system_clock.freq();
+                clocks
+                    .peripheral_clock
+                    .configure_clock(&clocks.system_clock, freq)
+            }
+        }
+    }
+}
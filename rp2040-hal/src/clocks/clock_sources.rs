@@ -76,18 +76,37 @@ impl ClockSource for RingOscillator<Enabled> {
     }
 }
 
-// GPIN0
-pub(crate) type GPin0 = Pin<Gpio20, FunctionClock>;
-impl ClockSource for GPin0 {
-    fn get_freq(&self) -> HertzU32 {
-        todo!()
-    }
-}
+macro_rules! gpin {
+    ($name:ident, $pin:ident, $doc:literal) => {
+        #[doc = $doc]
+        ///
+        /// The frequency of the external clock has to be provided, as it can't be measured.
+        pub struct $name {
+            pin: Pin<$pin, FunctionClock>,
+            frequency: HertzU32,
+        }
 
-// GPIN1
-pub(crate) type GPin1 = Pin<Gpio22, FunctionClock>;
-impl ClockSource for Pin<Gpio22, FunctionClock> {
-    fn get_freq(&self) -> HertzU32 {
-        todo!()
-    }
+        impl $name {
+            /// Use `pin` as a clock input, driven by an external clock running at `frequency`
+            pub fn new(pin: Pin<$pin, FunctionClock>, frequency: HertzU32) -> Self {
+                Self { pin, frequency }
+            }
+
+            /// Release the pin
+            pub fn free(self) -> Pin<$pin, FunctionClock> {
+                self.pin
+            }
+        }
+
+        impl Sealed for $name {}
+
+        impl ClockSource for $name {
+            fn get_freq(&self) -> HertzU32 {
+                self.frequency
+            }
+        }
+    };
 }
+
+gpin!(GPin0, Gpio20, "Clock input GPIN0, on GPIO20");
+gpin!(GPin1, Gpio22, "Clock input GPIN1, on GPIO22");
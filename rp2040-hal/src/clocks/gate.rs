@@ -0,0 +1,109 @@
+//! Gating the clocks of individual peripherals
+
+use super::*;
+use crate::typelevel::Sealed;
+use pac::{
+    ADC, DMA, I2C0, I2C1, PIO0, PIO1, PWM, RTC, SPI0, SPI1, TIMER, UART0, UART1, USBCTRL_REGS,
+};
+
+/// A peripheral whose clocks can be gated with [`ClocksManager::disable_clock`]
+///
+/// This is implemented by the PAC peripherals: as the drivers take ownership of them, and
+/// gating takes ownership too, a peripheral which is still in use by a driver can't be gated.
+pub trait GatedPeripheral: Sealed {
+    /// Bits of the peripheral's clocks in WAKE_EN0 and SLEEP_EN0
+    const EN0: u32;
+    /// Bits of the peripheral's clocks in WAKE_EN1 and SLEEP_EN1
+    const EN1: u32;
+}
+
+macro_rules! gated_peripheral {
+    ($($peripheral:ident: ($en0:expr, $en1:expr),)+) => {
+        $(
+            impl GatedPeripheral for $peripheral {
+                const EN0: u32 = $en0;
+                const EN1: u32 = $en1;
+            }
+        )+
+    };
+}
+
+// PIO0 and PIO1 are sealed by the GPIO functions already
+impl Sealed for ADC {}
+impl Sealed for DMA {}
+impl Sealed for I2C0 {}
+impl Sealed for I2C1 {}
+impl Sealed for PWM {}
+impl Sealed for RTC {}
+impl Sealed for SPI0 {}
+impl Sealed for SPI1 {}
+impl Sealed for TIMER {}
+impl Sealed for UART0 {}
+impl Sealed for UART1 {}
+impl Sealed for USBCTRL_REGS {}
+
+// The bits of the register interface on clk_sys, and of the peripheral's own clock if any
+gated_peripheral! {
+    ADC: ((1 << 1) | (1 << 2), 0),
+    DMA: (1 << 5, 0),
+    I2C0: (1 << 6, 0),
+    I2C1: (1 << 7, 0),
+    PIO0: (1 << 12, 0),
+    PIO1: (1 << 13, 0),
+    PWM: (1 << 17, 0),
+    RTC: ((1 << 21) | (1 << 22), 0),
+    SPI0: ((1 << 24) | (1 << 25), 0),
+    SPI1: ((1 << 26) | (1 << 27), 0),
+    TIMER: (0, 1 << 5),
+    UART0: (0, (1 << 6) | (1 << 7)),
+    UART1: (0, (1 << 8) | (1 << 9)),
+    USBCTRL_REGS: (0, (1 << 10) | (1 << 11)),
+}
+
+/// A peripheral whose clocks were stopped with [`ClocksManager::disable_clock`]
+///
+/// The peripheral is only given back by [`ClocksManager::enable_clock`], so it can't be
+/// passed to a driver while its clocks are stopped.
+pub struct Gated<P: GatedPeripheral> {
+    peripheral: P,
+}
+
+impl ClocksManager {
+    /// Stop the clocks of an unused peripheral, to save power
+    ///
+    /// Accessing the registers of the peripheral stalls until its clocks are enabled again, so
+    /// it is kept in the returned [`Gated`] until then: while a driver owns it, its clocks
+    /// can't be stopped, and while its clocks are stopped, no driver can own it.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{clocks::ClocksManager, pac};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+    /// // The ADC and USB aren't used by this application
+    /// let adc = clocks.disable_clock(peripherals.ADC);
+    /// let usb = clocks.disable_clock(peripherals.USBCTRL_REGS);
+    /// // Until the ADC is needed after all
+    /// let adc = clocks.enable_clock(adc);
+    /// ```
+    pub fn disable_clock<P: GatedPeripheral>(&mut self, peripheral: P) -> Gated<P> {
+        self.clocks
+            .wake_en0
+            .modify(|r, w| unsafe { w.bits(r.bits() & !P::EN0) });
+        self.clocks
+            .wake_en1
+            .modify(|r, w| unsafe { w.bits(r.bits() & !P::EN1) });
+        Gated { peripheral }
+    }
+
+    /// Restart the clocks of a peripheral stopped with [`disable_clock`](Self::disable_clock),
+    /// and give it back
+    pub fn enable_clock<P: GatedPeripheral>(&mut self, gated: Gated<P>) -> P {
+        self.clocks
+            .wake_en0
+            .modify(|r, w| unsafe { w.bits(r.bits() | P::EN0) });
+        self.clocks
+            .wake_en1
+            .modify(|r, w| unsafe { w.bits(r.bits() | P::EN1) });
+        gated.peripheral
+    }
+}
@@ -32,7 +32,7 @@ macro_rules! clocks {
                     }
 
                     let shared_clocks = ShareableClocks::new(&mut clocks_block);
-                    ClocksManager {
+                    let manager = ClocksManager {
                         clocks: clocks_block,
                         $(
                             [<$name:snake>]: $name {
@@ -40,7 +40,10 @@ macro_rules! clocks {
                                 frequency: $init_freq.Hz(),
                             },
                         )*
-                    }
+                    };
+                    REFERENCE_CLOCK_FREQ.store(manager.reference_clock.frequency.to_Hz(), Ordering::Relaxed);
+                    SYSTEM_CLOCK_FREQ.store(manager.system_clock.frequency.to_Hz(), Ordering::Relaxed);
+                    manager
                 }
             }
         }
@@ -140,8 +143,10 @@ macro_rules! clock {
                         w.src().variant(self.get_default_clock_source())
                     });
 
+                    // The divider stays in place while running from the default source
                     use fugit::RateExtU32;
-                    self.frequency = 12.MHz(); //TODO Get actual clock source.. Most likely 12 MHz though
+                    let src_freq = self.get_default_source_freq().to_Hz();
+                    self.set_frequency(fractional_div(src_freq, self.get_div()).unwrap_or(src_freq).Hz());
 
                     self.await_select(&ChangingClockToken{clock_nr:0, clock: PhantomData::<Self>})
                 }
@@ -219,7 +224,7 @@ macro_rules! clock {
 
                     // Store the configured frequency
                     use fugit::RateExtU32;
-                    self.frequency = fractional_div(src_freq.to_Hz(), div).ok_or(ClockError::FrequencyTooHigh)?.Hz();
+                    self.set_frequency(fractional_div(src_freq.to_Hz(), div).ok_or(ClockError::FrequencyTooHigh)?.Hz());
 
                     Ok(())
                 }
@@ -358,7 +363,7 @@ macro_rules! stoppable_clock {
                         // Delay for 3 cycles of the target clock, for ENABLE propagation.
                         // Note XOSC_COUNT is not helpful here because XOSC is not
                         // necessarily running, nor is timer... so, 3 cycles per loop:
-                        let sys_freq = 125_000_000; // TODO get actual sys_clk frequency
+                        let sys_freq = SYSTEM_CLOCK_FREQ.load(Ordering::Relaxed);
                         let delay_cyc = sys_freq / self.frequency.to_Hz() + 1u32;
                         cortex_m::asm::delay(delay_cyc);
                     }
@@ -60,29 +60,101 @@
 //! # }
 //! ```
 //!
+//! ## Clock outputs
+//!
+//! The four GPIO output clocks can drive external chips, or make internal clocks visible to a
+//! scope. GPOUT0 to GPOUT3 are on GPIO21, GPIO23, GPIO24 and GPIO25:
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{clocks::{init_clocks_and_plls, Clock}, gpio::Pins, pac, Sio, watchdog::Watchdog};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+//! let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! // Output clk_usb divided by 4, 12MHz, on GPIO21
+//! let usb_clock = &clocks.usb_clock;
+//! clocks.gpio_output0_clock.configure_clock(usb_clock, 12.MHz()).ok().unwrap();
+//! let _pin = clocks.gpio_output0_clock.output_to(pins.gpio21);
+//! ```
+//!
+//! ## Changing clk_sys at runtime
+//!
+//! [`ClocksManager::set_sys_clock`] changes the frequency of clk_sys. The drivers which derive
+//! their timing from clk_sys, or from clk_peri when it runs from clk_sys, have to be told
+//! about the new frequency:
+//! - [`UartPeripheral::set_baudrate`](crate::uart::UartPeripheral::set_baudrate) and
+//!   [`Spi::set_baudrate`](crate::spi::Spi::set_baudrate), from clk_peri
+//! - [`I2C::set_bus_frequency`](crate::i2c::I2C::set_bus_frequency), from clk_sys
+//! - [`Slice::set_freq_hz`](crate::pwm::Slice::set_freq_hz) and
+//!   [`Tone::set_clk_sys_freq`](crate::pwm::Tone::set_clk_sys_freq), from clk_sys
+//!
+//! Running clk_peri from a source which doesn't change, like PLL_USB, keeps UART and SPI
+//! unaffected.
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{clocks::{Clock, ClocksManager}, pac, pll::{common_configs::PLL_SYS_125MHZ, setup_pll_blocking}, xosc::setup_xosc_blocking};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let xosc = setup_xosc_blocking(peripherals.XOSC, 12.MHz()).ok().unwrap();
+//! let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+//! let pll_sys = setup_pll_blocking(peripherals.PLL_SYS, xosc.operating_frequency(), PLL_SYS_125MHZ, &mut clocks, &mut peripherals.RESETS).ok().unwrap();
+//! // Slow down to 25MHz while there is nothing to do
+//! clocks.set_sys_clock(&pll_sys, 25.MHz()).ok().unwrap();
+//! let sys_freq = clocks.system_clock.freq();
+//! ```
+//!
+//! ## Frequency counter
+//!
+//! [`ClocksManager::measure_frequency`] measures the actual frequency of the oscillators, PLLs
+//! and clocks, e.g. to check the ring oscillator:
+//! ```no_run
+//! use rp2040_hal::{clocks::{ClocksManager, FrequencyCounterSource}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+//! let rosc_freq = clocks.measure_frequency(FrequencyCounterSource::Rosc);
+//! ```
+//!
 //! See [Chapter 2 Section 15](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
 
 use crate::{
+    gpio::{
+        bank0::{Gpio21, Gpio23, Gpio24, Gpio25},
+        FunctionClock, Pin, PinMode, ValidPinMode,
+    },
     pll::{
         common_configs::{PLL_SYS_125MHZ, PLL_USB_48MHZ},
-        setup_pll_blocking, Error as PllError, Locked, PhaseLockedLoop,
+        find_config, setup_pll_blocking, Error as PllError, Locked, PhaseLockedLoop,
     },
+    resets::SubsystemReset,
+    rosc::{self, RingOscillator},
     typelevel::Sealed,
+    vreg::{self, Voltage},
     watchdog::Watchdog,
-    xosc::{setup_xosc_blocking, CrystalOscillator, Error as XoscError, Stable},
+    xosc::{
+        setup_xosc_blocking_with_delay_multiplier, CrystalOscillator, Error as XoscError, Stable,
+    },
+};
+use core::{
+    convert::Infallible,
+    marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
 };
-use core::{convert::Infallible, marker::PhantomData};
 use fugit::HertzU32;
 use fugit::RateExtU32;
-use pac::{CLOCKS, PLL_SYS, PLL_USB, RESETS, XOSC};
+use pac::{CLOCKS, PLL_SYS, PLL_USB, RESETS, VREG_AND_CHIP_RESET, XOSC};
 
 #[macro_use]
 mod macros;
+mod builder;
 mod clock_sources;
+mod gate;
 
 use clock_sources::PllSys;
 
-use self::clock_sources::{GPin0, GPin1, PllUsb, Rosc, Xosc};
+use self::clock_sources::{PllUsb, Rosc, Xosc};
+pub use builder::{ClocksBuilder, SourceSelect};
+pub use clock_sources::{GPin0, GPin1};
+pub use gate::{Gated, GatedPeripheral};
 
 #[derive(Copy, Clone)]
 /// Provides refs to the CLOCKS block.
@@ -103,6 +175,7 @@ impl ShareableClocks {
 /// Something when wrong setting up the clock
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ClockError {
     /// The frequency desired is higher than the source frequency
     CantIncreaseFreq,
@@ -110,6 +183,10 @@ pub enum ClockError {
     FrequencyTooHigh,
     /// The desired frequency is too low (divider can't reach the desired value)
     FrequencyTooLow,
+    /// The source of the clock is not configured
+    SourceNotConfigured,
+    /// The clock can't run from this source
+    UnsupportedSource,
 }
 
 /// For clocks
@@ -118,6 +195,10 @@ pub trait Clock: Sealed + Sized {
     type Variant;
 
     /// Get operating frequency
+    ///
+    /// This is the frequency the clock was last configured for, computed from the frequency
+    /// of its source and the divider actually used. Drivers should use it instead of assuming
+    /// the default frequencies.
     fn freq(&self) -> HertzU32;
 
     /// Configure this clock based on a clock source and desired frequency
@@ -248,6 +329,11 @@ clocks! {
     }
 }
 
+// Frequencies of clk_ref and clk_sys as last configured, in Hz. They are needed by the other
+// clocks while those are reconfigured, which only have access to themselves, and by busy waits.
+static REFERENCE_CLOCK_FREQ: AtomicU32 = AtomicU32::new(12_000_000);
+pub(crate) static SYSTEM_CLOCK_FREQ: AtomicU32 = AtomicU32::new(12_000_000);
+
 impl SystemClock {
     fn get_default_clock_source(&self) -> pac::clocks::clk_sys_ctrl::SRC_A {
         pac::clocks::clk_sys_ctrl::SRC_A::CLK_REF
@@ -256,6 +342,15 @@ impl SystemClock {
     fn get_aux_source(&self) -> pac::clocks::clk_sys_ctrl::SRC_A {
         pac::clocks::clk_sys_ctrl::SRC_A::CLKSRC_CLK_SYS_AUX
     }
+
+    fn get_default_source_freq(&self) -> HertzU32 {
+        REFERENCE_CLOCK_FREQ.load(Ordering::Relaxed).Hz()
+    }
+
+    fn set_frequency(&mut self, freq: HertzU32) {
+        self.frequency = freq;
+        SYSTEM_CLOCK_FREQ.store(freq.to_Hz(), Ordering::Relaxed);
+    }
 }
 
 impl ReferenceClock {
@@ -266,8 +361,52 @@ impl ReferenceClock {
     fn get_aux_source(&self) -> pac::clocks::clk_ref_ctrl::SRC_A {
         pac::clocks::clk_ref_ctrl::SRC_A::CLKSRC_CLK_REF_AUX
     }
+
+    fn get_default_source_freq(&self) -> HertzU32 {
+        // The ROSC actually varies with input voltage etc, but 12 MHz seems to be a good value
+        12.MHz()
+    }
+
+    fn set_frequency(&mut self, freq: HertzU32) {
+        self.frequency = freq;
+        REFERENCE_CLOCK_FREQ.store(freq.to_Hz(), Ordering::Relaxed);
+    }
+}
+
+macro_rules! gpio_output_clock {
+    ($name:ident, $reg:ident, $pin:ident) => {
+        $crate::paste::paste! {
+            impl $name {
+                /// Route the clock to its pin.
+                ///
+                /// The clock is configured and enabled by
+                /// [`configure_clock`](Clock::configure_clock).
+                pub fn output_to<M: PinMode + ValidPinMode<$pin>>(
+                    &mut self,
+                    pin: Pin<$pin, M>,
+                ) -> Pin<$pin, FunctionClock> {
+                    pin.into_mode()
+                }
+
+                /// Enable or disable the 50% duty cycle correction.
+                ///
+                /// Without it, odd integer dividers produce an output which is high for one
+                /// cycle of the source less than it is low. Correction is enabled after reset.
+                pub fn set_duty_cycle_correction(&mut self, enabled: bool) {
+                    unsafe { self.shared_dev.get() }
+                        .[<$reg _ctrl>]
+                        .modify(|_, w| w.dc50().bit(enabled));
+                }
+            }
+        }
+    };
 }
 
+gpio_output_clock!(GpioOutput0Clock, clk_gpout0, Gpio21);
+gpio_output_clock!(GpioOutput1Clock, clk_gpout1, Gpio23);
+gpio_output_clock!(GpioOutput2Clock, clk_gpout2, Gpio24);
+gpio_output_clock!(GpioOutput3Clock, clk_gpout3, Gpio25);
+
 impl ClocksManager {
     /// Initialize the clocks to a sane default
     pub fn init_default(
@@ -302,13 +441,329 @@ impl ClocksManager {
             .configure_clock(&self.system_clock, self.system_clock.freq())
     }
 
+    /// Change clk_sys at runtime, e.g. to scale it down to save power.
+    ///
+    /// clk_sys is switched over without glitches, and its divider is changed before or after
+    /// the source so that it never runs faster than the old or the new frequency. If clk_peri
+    /// runs from clk_sys, its tracked frequency changes too.
+    ///
+    /// The peripherals keep their dividers, see
+    /// [the module documentation](self#changing-clk_sys-at-runtime) for how to update them.
+    pub fn set_sys_clock<S: ValidSrc<SystemClock>>(
+        &mut self,
+        src: &S,
+        freq: HertzU32,
+    ) -> Result<(), ClockError> {
+        self.system_clock.configure_clock(src, freq)?;
+        self.update_peripheral_freq();
+        Ok(())
+    }
+
+    /// Run clk_sys at `freq` from PLL_SYS, adjusting the core voltage to `voltage`.
+    ///
+    /// clk_sys runs from clk_ref while PLL_SYS is reconfigured with a configuration found by
+    /// [`find_config`](crate::pll::find_config). When speeding up, the voltage is raised first
+    /// and given 10ms to settle. When slowing down, it is lowered last. Other clocks running
+    /// from PLL_SYS stop while it locks again, and their tracked frequencies aren't updated.
+    ///
+    /// Frequencies above 133MHz are outside of the specification of the RP2040. The QSPI
+    /// clock of the flash is derived from clk_sys as well: as the usual boot2 divides it by 2,
+    /// some flash chips can't follow above about 266MHz, and need a larger divider.
+    ///
+    /// ```no_run
+    /// use fugit::RateExtU32;
+    /// use rp2040_hal::{clocks::ClocksManager, pac, pll::{common_configs::PLL_SYS_125MHZ, setup_pll_blocking}, vreg::Voltage, xosc::setup_xosc_blocking};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let xosc = setup_xosc_blocking(peripherals.XOSC, 12.MHz()).ok().unwrap();
+    /// let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+    /// let mut pll_sys = setup_pll_blocking(peripherals.PLL_SYS, xosc.operating_frequency(), PLL_SYS_125MHZ, &mut clocks, &mut peripherals.RESETS).ok().unwrap();
+    /// clocks
+    ///     .overclock_to(&mut pll_sys, &mut peripherals.VREG_AND_CHIP_RESET, 200.MHz(), Voltage::V1_15)
+    ///     .ok()
+    ///     .unwrap();
+    /// ```
+    pub fn overclock_to(
+        &mut self,
+        pll_sys: &mut PhaseLockedLoop<Locked, PLL_SYS>,
+        vreg_dev: &mut VREG_AND_CHIP_RESET,
+        freq: HertzU32,
+        voltage: Voltage,
+    ) -> Result<(), OverclockError> {
+        let config = find_config(pll_sys.xosc_frequency(), freq)
+            .ok_or(OverclockError::UnreachableFrequency)?;
+
+        let speeding_up = freq > self.system_clock.freq();
+        if speeding_up {
+            vreg::set_voltage(vreg_dev, voltage);
+            // Wait 10ms at the current frequency for the voltage to settle
+            cortex_m::asm::delay(self.system_clock.freq().to_Hz() / 100);
+        }
+
+        nb::block!(self.system_clock.reset_source_await()).unwrap();
+        pll_sys
+            .reconfigure(config)
+            .map_err(OverclockError::PllError)?;
+        self.set_sys_clock(pll_sys, freq)
+            .map_err(OverclockError::ClockError)?;
+
+        if !speeding_up {
+            vreg::set_voltage(vreg_dev, voltage);
+        }
+        Ok(())
+    }
+
+    /// clk_peri has no divider, so when it runs from clk_sys it follows its frequency
+    fn update_peripheral_freq(&mut self) {
+        if self.clocks.clk_peri_ctrl.read().auxsrc().is_clk_sys() {
+            self.peripheral_clock.frequency = self.system_clock.freq();
+        }
+    }
+
+    /// Measure the frequency of a clock with the frequency counter.
+    ///
+    /// The measurement uses clk_ref as reference, so its frequency has to be known: the result
+    /// is as accurate as `reference_clock`, with a resolution of about 31Hz. Measuring takes
+    /// about 1ms.
+    pub fn measure_frequency(&mut self, src: FrequencyCounterSource) -> HertzU32 {
+        let clocks = &self.clocks;
+        while clocks.fc0_status.read().running().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        let ref_khz = self.reference_clock.freq().to_kHz();
+        // Safety: all values are valid for these registers
+        unsafe {
+            clocks.fc0_ref_khz.write(|w| w.fc0_ref_khz().bits(ref_khz));
+            // The test interval is about 2^10us, i.e. 1ms, whatever the reference frequency
+            clocks.fc0_interval.write(|w| w.fc0_interval().bits(10));
+            clocks.fc0_min_khz.write(|w| w.fc0_min_khz().bits(0));
+            clocks
+                .fc0_max_khz
+                .write(|w| w.fc0_max_khz().bits(0x1ff_ffff));
+            // Writing the source starts the measurement
+            clocks.fc0_src.write(|w| w.fc0_src().bits(src as u8));
+        }
+
+        while clocks.fc0_status.read().done().bit_is_clear() {
+            cortex_m::asm::nop();
+        }
+        let result = clocks.fc0_result.read();
+        let khz = result.khz().bits();
+        let frac = result.frac().bits() as u32;
+        (khz * 1000 + frac * 1000 / 32).Hz()
+    }
+
+    /// Enable the resuscitation of clk_sys.
+    ///
+    /// If clk_sys stops for `timeout` cycles of clk_ref, e.g. because the PLL or the crystal
+    /// failed, clk_sys is switched over to clk_ref and the `CLOCKS_IRQ` interrupt is raised. Its
+    /// handler should call [`ClocksManager::clear_resus_interrupt`], the application can then
+    /// call [`recover_from_resus`](Self::recover_from_resus) and try to restart the failed
+    /// clock source.
+    pub fn enable_resus(&mut self, timeout: u8) {
+        let clocks = &self.clocks;
+        clocks.inte.modify(|_, w| w.clk_sys_resus().set_bit());
+        clocks
+            .clk_sys_resus_ctrl
+            .write(|w| unsafe { w.timeout().bits(timeout).enable().set_bit() });
+    }
+
+    /// Disable the resuscitation of clk_sys.
+    pub fn disable_resus(&mut self) {
+        let clocks = &self.clocks;
+        clocks
+            .clk_sys_resus_ctrl
+            .modify(|_, w| w.enable().clear_bit());
+        clocks.inte.modify(|_, w| w.clk_sys_resus().clear_bit());
+    }
+
+    /// Returns true if clk_sys has been resuscitated, and is running from clk_ref.
+    pub fn has_resussed(&self) -> bool {
+        self.clocks
+            .clk_sys_resus_status
+            .read()
+            .resussed()
+            .bit_is_set()
+    }
+
+    /// Switch clk_sys to clk_ref after a resuscitation and clear it.
+    ///
+    /// This makes the configuration of clk_sys match the resuscitated state, and keeps the
+    /// tracked frequency of clk_sys accurate. clk_sys can be reconfigured to a faster source
+    /// once that is running again.
+    pub fn recover_from_resus(&mut self) -> Result<(), ClockError> {
+        let ref_freq = self.reference_clock.freq();
+        self.system_clock
+            .configure_clock(&self.reference_clock, ref_freq)?;
+        self.update_peripheral_freq();
+        let clocks = &self.clocks;
+        clocks.clk_sys_resus_ctrl.modify(|_, w| w.clear().set_bit());
+        clocks
+            .clk_sys_resus_ctrl
+            .modify(|_, w| w.clear().clear_bit());
+        Ok(())
+    }
+
+    /// Clear the resus interrupt, returning true if it was pending.
+    ///
+    /// Call this from the `CLOCKS_IRQ` interrupt handler. The interrupt is only cleared once
+    /// the resuscitation itself is [cleared](Self::recover_from_resus), so it is disabled here.
+    pub fn clear_resus_interrupt() -> bool {
+        // Safety: only the resus bit of the interrupt enable register is written, which is
+        // owned by the resus functions
+        let clocks = unsafe { &*CLOCKS::ptr() };
+        let pending = clocks.ints.read().clk_sys_resus().bit_is_set();
+        if pending {
+            clocks.inte.modify(|_, w| w.clk_sys_resus().clear_bit());
+        }
+        pending
+    }
+
+    /// Run clk_ref and clk_sys from the XOSC, and stop clk_usb and clk_adc
+    ///
+    /// Returns the previous configuration, for [`restore`](Self::restore). clk_peri and clk_rtc
+    /// are left as they are.
+    pub(crate) fn run_from_xosc(
+        &mut self,
+        xosc: &CrystalOscillator<Stable>,
+    ) -> Result<SavedClocks, ClockError> {
+        let clocks = &self.clocks;
+        let saved = SavedClocks {
+            ref_ctrl: clocks.clk_ref_ctrl.read().bits(),
+            ref_div: clocks.clk_ref_div.read().bits(),
+            ref_freq: self.reference_clock.freq(),
+            sys_ctrl: clocks.clk_sys_ctrl.read().bits(),
+            sys_div: clocks.clk_sys_div.read().bits(),
+            sys_freq: self.system_clock.freq(),
+            peri_freq: self.peripheral_clock.freq(),
+            usb_ctrl: clocks.clk_usb_ctrl.read().bits(),
+            usb_div: clocks.clk_usb_div.read().bits(),
+            adc_ctrl: clocks.clk_adc_ctrl.read().bits(),
+            adc_div: clocks.clk_adc_div.read().bits(),
+        };
+
+        self.reference_clock
+            .configure_clock(xosc, xosc.get_freq())?;
+        let ref_freq = self.reference_clock.freq();
+        self.system_clock
+            .configure_clock(&self.reference_clock, ref_freq)?;
+        self.update_peripheral_freq();
+        self.usb_clock.disable();
+        self.adc_clock.disable();
+        Ok(saved)
+    }
+
+    /// Restore the configuration saved by [`run_from_xosc`](Self::run_from_xosc)
+    ///
+    /// The sources of the clocks have to be running again.
+    pub(crate) fn restore(&mut self, saved: &SavedClocks) {
+        // clk_ref and clk_sys run from their glitchless sources, so their aux mux can be changed
+        // first. The divider is set before the source to avoid overspeed, integer dividers can
+        // be changed without glitches.
+        const REF_SRC_MASK: u32 = 0b11;
+        const SYS_SRC_MASK: u32 = 0b1;
+        let clocks = &self.clocks;
+
+        clocks.clk_ref_ctrl.modify(|r, w| unsafe {
+            w.bits((saved.ref_ctrl & !REF_SRC_MASK) | (r.bits() & REF_SRC_MASK))
+        });
+        clocks
+            .clk_ref_div
+            .write(|w| unsafe { w.bits(saved.ref_div) });
+        clocks
+            .clk_ref_ctrl
+            .write(|w| unsafe { w.bits(saved.ref_ctrl) });
+        while clocks.clk_ref_selected.read().bits() != 1 << (saved.ref_ctrl & REF_SRC_MASK) {
+            cortex_m::asm::nop();
+        }
+        self.reference_clock.set_frequency(saved.ref_freq);
+
+        clocks.clk_sys_ctrl.modify(|r, w| unsafe {
+            w.bits((saved.sys_ctrl & !SYS_SRC_MASK) | (r.bits() & SYS_SRC_MASK))
+        });
+        clocks
+            .clk_sys_div
+            .write(|w| unsafe { w.bits(saved.sys_div) });
+        clocks
+            .clk_sys_ctrl
+            .write(|w| unsafe { w.bits(saved.sys_ctrl) });
+        while clocks.clk_sys_selected.read().bits() != 1 << (saved.sys_ctrl & SYS_SRC_MASK) {
+            cortex_m::asm::nop();
+        }
+        self.system_clock.set_frequency(saved.sys_freq);
+        self.peripheral_clock.frequency = saved.peri_freq;
+
+        // clk_usb and clk_adc are stopped, so their aux mux can be changed when enabling them
+        clocks
+            .clk_usb_div
+            .write(|w| unsafe { w.bits(saved.usb_div) });
+        clocks
+            .clk_usb_ctrl
+            .write(|w| unsafe { w.bits(saved.usb_ctrl) });
+        clocks
+            .clk_adc_div
+            .write(|w| unsafe { w.bits(saved.adc_div) });
+        clocks
+            .clk_adc_ctrl
+            .write(|w| unsafe { w.bits(saved.adc_ctrl) });
+    }
+
     /// Releases the CLOCKS block
     pub fn free(self) -> CLOCKS {
         self.clocks
     }
 }
 
+/// Configuration of the clocks, saved while running from the XOSC
+pub(crate) struct SavedClocks {
+    ref_ctrl: u32,
+    ref_div: u32,
+    ref_freq: HertzU32,
+    sys_ctrl: u32,
+    sys_div: u32,
+    sys_freq: HertzU32,
+    peri_freq: HertzU32,
+    usb_ctrl: u32,
+    usb_div: u32,
+    adc_ctrl: u32,
+    adc_div: u32,
+}
+
+/// Clocks which can be measured with [`ClocksManager::measure_frequency`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum FrequencyCounterSource {
+    /// Output of PLL_SYS
+    PllSys = 1,
+    /// Output of PLL_USB
+    PllUsb = 2,
+    /// Ring oscillator
+    Rosc = 3,
+    /// Phase shifted output of the ring oscillator
+    RoscPhaseShifted = 4,
+    /// Crystal oscillator
+    Xosc = 5,
+    /// Clock input GPIN0
+    GPin0 = 6,
+    /// Clock input GPIN1
+    GPin1 = 7,
+    /// clk_ref
+    ReferenceClock = 8,
+    /// clk_sys
+    SystemClock = 9,
+    /// clk_peri
+    PeripheralClock = 10,
+    /// clk_usb
+    UsbClock = 11,
+    /// clk_adc
+    AdcClock = 12,
+    /// clk_rtc
+    RtcClock = 13,
+}
+
 /// Possible init errors
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum InitError {
     /// Something went wrong setting up the Xosc
     XoscErr(XoscError),
@@ -318,7 +773,20 @@ pub enum InitError {
     ClockError(ClockError),
 }
 
+/// Possible errors of [`ClocksManager::overclock_to`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OverclockError {
+    /// PLL_SYS can't generate exactly the requested frequency
+    UnreachableFrequency,
+    /// Something went wrong reconfiguring the Pll
+    PllError(PllError),
+    /// Something went wrong switching clk_sys back to the Pll
+    ClockError(ClockError),
+}
+
 /// Initialize the clocks and plls according to the reference implementation
+///
+/// Use [`ClocksBuilder`] for other configurations.
 pub fn init_clocks_and_plls(
     xosc_crystal_freq: u32,
     xosc_dev: XOSC,
@@ -328,33 +796,70 @@ pub fn init_clocks_and_plls(
     resets: &mut RESETS,
     watchdog: &mut Watchdog,
 ) -> Result<ClocksManager, InitError> {
-    let xosc = setup_xosc_blocking(xosc_dev, xosc_crystal_freq.Hz()).map_err(InitError::XoscErr)?;
-
-    // Configure watchdog tick generation to tick over every microsecond
-    watchdog.enable_tick_generation((xosc_crystal_freq / 1_000_000) as u8);
-
-    let mut clocks = ClocksManager::new(clocks_dev);
-
-    let pll_sys = setup_pll_blocking(
+    ClocksBuilder::new(xosc_crystal_freq.Hz()).build(
+        xosc_dev,
+        clocks_dev,
         pll_sys_dev,
-        xosc.operating_frequency(),
-        PLL_SYS_125MHZ,
-        &mut clocks,
-        resets,
-    )
-    .map_err(InitError::PllError)?;
-    let pll_usb = setup_pll_blocking(
         pll_usb_dev,
-        xosc.operating_frequency(),
-        PLL_USB_48MHZ,
-        &mut clocks,
         resets,
+        watchdog,
     )
-    .map_err(InitError::PllError)?;
+}
 
+/// Run all clocks from the ring oscillator, with the crystal oscillator and the PLLs stopped
+///
+/// This is a low-power configuration for idle states: clk_ref, clk_sys and clk_peri run from
+/// `rosc` undivided. clk_usb, clk_adc and clk_rtc need an accurate frequency, so they are
+/// stopped. The devices are only borrowed so that the crystal oscillator and the PLLs can be
+/// started again later, e.g. with [`init_clocks_and_plls`].
+///
+/// The frequency of the ROSC is only an estimate, which the tracked frequencies of the clocks
+/// and the watchdog tick are based on.
+///
+/// ```no_run
+/// use rp2040_hal::{clocks::init_clocks_from_rosc, pac, rosc::{FrequencyRange, RingOscillator}, watchdog::Watchdog};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+/// // About 104MHz / 8 = 13MHz
+/// let rosc = RingOscillator::new(peripherals.ROSC).initialize_with(FrequencyRange::Low, 8);
+/// let clocks = init_clocks_from_rosc(&rosc, peripherals.CLOCKS, &peripherals.XOSC, &peripherals.PLL_SYS, &peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).unwrap();
+/// ```
+pub fn init_clocks_from_rosc(
+    rosc: &RingOscillator<rosc::Enabled>,
+    clocks_dev: CLOCKS,
+    xosc_dev: &XOSC,
+    pll_sys_dev: &PLL_SYS,
+    pll_usb_dev: &PLL_USB,
+    resets: &mut RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, ClockError> {
+    let mut clocks = ClocksManager::new(clocks_dev);
+
+    clocks
+        .reference_clock
+        .configure_clock(rosc, rosc.get_freq())?;
+    clocks.system_clock.configure_clock(rosc, rosc.get_freq())?;
+    let sys_freq = clocks.system_clock.freq();
     clocks
-        .init_default(&xosc, &pll_sys, &pll_usb)
-        .map_err(InitError::ClockError)?;
+        .peripheral_clock
+        .configure_clock(&clocks.system_clock, sys_freq)?;
+
+    clocks.usb_clock.disable();
+    clocks.usb_clock.frequency = 0.Hz();
+    clocks.adc_clock.disable();
+    clocks.adc_clock.frequency = 0.Hz();
+    clocks.rtc_clock.disable();
+    clocks.rtc_clock.frequency = 0.Hz();
+
+    // Nothing runs from the PLLs and the XOSC anymore
+    pll_sys_dev.reset_bring_down(resets);
+    pll_usb_dev.reset_bring_down(resets);
+    xosc_dev.ctrl.modify(|_, w| w.enable().disable());
+
+    // Configure watchdog tick generation to tick over about every microsecond
+    let ref_mhz = (clocks.reference_clock.freq().to_Hz() + 500_000) / 1_000_000;
+    watchdog.enable_tick_generation(ref_mhz.max(1).min(u8::MAX as u32) as u8);
+
     Ok(clocks)
 }
 
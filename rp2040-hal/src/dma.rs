@@ -1,6 +1,22 @@
 //! # DMA
 //!
 //! This is the start of a DMA driver.
+//!
+//! [`DMAExt::split`] splits the DMA block into its twelve channels. Drivers
+//! which stream data to or from memory, like [`AdcCapture`](crate::adc::AdcCapture),
+//! take ownership of the channels they use.
+//!
+//! ```no_run
+//! use rp2040_hal::{dma::DMAExt, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let ch0 = dma.ch0;
+//! ```
+
+use core::marker::PhantomData;
+use pac::{DMA, RESETS};
+
+use crate::{resets::SubsystemReset, typelevel::Sealed};
 
 /// The DREQ value for PIO0's TX FIFO 0
 pub const DREQ_PIO0_TX0: u8 = 0;
@@ -82,3 +98,330 @@ pub const DREQ_XIP_STREAM: u8 = 37;
 pub const DREQ_XIP_SSITX: u8 = 38;
 /// The DREQ value for the XIP SSI RX FIFO
 pub const DREQ_XIP_SSIRX: u8 = 39;
+/// The TREQ value for transfers paced by DMA timer 0
+pub const TREQ_TIMER0: u8 = 0x3b;
+/// The TREQ value for transfers paced by DMA timer 1
+pub const TREQ_TIMER1: u8 = 0x3c;
+/// The TREQ value for transfers paced by DMA timer 2
+pub const TREQ_TIMER2: u8 = 0x3d;
+/// The TREQ value for transfers paced by DMA timer 3
+pub const TREQ_TIMER3: u8 = 0x3e;
+/// The TREQ value for unpaced transfers, running as fast as possible
+pub const TREQ_UNPACED: u8 = 0x3f;
+
+/// DMA channel identifier
+pub trait ChannelIndex: Sealed {
+    /// Numerical index of the DMA channel (0..11)
+    fn id() -> u8;
+}
+
+/// Split the DMA peripheral into individual channels
+pub trait DMAExt {
+    /// Reset the DMA block and split it into its channels
+    fn split(self, resets: &mut RESETS) -> Channels;
+}
+
+/// A single DMA channel
+pub struct Channel<CH: ChannelIndex> {
+    _phantom: PhantomData<CH>,
+}
+
+macro_rules! channels {
+    ($($CH:ident: ($ch:ident, $id:expr),)+) => {
+        $(
+            #[doc = r"DMA channel identifier for channel "]
+            #[doc = stringify!($id)]
+            pub struct $CH;
+
+            impl Sealed for $CH {}
+
+            impl ChannelIndex for $CH {
+                fn id() -> u8 {
+                    $id
+                }
+            }
+        )+
+
+        /// Set of the DMA channels, obtained from [`DMAExt::split`]
+        pub struct Channels {
+            $(
+                #[doc = r"DMA channel "]
+                #[doc = stringify!($id)]
+                pub $ch: Channel<$CH>,
+            )+
+            device: DMA,
+        }
+
+        impl Channels {
+            /// Stop all channels and release the DMA block
+            pub fn free(self) -> DMA {
+                abort_channels(0xfff);
+                crate::sleep::peripheral_disabled::<DMA>();
+                self.device
+            }
+        }
+
+        impl DMAExt for DMA {
+            fn split(self, resets: &mut RESETS) -> Channels {
+                self.reset_bring_down(resets);
+                self.reset_bring_up(resets);
+                // Transfers read and write memory while the cores sleep
+                crate::sleep::peripheral_enabled::<DMA>();
+
+                Channels {
+                    $(
+                        $ch: Channel {
+                            _phantom: PhantomData,
+                        },
+                    )+
+                    device: self,
+                }
+            }
+        }
+    };
+}
+
+channels! {
+    CH0: (ch0, 0),
+    CH1: (ch1, 1),
+    CH2: (ch2, 2),
+    CH3: (ch3, 3),
+    CH4: (ch4, 4),
+    CH5: (ch5, 5),
+    CH6: (ch6, 6),
+    CH7: (ch7, 7),
+    CH8: (ch8, 8),
+    CH9: (ch9, 9),
+    CH10: (ch10, 10),
+    CH11: (ch11, 11),
+}
+
+/// Size of a single transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TransferSize {
+    HalfWord = 1,
+    Word = 2,
+}
+
+/// Configuration of a channel, encoded into its CTRL register
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ChannelConfig {
+    pub size: TransferSize,
+    pub incr_read: bool,
+    pub incr_write: bool,
+    pub treq: u8,
+    /// Channel to trigger when this channel completes, if any
+    pub chain_to: Option<u8>,
+    /// Wrap the read address on a boundary of `1 << read_ring` bytes, 0 to disable
+    pub read_ring: u8,
+}
+
+impl ChannelConfig {
+    fn ctrl_bits(&self, own_id: u8) -> u32 {
+        // Chaining a channel to itself disables chaining.
+        let chain_to = self.chain_to.unwrap_or(own_id);
+        1 // EN
+            | (self.size as u32) << 2
+            | (self.incr_read as u32) << 4
+            | (self.incr_write as u32) << 5
+            | (self.read_ring as u32 & 0xf) << 6
+            | (chain_to as u32 & 0xf) << 11
+            | (self.treq as u32 & 0x3f) << 15
+    }
+}
+
+impl<CH: ChannelIndex> Channel<CH> {
+    /// Numerical index of this channel
+    pub fn id(&self) -> u8 {
+        CH::id()
+    }
+
+    /// Returns true if the channel is currently transferring data
+    pub fn is_busy(&self) -> bool {
+        self.regs().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    /// Stop any transfer in progress and disable the channel
+    pub fn abort(&mut self) {
+        abort_channels(1 << CH::id());
+    }
+
+    pub(crate) fn regs(&self) -> &pac::dma::CH {
+        let dma = unsafe { &*DMA::ptr() };
+        &dma.ch[CH::id() as usize]
+    }
+
+    /// Set up a transfer without starting it
+    ///
+    /// # Safety
+    ///
+    /// Once started, the channel reads from `read_addr` and writes to
+    /// `write_addr` without regard for Rust's ownership rules. Both must stay
+    /// valid for `count` transfers of the configured size.
+    pub(crate) unsafe fn configure(
+        &mut self,
+        config: &ChannelConfig,
+        read_addr: u32,
+        write_addr: u32,
+        count: u32,
+    ) {
+        let regs = self.regs();
+        regs.ch_read_addr.write(|w| w.bits(read_addr));
+        regs.ch_write_addr.write(|w| w.bits(write_addr));
+        regs.ch_trans_count.write(|w| w.bits(count));
+        regs.ch_al1_ctrl
+            .write(|w| w.bits(config.ctrl_bits(CH::id())));
+    }
+
+    /// Start the configured transfer
+    pub(crate) fn start(&mut self) {
+        let dma = unsafe { &*DMA::ptr() };
+        dma.multi_chan_trigger
+            .write(|w| unsafe { w.bits(1 << CH::id()) });
+    }
+
+    /// Returns true if the channel finished a transfer since the flag was last cleared
+    ///
+    /// This is the raw interrupt status, it does not depend on the channel's
+    /// interrupt being enabled.
+    pub(crate) fn is_complete(&self) -> bool {
+        let dma = unsafe { &*DMA::ptr() };
+        dma.intr.read().bits() & (1 << CH::id()) != 0
+    }
+
+    /// Clear the flag returned by [`is_complete`](Self::is_complete)
+    pub(crate) fn clear_complete(&mut self) {
+        let dma = unsafe { &*DMA::ptr() };
+        dma.intr.write(|w| unsafe { w.bits(1 << CH::id()) });
+    }
+}
+
+/// Disable and abort all channels in `mask`
+///
+/// Channels chained to each other have to be disabled before they are aborted,
+/// otherwise an aborted channel could still trigger another one.
+pub(crate) fn abort_channels(mask: u16) {
+    let dma = unsafe { &*DMA::ptr() };
+    for (id, ch) in dma.ch.iter().enumerate() {
+        if mask & (1 << id) != 0 {
+            ch.ch_al1_ctrl.modify(|_, w| w.en().clear_bit());
+        }
+    }
+    dma.chan_abort.write(|w| unsafe { w.bits(mask as u32) });
+    while dma.chan_abort.read().bits() != 0 {
+        cortex_m::asm::nop();
+    }
+}
+
+/// Addresses of the two buffers of a [`DoubleBuffer`], in the order they are reloaded
+///
+/// The control channel reads them in a ring of 8 bytes, so they have to be aligned on 8 bytes.
+#[derive(Clone, Copy)]
+#[repr(C, align(8))]
+struct ReloadAddrs([u32; 2]);
+
+/// The reload addresses of each data channel
+///
+/// Each entry is only used by the [`DoubleBuffer`] owning the data channel of the same index.
+static mut RELOAD_ADDRS: [ReloadAddrs; 12] = [ReloadAddrs([0; 2]); 12];
+
+/// A data channel alternating between two buffers, restarted by a control channel
+///
+/// Each time the data channel completes a buffer, it triggers the control channel, which writes
+/// the address of the other buffer to the data channel and so restarts it. The addresses are
+/// reloaded by the hardware, so the data channel never runs past the end of a buffer, however late
+/// the code processing the buffers is.
+pub(crate) struct DoubleBuffer<D: ChannelIndex, C: ChannelIndex> {
+    data: Channel<D>,
+    control: Channel<C>,
+}
+
+impl<D: ChannelIndex, C: ChannelIndex> DoubleBuffer<D, C> {
+    /// Set up the channels without starting them
+    ///
+    /// If `config.incr_write` is set, the buffers are written to and `fixed_addr` is read from.
+    /// Otherwise the buffers are read from and `fixed_addr` is written to.
+    ///
+    /// # Safety
+    ///
+    /// Both buffers must stay valid for `count` transfers of the configured size, until the
+    /// channels are [freed](Self::free).
+    pub(crate) unsafe fn new(
+        mut data: Channel<D>,
+        mut control: Channel<C>,
+        config: &ChannelConfig,
+        fixed_addr: u32,
+        buffers: [u32; 2],
+        count: u32,
+    ) -> Self {
+        let reload = &mut *core::ptr::addr_of_mut!(RELOAD_ADDRS[D::id() as usize]);
+        reload.0 = [buffers[1], buffers[0]];
+
+        let (read_addr, write_addr, trigger_addr) = if config.incr_write {
+            (
+                fixed_addr,
+                buffers[0],
+                data.regs().ch_al2_write_addr_trig.as_ptr() as u32,
+            )
+        } else {
+            (
+                buffers[0],
+                fixed_addr,
+                data.regs().ch_al3_read_addr_trig.as_ptr() as u32,
+            )
+        };
+        let data_config = ChannelConfig {
+            chain_to: Some(C::id()),
+            ..*config
+        };
+        data.configure(&data_config, read_addr, write_addr, count);
+
+        let control_config = ChannelConfig {
+            size: TransferSize::Word,
+            incr_read: true,
+            incr_write: false,
+            treq: TREQ_UNPACED,
+            chain_to: None,
+            read_ring: 3,
+        };
+        control.configure(&control_config, reload.0.as_ptr() as u32, trigger_addr, 1);
+
+        data.clear_complete();
+        Self { data, control }
+    }
+
+    /// Start the data channel on the first buffer
+    pub(crate) fn start(&mut self) {
+        self.data.start();
+    }
+
+    /// Returns true if the data channel completed a buffer that wasn't [waited](Self::wait) for
+    pub(crate) fn is_ready(&self) -> bool {
+        self.data.is_complete()
+    }
+
+    /// Wait for the data channel to complete a buffer, and return the index of the last one
+    /// completed
+    pub(crate) fn wait(&mut self) -> usize {
+        while !self.data.is_complete() {
+            cortex_m::asm::nop();
+        }
+        self.data.clear_complete();
+        // The control channel restarts the data channel within a few cycles
+        while !self.data.is_busy() {
+            cortex_m::asm::nop();
+        }
+        // The control channel points to the address it reloads next, i.e. the one of the buffer
+        // after the current one
+        let next = self.control.regs().ch_read_addr.read().bits();
+        let current = ((next & 0x7) >> 2) as usize;
+        1 - current
+    }
+
+    /// Stop both channels and return them
+    pub(crate) fn free(mut self) -> (Channel<D>, Channel<C>) {
+        abort_channels((1 << D::id()) | (1 << C::id()));
+        self.data.clear_complete();
+        (self.data, self.control)
+    }
+}
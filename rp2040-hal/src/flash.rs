@@ -0,0 +1,448 @@
+//! In-application programming of the flash
+//!
+//! The program runs from the external QSPI flash through the XIP interface, which has to be
+//! switched off while the flash is erased or programmed. This module wraps the flash routines
+//! of the bootrom, and runs the whole sequence from RAM with the interrupts of the calling
+//! core disabled and the other core locked out: XIP is left, the operation is done, the XIP
+//! cache is flushed, and XIP is entered again with the second stage bootloader, for the full
+//! read speed. The clock divider and the read command set with the [`ssi`](crate::ssi) module
+//! are kept.
+//!
+//! Offsets are relative to the start of the flash, as seen by the flash chip: offset 0 is the
+//! second stage bootloader, at [`XIP_BASE`] in the address space.
+//!
+//! ```no_run
+//! use rp2040_hal::flash;
+//!
+//! // The last sector of a 2MB flash, well after the program
+//! const SETTINGS_OFFSET: u32 = 2 * 1024 * 1024 - flash::SECTOR_SIZE;
+//!
+//! let mut page = [0xff; flash::PAGE_SIZE as usize];
+//! page[..4].copy_from_slice(&42u32.to_le_bytes());
+//! // Safety: no DMA channel reads from flash, and the sector isn't used by the program
+//! unsafe {
+//!     flash::erase(SETTINGS_OFFSET, flash::SECTOR_SIZE).unwrap();
+//!     flash::program(SETTINGS_OFFSET, &page).unwrap();
+//! }
+//!
+//! let mut value = [0; 4];
+//! flash::read(SETTINGS_OFFSET, &mut value).unwrap();
+//! assert_eq!(u32::from_le_bytes(value), 42);
+//! ```
+//!
+//! While XIP is off, nothing may run from flash or read data from it. The interrupt handlers
+//! of the calling core are masked. If the other core runs code, it's locked out through the
+//! inter-core FIFO: it has to handle its FIFO interrupt with
+//! [`SioFifo::on_interrupt`](crate::sio::SioFifo::on_interrupt), which spins in RAM for the
+//! duration of the operation, see [`SioFifo::enable_interrupt`](crate::sio::SioFifo::enable_interrupt).
+//! Otherwise the operations fail with [`Error::OtherCoreRunning`]. Only one core at a time may
+//! use the flash functions. DMA channels reading from flash aren't stopped, which the
+//! functions being `unsafe` leaves to the caller.
+//!
+//! A [`FlashRegion`] is a range of sectors after the program, which can be handed out to a
+//! driver with safe methods. With the `embedded-storage` feature, it implements the `NorFlash`
+//! and `ReadNorFlash` traits of embedded-storage, for crates such as `sequential-storage`.
+//!
+//! The [`settings`] module stores key-value settings in a region, with wear levelling.
+//!
+//! The unique ID of the flash chip, read with [`unique_id`], is the usual serial number of a
+//! board, as the RP2040 itself has none: it's stable across reflashing, and suits USB
+//! descriptors. It's best read once at start-up, before core 1 is started.
+//!
+//! See [Chapter 2 Section 8](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use crate::pac;
+use crate::rom_data;
+use crate::sio;
+use crate::ssi;
+
+pub mod settings;
+
+/// Address of the start of the flash in the address space
+pub const XIP_BASE: u32 = 0x1000_0000;
+/// Largest size of flash which can be mapped, 16MB
+pub const MAX_FLASH_SIZE: u32 = 16 * 1024 * 1024;
+/// Unit of programming
+pub const PAGE_SIZE: u32 = 256;
+/// Unit of erasing
+pub const SECTOR_SIZE: u32 = 4096;
+/// Size of the larger erase, used where possible as it's much faster
+pub const BLOCK_SIZE: u32 = 65536;
+/// The command of the 64kB block erase
+const BLOCK_ERASE_CMD: u8 = 0xd8;
+
+/// Size of the second stage bootloader, at the start of the flash
+const BOOT2_SIZE: usize = 256;
+
+/// The command to read the unique ID, followed by 4 dummy bytes
+const READ_UNIQUE_ID_CMD: u8 = 0x4b;
+/// The command to read the manufacturer ID, memory type and capacity
+const READ_JEDEC_ID_CMD: u8 = 0x9f;
+/// Bytes sent to the SSI ahead of those received, to not overflow its 16 entry RX FIFO
+const MAX_IN_FLIGHT: usize = 14;
+
+/// Error of a flash operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The offset or length isn't aligned to a page for programming, or to a sector for
+    /// erasing
+    Unaligned,
+    /// The range goes past the end of the flash
+    OutOfBounds,
+    /// The data to program is in flash, which can't be read while programming
+    DataInFlash,
+    /// The region overlaps the program image
+    InProgram,
+    /// The other core runs code, but doesn't handle its FIFO interrupt to be locked out
+    OtherCoreRunning,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::NorFlashError for Error {
+    fn kind(&self) -> embedded_storage::nor_flash::NorFlashErrorKind {
+        use embedded_storage::nor_flash::NorFlashErrorKind;
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// What to do once XIP is off
+enum Operation {
+    Erase {
+        offset: u32,
+        len: u32,
+    },
+    Program {
+        offset: u32,
+        data: *const u8,
+        len: u32,
+    },
+    /// Send the bytes of `buf` to the flash, and replace them with those received
+    Command {
+        buf: *mut u8,
+        len: usize,
+    },
+}
+
+/// The bootrom routines, looked up before leaving XIP
+struct FlashFunctions {
+    xip: ssi::XipFunctions,
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+}
+
+impl FlashFunctions {
+    fn lookup(boot2: *const u32) -> Self {
+        Self {
+            xip: ssi::XipFunctions::lookup(boot2),
+            flash_range_erase: rom_data::flash_range_erase::ptr(),
+            flash_range_program: rom_data::flash_range_program::ptr(),
+            flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+        }
+    }
+}
+
+fn check_range(offset: u32, len: u32, align: u32) -> Result<(), Error> {
+    if offset % align != 0 || len % align != 0 {
+        return Err(Error::Unaligned);
+    }
+    match offset.checked_add(len) {
+        Some(end) if end <= MAX_FLASH_SIZE => Ok(()),
+        _ => Err(Error::OutOfBounds),
+    }
+}
+
+/// Erase `len` bytes from `offset`, both multiples of [`SECTOR_SIZE`]
+///
+/// # Safety
+///
+/// No DMA channel may read from flash during the call, see the
+/// [module level documentation](self). The erased range must not hold the program or data in
+/// use.
+pub unsafe fn erase(offset: u32, len: u32) -> Result<(), Error> {
+    check_range(offset, len, SECTOR_SIZE)?;
+    run_from_ram(Operation::Erase { offset, len })
+}
+
+/// Program `data` at `offset`, both multiples of [`PAGE_SIZE`]
+///
+/// The range has to be erased first, as programming can only clear bits. `data` has to be in
+/// RAM.
+///
+/// # Safety
+///
+/// See [`erase`].
+pub unsafe fn program(offset: u32, data: &[u8]) -> Result<(), Error> {
+    check_range(offset, data.len() as u32, PAGE_SIZE)?;
+    let address = data.as_ptr() as u32;
+    if (XIP_BASE..XIP_BASE + 0x0400_0000).contains(&address) {
+        return Err(Error::DataInFlash);
+    }
+    run_from_ram(Operation::Program {
+        offset,
+        data: data.as_ptr(),
+        len: data.len() as u32,
+    })
+}
+
+/// Read the 64-bit unique ID of the flash chip
+///
+/// # Safety
+///
+/// See [`erase`].
+pub unsafe fn unique_id() -> Result<[u8; 8], Error> {
+    let mut buf = [0; 13];
+    buf[0] = READ_UNIQUE_ID_CMD;
+    command(&mut buf)?;
+    let mut id = [0; 8];
+    id.copy_from_slice(&buf[5..]);
+    Ok(id)
+}
+
+/// Read the JEDEC ID of the flash chip
+///
+/// This is the manufacturer ID in bits 23:16, then the memory type and the capacity, e.g.
+/// 0xef4015 for the 2MB Winbond W25Q16JV of the Pico.
+///
+/// # Safety
+///
+/// See [`erase`].
+pub unsafe fn jedec_id() -> Result<u32, Error> {
+    let mut buf = [0; 4];
+    buf[0] = READ_JEDEC_ID_CMD;
+    command(&mut buf)?;
+    Ok(u32::from_be_bytes(buf) & 0x00ff_ffff)
+}
+
+/// Send a command to the flash, and replace the bytes of `buf` with the response
+fn command(buf: &mut [u8]) -> Result<(), Error> {
+    run_from_ram(Operation::Command {
+        buf: buf.as_mut_ptr(),
+        len: buf.len(),
+    })
+}
+
+/// Read `buf.len()` bytes from `offset`, through XIP
+pub fn read(offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+    check_range(offset, buf.len() as u32, 1)?;
+    let address = (XIP_BASE + offset) as *const u8;
+    // Safety: the whole flash is mapped for reading, and XIP is on outside of the operations
+    unsafe {
+        core::ptr::copy_nonoverlapping(address, buf.as_mut_ptr(), buf.len());
+    }
+    Ok(())
+}
+
+/// Returns the offset of the end of the program image, including the initial values of
+/// `.data`
+pub fn program_end() -> u32 {
+    extern "C" {
+        static __sidata: u32;
+        static __sdata: u32;
+        static __edata: u32;
+    }
+    // Safety: only the addresses of the symbols defined by cortex-m-rt are used
+    unsafe {
+        let data_len = core::ptr::addr_of!(__edata) as u32 - core::ptr::addr_of!(__sdata) as u32;
+        core::ptr::addr_of!(__sidata) as u32 + data_len - XIP_BASE
+    }
+}
+
+/// A range of whole sectors of the flash, after the program image
+///
+/// Offsets passed to the methods are relative to the start of the region. Writes aren't
+/// limited to pages: the bytes around those written are programmed as 0xff, which leaves them
+/// unchanged.
+///
+/// ```no_run
+/// use rp2040_hal::flash::{FlashRegion, SECTOR_SIZE};
+///
+/// // The last 64kB of a 2MB flash
+/// // Safety: no DMA channel reads from flash, and this is the only region
+/// let mut storage = unsafe { FlashRegion::new(2 * 1024 * 1024 - 16 * SECTOR_SIZE, 16 * SECTOR_SIZE) }.unwrap();
+/// storage.erase(0, SECTOR_SIZE).unwrap();
+/// storage.write(0, b"hello").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FlashRegion {
+    start: u32,
+    len: u32,
+}
+
+impl FlashRegion {
+    /// Create the region of `len` bytes from `offset`, both multiples of [`SECTOR_SIZE`]
+    ///
+    /// This fails if the region overlaps the program image, or goes past the largest size of
+    /// flash. The size of the actual chip isn't known, so the region has to fit in it.
+    ///
+    /// # Safety
+    ///
+    /// The conditions of [`erase`] have to be met whenever the region is erased or written.
+    /// Regions must not overlap.
+    pub unsafe fn new(offset: u32, len: u32) -> Result<Self, Error> {
+        check_range(offset, len, SECTOR_SIZE)?;
+        if offset < program_end() {
+            return Err(Error::InProgram);
+        }
+        Ok(Self { start: offset, len })
+    }
+
+    /// Returns the offset of the region in flash
+    pub fn offset(&self) -> u32 {
+        self.start
+    }
+
+    /// Returns the size of the region in bytes
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns true if the region is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn check(&self, offset: u32, len: u32) -> Result<(), Error> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => Ok(()),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+
+    /// Read `buf.len()` bytes from `offset`
+    pub fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+        self.check(offset, buf.len() as u32)?;
+        read(self.start + offset, buf)
+    }
+
+    /// Erase `len` bytes from `offset`, both multiples of [`SECTOR_SIZE`]
+    pub fn erase(&mut self, offset: u32, len: u32) -> Result<(), Error> {
+        self.check(offset, len)?;
+        // Safety: left to the creator of the region
+        unsafe { erase(self.start + offset, len) }
+    }
+
+    /// Write `bytes` from `offset`, to erased flash
+    pub fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        self.check(offset, bytes.len() as u32)?;
+        let mut offset = self.start + offset;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let in_page = (offset % PAGE_SIZE) as usize;
+            let count = bytes.len().min(PAGE_SIZE as usize - in_page);
+            let mut page = [0xff; PAGE_SIZE as usize];
+            page[in_page..in_page + count].copy_from_slice(&bytes[..count]);
+            // Safety: left to the creator of the region
+            unsafe { program(offset - in_page as u32, &page)? };
+            offset += count as u32;
+            bytes = &bytes[count..];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::ErrorType for FlashRegion {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::ReadNorFlash for FlashRegion {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        FlashRegion::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::NorFlash for FlashRegion {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        let len = to.checked_sub(from).ok_or(Error::OutOfBounds)?;
+        FlashRegion::erase(self, from, len)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        FlashRegion::write(self, offset, bytes)
+    }
+}
+
+/// Copy the second stage bootloader, which can't be read from flash once XIP is off, to run it
+/// from RAM
+pub(crate) fn copy_boot2() -> [u32; BOOT2_SIZE / 4] {
+    let mut boot2 = [0u32; BOOT2_SIZE / 4];
+    // Safety: the bootloader is at the start of the flash
+    unsafe {
+        core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), BOOT2_SIZE / 4);
+    }
+    boot2
+}
+
+fn run_from_ram(operation: Operation) -> Result<(), Error> {
+    let boot2 = copy_boot2();
+    let functions = FlashFunctions::lookup(boot2.as_ptr());
+    let serial = ssi::is_serial_read();
+    cortex_m::interrupt::free(|cs| {
+        // Released once XIP is on again
+        let _lockout = sio::lock_out_other_core(cs).ok_or(Error::OtherCoreRunning)?;
+        // Safety: the routines were looked up, and the bootloader copied, while XIP was on
+        unsafe { flash_operation(&functions, serial, &operation) };
+        Ok(())
+    })
+}
+
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_operation(functions: &FlashFunctions, serial: bool, operation: &Operation) {
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    let divider = (*pac::XIP_SSI::ptr()).baudr.as_ptr().read_volatile();
+    (functions.xip.connect_internal_flash)();
+    (functions.xip.flash_exit_xip)();
+    match *operation {
+        Operation::Erase { offset, len } => {
+            (functions.flash_range_erase)(offset, len as usize, BLOCK_SIZE, BLOCK_ERASE_CMD)
+        }
+        Operation::Program { offset, data, len } => {
+            (functions.flash_range_program)(offset, data, len as usize)
+        }
+        Operation::Command { buf, len } => {
+            let ssi = &*pac::XIP_SSI::ptr();
+            let chip_select = &(*pac::IO_QSPI::ptr()).gpio_qspiss.gpio_ctrl;
+            chip_select.modify(|_, w| w.outover().low());
+            let mut sent = 0;
+            let mut received = 0;
+            while received < len {
+                let status = ssi.sr.read();
+                if status.tfnf().bit_is_set() && sent < len && sent - received < MAX_IN_FLIGHT {
+                    ssi.dr0.as_ptr().write_volatile(u32::from(*buf.add(sent)));
+                    sent += 1;
+                }
+                if status.rfne().bit_is_set() {
+                    *buf.add(received) = ssi.dr0.as_ptr().read_volatile() as u8;
+                    received += 1;
+                }
+            }
+            chip_select.modify(|_, w| w.outover().high());
+        }
+    }
+    // This also gives the chip select back to the SSI, but enables the cache: it's disabled
+    // again if it was, in particular when it's used as SRAM, whose contents the flush keeps
+    let xip_ctrl = &(*pac::XIP_CTRL::ptr()).ctrl;
+    let cache_ctrl = xip_ctrl.as_ptr().read_volatile();
+    (functions.flash_flush_cache)();
+    xip_ctrl.as_ptr().write_volatile(cache_ctrl);
+    ssi::reenter_xip(&functions.xip, serial, divider);
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
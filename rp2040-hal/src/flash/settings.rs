@@ -0,0 +1,367 @@
+//! Key-value settings stored in flash
+//!
+//! [`Settings`] emulates a small EEPROM in two sectors of a [`FlashRegion`], to keep
+//! calibration or configuration data across resets without a filesystem. Values of up to
+//! [`MAX_VALUE_LEN`] bytes are stored under 16-bit keys:
+//!
+//! ```no_run
+//! use rp2040_hal::flash::{settings::Settings, FlashRegion, SECTOR_SIZE};
+//!
+//! const BRIGHTNESS: u16 = 1;
+//!
+//! // The last two sectors of a 2MB flash
+//! // Safety: no DMA channel reads from flash, and this is the only region
+//! let region = unsafe { FlashRegion::new(2 * 1024 * 1024 - 2 * SECTOR_SIZE, 2 * SECTOR_SIZE) };
+//! let mut settings = Settings::new(region.unwrap()).unwrap();
+//!
+//! let mut brightness = [128];
+//! if settings.get(BRIGHTNESS, &mut brightness).unwrap().is_none() {
+//!     settings.set(BRIGHTNESS, &brightness).unwrap();
+//! }
+//! ```
+//!
+//! Each change is appended to the active sector as a record, protected by a CRC, and the last
+//! record of a key holds its value. Once the active sector is full, the current values are
+//! copied to the other sector, which becomes the active one, and the old sector is erased. The
+//! erases are spread over both sectors, and only happen once per sector worth of changes.
+//!
+//! A change interrupted by a reset is lost, but leaves the previous values intact: a torn
+//! record fails its CRC and is dropped, and a copy to the other sector only takes effect once
+//! it's complete.
+
+use super::{Error as FlashError, FlashRegion, SECTOR_SIZE};
+
+/// Marks a sector holding records, "RPKV"
+const MAGIC: u32 = 0x564b_5052;
+/// The magic number, then the generation counting the copies between the sectors
+const SECTOR_HEADER_SIZE: u32 = 8;
+/// The key, the length of the value and the CRC of both and of the value
+const RECORD_HEADER_SIZE: u32 = 8;
+/// Key of erased flash, which ends the records
+const ERASED_KEY: u16 = 0xffff;
+/// Flag in the length of a record removing its key
+const DELETED: u16 = 0x8000;
+
+/// The longest value which can be stored
+pub const MAX_VALUE_LEN: usize = 1024;
+
+/// Error of the settings store
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The flash operation failed, or the region is smaller than two sectors
+    Flash(FlashError),
+    /// The key 0xffff is reserved
+    InvalidKey,
+    /// The value is longer than [`MAX_VALUE_LEN`]
+    ValueTooLong,
+    /// The buffer is shorter than the value
+    BufferTooSmall,
+    /// The current values don't fit in a sector
+    Full,
+}
+
+impl From<FlashError> for Error {
+    fn from(error: FlashError) -> Self {
+        Error::Flash(error)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Record {
+    key: u16,
+    len: u16,
+    deleted: bool,
+    crc: u32,
+}
+
+impl Record {
+    fn parse(header: &[u8; RECORD_HEADER_SIZE as usize]) -> Self {
+        let len = u16::from_le_bytes([header[2], header[3]]);
+        Self {
+            key: u16::from_le_bytes([header[0], header[1]]),
+            len: len & !DELETED,
+            deleted: len & DELETED != 0,
+            crc: u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+        }
+    }
+
+    /// Size of the record in flash
+    fn size(&self) -> u32 {
+        RECORD_HEADER_SIZE + u32::from(self.len)
+    }
+}
+
+/// CRC-32, as used by Ethernet and zip, computed bytewise to not need a table in flash
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// A key-value store in the first two sectors of a region, see the
+/// [module level documentation](self)
+pub struct Settings {
+    region: FlashRegion,
+    /// Offset of the active sector in the region
+    active: u32,
+    generation: u32,
+    /// End of the valid records in the active sector
+    end: u32,
+    /// A torn record follows the valid ones, so nothing can be appended until the next copy
+    torn: bool,
+}
+
+impl Settings {
+    /// Open the store, or create an empty one if neither sector holds one
+    pub fn new(region: FlashRegion) -> Result<Self, Error> {
+        if region.len() < 2 * SECTOR_SIZE {
+            return Err(FlashError::OutOfBounds.into());
+        }
+        let mut settings = Self {
+            region,
+            active: 0,
+            generation: 0,
+            end: SECTOR_HEADER_SIZE,
+            torn: false,
+        };
+        let first = settings.sector_generation(0)?;
+        let second = settings.sector_generation(SECTOR_SIZE)?;
+        let (active, generation) = match (first, second) {
+            // Both are valid if a reset happened before the old sector was erased
+            (Some(first), Some(second)) if (second.wrapping_sub(first) as i32) > 0 => {
+                (SECTOR_SIZE, second)
+            }
+            (Some(first), _) => (0, first),
+            (None, Some(second)) => (SECTOR_SIZE, second),
+            (None, None) => {
+                settings.region.erase(0, SECTOR_SIZE)?;
+                settings.write_sector_header(0, 0)?;
+                (0, 0)
+            }
+        };
+        settings.active = active;
+        settings.generation = generation;
+        settings.find_end()?;
+        Ok(settings)
+    }
+
+    /// Read the value of `key` into `buf`, returning its length, or `None` if the key isn't set
+    pub fn get(&self, key: u16, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let (offset, record) = match self.find(key)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let len = usize::from(record.len);
+        let buf = buf.get_mut(..len).ok_or(Error::BufferTooSmall)?;
+        self.region
+            .read(self.active + offset + RECORD_HEADER_SIZE, buf)?;
+        Ok(Some(len))
+    }
+
+    /// Returns true if `key` is set
+    pub fn contains(&self, key: u16) -> Result<bool, Error> {
+        Ok(self.find(key)?.is_some())
+    }
+
+    /// Set the value of `key`
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error> {
+        if key == ERASED_KEY {
+            return Err(Error::InvalidKey);
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::ValueTooLong);
+        }
+        self.append(key, value, false)
+    }
+
+    /// Remove `key`, if it's set
+    pub fn remove(&mut self, key: u16) -> Result<(), Error> {
+        if self.find(key)?.is_none() {
+            return Ok(());
+        }
+        self.append(key, &[], true)
+    }
+
+    /// Release the flash region
+    pub fn free(self) -> FlashRegion {
+        self.region
+    }
+
+    fn sector_generation(&self, sector: u32) -> Result<Option<u32>, Error> {
+        let mut header = [0; SECTOR_HEADER_SIZE as usize];
+        self.region.read(sector, &mut header)?;
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let generation = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        Ok(if magic == MAGIC {
+            Some(generation)
+        } else {
+            None
+        })
+    }
+
+    fn write_sector_header(&mut self, sector: u32, generation: u32) -> Result<(), Error> {
+        let mut header = [0; SECTOR_HEADER_SIZE as usize];
+        header[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..].copy_from_slice(&generation.to_le_bytes());
+        self.region.write(sector, &header)?;
+        Ok(())
+    }
+
+    fn read_header(&self, offset: u32) -> Result<[u8; RECORD_HEADER_SIZE as usize], Error> {
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        self.region.read(self.active + offset, &mut header)?;
+        Ok(header)
+    }
+
+    fn record(&self, offset: u32) -> Result<Record, Error> {
+        Ok(Record::parse(&self.read_header(offset)?))
+    }
+
+    fn crc(&self, offset: u32, header: &[u8; RECORD_HEADER_SIZE as usize]) -> Result<u32, Error> {
+        let len = u32::from(Record::parse(header).len);
+        let mut crc = crc32_update(!0, &header[..4]);
+        let mut chunk = [0; 64];
+        let mut done = 0;
+        while done < len {
+            let count = (len - done).min(chunk.len() as u32);
+            let chunk = &mut chunk[..count as usize];
+            self.region
+                .read(self.active + offset + RECORD_HEADER_SIZE + done, chunk)?;
+            crc = crc32_update(crc, chunk);
+            done += count;
+        }
+        Ok(!crc)
+    }
+
+    /// Check the records of the active sector, up to erased flash
+    fn find_end(&mut self) -> Result<(), Error> {
+        let mut offset = SECTOR_HEADER_SIZE;
+        self.torn = false;
+        while offset + RECORD_HEADER_SIZE <= SECTOR_SIZE {
+            let header = self.read_header(offset)?;
+            if header == [0xff; RECORD_HEADER_SIZE as usize] {
+                break;
+            }
+            let record = Record::parse(&header);
+            if record.key == ERASED_KEY
+                || usize::from(record.len) > MAX_VALUE_LEN
+                || offset + record.size() > SECTOR_SIZE
+                || self.crc(offset, &header)? != record.crc
+            {
+                self.torn = true;
+                break;
+            }
+            offset += record.size();
+        }
+        self.end = offset;
+        Ok(())
+    }
+
+    /// Find the last record of `key`, unless it removes the key
+    fn find(&self, key: u16) -> Result<Option<(u32, Record)>, Error> {
+        let mut found = None;
+        let mut offset = SECTOR_HEADER_SIZE;
+        while offset < self.end {
+            let record = self.record(offset)?;
+            if record.key == key {
+                found = Some((offset, record));
+            }
+            offset += record.size();
+        }
+        Ok(found.filter(|(_, record)| !record.deleted))
+    }
+
+    /// Find the first record from `offset` holding the current value of a key other than `skip`
+    fn next_current(&self, mut offset: u32, skip: u16) -> Result<Option<(u32, Record)>, Error> {
+        while offset < self.end {
+            let record = self.record(offset)?;
+            if !record.deleted && record.key != skip {
+                let (key, mut next) = (record.key, offset + record.size());
+                let mut latest = true;
+                while latest && next < self.end {
+                    let other = self.record(next)?;
+                    latest = other.key != key;
+                    next += other.size();
+                }
+                if latest {
+                    return Ok(Some((offset, record)));
+                }
+            }
+            offset += record.size();
+        }
+        Ok(None)
+    }
+
+    fn append(&mut self, key: u16, value: &[u8], deleted: bool) -> Result<(), Error> {
+        let size = RECORD_HEADER_SIZE + value.len() as u32;
+        if self.torn || self.end + size > SECTOR_SIZE {
+            self.copy_to_other_sector(key, size)?;
+        }
+
+        let len = value.len() as u16 | if deleted { DELETED } else { 0 };
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        header[..2].copy_from_slice(&key.to_le_bytes());
+        header[2..4].copy_from_slice(&len.to_le_bytes());
+        let crc = !crc32_update(crc32_update(!0, &header[..4]), value);
+        header[4..].copy_from_slice(&crc.to_le_bytes());
+
+        // The header goes first, so that a torn value fails the CRC
+        self.region.write(self.active + self.end, &header)?;
+        self.region
+            .write(self.active + self.end + RECORD_HEADER_SIZE, value)?;
+        self.end += size;
+        Ok(())
+    }
+
+    /// Copy the current values but the one of `skip` to the other sector, leaving `extra`
+    /// bytes for its new record
+    fn copy_to_other_sector(&mut self, skip: u16, extra: u32) -> Result<(), Error> {
+        let mut needed = SECTOR_HEADER_SIZE + extra;
+        let mut next = self.next_current(SECTOR_HEADER_SIZE, skip)?;
+        while let Some((offset, record)) = next {
+            needed += record.size();
+            next = self.next_current(offset + record.size(), skip)?;
+        }
+        if needed > SECTOR_SIZE {
+            return Err(Error::Full);
+        }
+
+        let target = SECTOR_SIZE - self.active;
+        self.region.erase(target, SECTOR_SIZE)?;
+        let mut end = SECTOR_HEADER_SIZE;
+        let mut chunk = [0; 64];
+        let mut next = self.next_current(SECTOR_HEADER_SIZE, skip)?;
+        while let Some((offset, record)) = next {
+            let mut done = 0;
+            while done < record.size() {
+                let count = (record.size() - done).min(chunk.len() as u32);
+                let chunk = &mut chunk[..count as usize];
+                self.region.read(self.active + offset + done, chunk)?;
+                self.region.write(target + end + done, chunk)?;
+                done += count;
+            }
+            end += record.size();
+            next = self.next_current(offset + record.size(), skip)?;
+        }
+
+        // The copy only takes effect with its header
+        let generation = self.generation.wrapping_add(1);
+        self.write_sector_header(target, generation)?;
+        let old = self.active;
+        self.active = target;
+        self.generation = generation;
+        self.end = end;
+        self.torn = false;
+        self.region.erase(old, SECTOR_SIZE)?;
+        Ok(())
+    }
+}
@@ -42,6 +42,7 @@ pub use dynpin::*;
 mod reg;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// The amount of current that a pin can drive when used as an output
 pub enum OutputDriveStrength {
     /// 2 mA
@@ -55,6 +56,7 @@ pub enum OutputDriveStrength {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// The slew rate of a pin when used as an output
 pub enum OutputSlewRate {
     /// Slew slow
@@ -64,6 +66,7 @@ pub enum OutputSlewRate {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Interrupt kind
 pub enum Interrupt {
     /// While low
@@ -77,6 +80,7 @@ pub enum Interrupt {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Interrupt override state.
 pub enum InterruptOverride {
     /// Don't invert the interrupt.
@@ -90,6 +94,7 @@ pub enum InterruptOverride {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Input override state.
 pub enum InputOverride {
     /// Don't invert the peripheral input.
@@ -103,6 +108,7 @@ pub enum InputOverride {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Output enable override state.
 pub enum OutputEnableOverride {
     /// Use the original output enable signal from selected peripheral.
@@ -116,6 +122,7 @@ pub enum OutputEnableOverride {
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Output override state.
 pub enum OutputOverride {
     /// Use the original output signal from selected peripheral.
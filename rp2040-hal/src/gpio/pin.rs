@@ -587,6 +587,21 @@ where
         self.regs.set_interrupt_enabled(interrupt, enabled);
     }
 
+    /// Is dormant wake enabled.
+    #[inline]
+    pub fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_enabled(interrupt)
+    }
+
+    /// Enable or disable waking the chip from dormant mode on interrupt.
+    ///
+    /// This is independent from [`set_interrupt_enabled`](Self::set_interrupt_enabled), see
+    /// [`sleep::dormant`](crate::sleep::dormant).
+    #[inline]
+    pub fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_dormant_wake_enabled(interrupt, enabled);
+    }
+
     /// Is interrupt forced.
     #[inline]
     pub fn is_interrupt_forced(&self, interrupt: Interrupt) -> bool {
@@ -940,6 +955,94 @@ where
     }
 }
 
+#[cfg(feature = "eh1_0")]
+impl<I, C> eh1_0::digital::ErrorType for Pin<I, Output<C>>
+where
+    I: PinId,
+    C: OutputConfig,
+{
+    type Error = Infallible;
+}
+
+#[cfg(feature = "eh1_0")]
+impl<I, C> eh1_0::digital::OutputPin for Pin<I, Output<C>>
+where
+    I: PinId,
+    C: OutputConfig,
+{
+    #[inline]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self._set_high();
+        Ok(())
+    }
+    #[inline]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self._set_low();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh1_0")]
+impl<I, C> eh1_0::digital::StatefulOutputPin for Pin<I, Output<C>>
+where
+    I: PinId,
+    C: OutputConfig,
+{
+    #[inline]
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_set_high())
+    }
+    #[inline]
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_set_low())
+    }
+    #[inline]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self._toggle();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh1_0")]
+impl<I> eh1_0::digital::InputPin for Pin<I, ReadableOutput>
+where
+    I: PinId,
+{
+    #[inline]
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_high())
+    }
+    #[inline]
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_low())
+    }
+}
+
+#[cfg(feature = "eh1_0")]
+impl<I, C> eh1_0::digital::ErrorType for Pin<I, Input<C>>
+where
+    I: PinId,
+    C: InputConfig,
+{
+    type Error = Infallible;
+}
+
+#[cfg(feature = "eh1_0")]
+impl<I, C> eh1_0::digital::InputPin for Pin<I, Input<C>>
+where
+    I: PinId,
+    C: InputConfig,
+{
+    #[inline]
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_high())
+    }
+    #[inline]
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self._is_low())
+    }
+}
+
 //==============================================================================
 //  Pin definitions
 //==============================================================================
@@ -1048,6 +1151,34 @@ gpio!(
 
 pub use bank0::Pins; // this is probably the default everyone is going to want
 
+impl bank0::Pins {
+    /// Disable the input buffers and the pulls of all the pins, to save power
+    ///
+    /// Out of reset, every pad has its input buffer enabled and is pulled down. An input
+    /// buffer of a floating pin can draw current, and a pull draws current from a pin driven
+    /// the other way by the board, so unused pins are best left with neither.
+    ///
+    /// Call this before taking the pins: each pin taken afterwards is configured by its
+    /// conversion into another mode, while the pins left unused stay floating with no input.
+    /// This includes pins taken but kept in their reset mode, which aren't pulled down anymore.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{gpio::Pins, pac, Sio};
+    /// let mut pac = pac::Peripherals::take().unwrap();
+    /// let sio = Sio::new(pac.SIO);
+    /// let mut pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+    /// pins.set_unused_low_power();
+    /// let led = pins.gpio25.into_push_pull_output();
+    /// ```
+    pub fn set_unused_low_power(&mut self) {
+        // Safety: self owns the pads of the bank
+        let pads = unsafe { &*pac::PADS_BANK0::ptr() };
+        for pad in pads.gpio.iter() {
+            pad.modify(|_, w| w.ie().clear_bit().pue().clear_bit().pde().clear_bit());
+        }
+    }
+}
+
 gpio!(
     Qspi, [ Xip ], [
         Sck: (0, "sck", PullDownDisabled),
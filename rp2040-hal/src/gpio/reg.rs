@@ -339,6 +339,39 @@ pub(super) unsafe trait RegisterInterface {
         }
     }
 
+    /// Is dormant wake enabled.
+    #[inline]
+    fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        let num = self.id().num as usize;
+        unsafe {
+            let io = &(*pac::IO_BANK0::ptr());
+            // There are four bits for each GPIO pin (one for each enumerator
+            // in the `Interrupt` enum). There are therefore eight pins per
+            // 32-bit register, and four registers in total.
+            let bit_in_reg = num % 8 * 4 + interrupt as usize;
+            (io.dormant_wake_inte[num >> 3].read().bits() & (1 << bit_in_reg)) != 0
+        }
+    }
+
+    /// Enable or disable dormant wake.
+    #[inline]
+    fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        let num = self.id().num as usize;
+        unsafe {
+            let io = &(*pac::IO_BANK0::ptr());
+            // There are four bits for each GPIO pin (one for each enumerator
+            // in the `Interrupt` enum). There are therefore eight pins per
+            // 32-bit register, and four registers in total.
+            let reg = io.dormant_wake_inte[num >> 3].as_ptr();
+            let bit_in_reg = num % 8 * 4 + interrupt as usize;
+            if enabled {
+                write_bitmask_set(reg, 1 << bit_in_reg);
+            } else {
+                write_bitmask_clear(reg, 1 << bit_in_reg);
+            }
+        }
+    }
+
     /// Is interrupt forced.
     #[inline]
     fn is_interrupt_forced(&self, interrupt: Interrupt) -> bool {
@@ -42,10 +42,32 @@
 //!
 //! See [examples/i2c.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/i2c.rs)
 //! for a complete example
+//!
+//! ## Async
+//!
+//! With the `async` feature, controllers implement the `I2c` trait of embedded-hal-async,
+//! waiting for the FIFOs and the end of the transfer with interrupts. Call
+//! [`on_i2c0_interrupt`] or [`on_i2c1_interrupt`] from the interrupt handler of the
+//! block, and unmask the interrupt in the NVIC:
+//! ```ignore
+//! #[interrupt]
+//! fn I2C1_IRQ() {
+//!     rp2040_hal::i2c::on_i2c1_interrupt();
+//! }
+//!
+//! async fn read_register(i2c: &mut impl embedded_hal_async::i2c::I2c) -> u8 {
+//!     let mut value = [0];
+//!     i2c.write_read(0x2c, &[0x0f], &mut value).await.unwrap();
+//!     value[0]
+//! }
+//! ```
+//!
+//! Dropping a transaction before it completes leaves the transfer unfinished on the bus.
 
 use core::{marker::PhantomData, ops::Deref};
 
 use crate::{
+    clocks::GatedPeripheral,
     gpio::pin::bank0::{
         BankPinId, Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17,
         Gpio18, Gpio19, Gpio2, Gpio20, Gpio21, Gpio22, Gpio23, Gpio24, Gpio25, Gpio26, Gpio27,
@@ -140,6 +162,32 @@ impl eh1_0_alpha::i2c::Error for Error {
     }
 }
 
+#[cfg(feature = "eh1_0")]
+impl eh1_0::i2c::Error for Error {
+    fn kind(&self) -> eh1_0::i2c::ErrorKind {
+        use eh1_0::i2c::{ErrorKind, NoAcknowledgeSource};
+        match &self {
+            Error::Abort(v) if v & 1<<12 != 0 // ARB_LOST
+                => ErrorKind::ArbitrationLoss,
+            Error::Abort(v) if v & 1<<7 != 0 // ABRT_SBYTE_ACKDET
+                => ErrorKind::Bus,
+            Error::Abort(v) if v & 1<<6 != 0 // ABRT_HS_ACKDET
+                => ErrorKind::Bus,
+            Error::Abort(v) if v & 1<<4 != 0 // ABRT_GCALL_NOACK
+                => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::Abort(v) if v & 1<<3 != 0 // ABRT_TXDATA_NOACK
+                => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            Error::Abort(v) if v & 1<<2 != 0 // ABRT_10ADDR2_NOACK
+                => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::Abort(v) if v & 1<<1 != 0 // ABRT_10ADDR1_NOACK
+                => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::Abort(v) if v & 1<<0 != 0 // ABRT_7B_ADDR_NOACK
+                => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
 /// SCL pin
 pub trait SclPin<I2C>: Sealed {}
 
@@ -225,7 +273,7 @@ fn i2c_reserved_addr(addr: u16) -> bool {
 
 impl<Block, Sda, Scl, Mode> I2C<Block, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Mode>
 where
-    Block: SubsystemReset + Deref<Target = I2CBlock>,
+    Block: SubsystemReset + GatedPeripheral + Deref<Target = I2CBlock>,
     Sda: PinId + BankPinId,
     Scl: PinId + BankPinId,
     Mode: I2CMode,
@@ -237,6 +285,7 @@ where
         resets: &mut RESETS,
     ) -> (Block, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>)) {
         self.i2c.reset_bring_down(resets);
+        crate::sleep::peripheral_disabled::<Block>();
 
         (self.i2c, self.pins)
     }
@@ -309,3 +358,55 @@ hal! {
     I2C0: (i2c0),
     I2C1: (i2c1),
 }
+
+#[cfg(feature = "async")]
+const INTR_RX_FULL: u32 = 1 << 2;
+#[cfg(feature = "async")]
+const INTR_TX_EMPTY: u32 = 1 << 4;
+#[cfg(feature = "async")]
+const INTR_TX_ABRT: u32 = 1 << 6;
+#[cfg(feature = "async")]
+const INTR_STOP_DET: u32 = 1 << 9;
+
+#[cfg(feature = "async")]
+static WAKERS: [crate::async_utils::WakerSlot; 2] = [
+    crate::async_utils::WakerSlot::new(),
+    crate::async_utils::WakerSlot::new(),
+];
+
+/// The waker of the task waiting for `block`
+#[cfg(feature = "async")]
+fn waker(block: &I2CBlock) -> &'static crate::async_utils::WakerSlot {
+    if block as *const I2CBlock == I2C0::ptr() {
+        &WAKERS[0]
+    } else {
+        &WAKERS[1]
+    }
+}
+
+#[cfg(feature = "async")]
+fn on_interrupt(block: &I2CBlock) {
+    // Safety: 0 masks all the interrupts
+    block.ic_intr_mask.write(|w| unsafe { w.bits(0) });
+    waker(block).wake();
+}
+
+/// Wake the task waiting for I2C0 in an async transaction
+///
+/// Call this from the `I2C0_IRQ` interrupt handler. As the interrupts are level
+/// triggered, they are masked here and unmasked again when the waiting task is polled.
+#[cfg(feature = "async")]
+pub fn on_i2c0_interrupt() {
+    // Safety: only the interrupt mask is written, which the driver unmasks again when polled
+    on_interrupt(unsafe { &*I2C0::ptr() })
+}
+
+/// Wake the task waiting for I2C1 in an async transaction
+///
+/// Call this from the `I2C1_IRQ` interrupt handler. As the interrupts are level
+/// triggered, they are masked here and unmasked again when the waiting task is polled.
+#[cfg(feature = "async")]
+pub fn on_i2c1_interrupt() {
+    // Safety: only the interrupt mask is written, which the driver unmasks again when polled
+    on_interrupt(unsafe { &*I2C1::ptr() })
+}
@@ -1,6 +1,7 @@
 use core::{marker::PhantomData, ops::Deref};
 
 use crate::{
+    clocks::GatedPeripheral,
     gpio::pin::bank0::BankPinId,
     gpio::pin::{FunctionI2C, Pin, PinId},
     resets::SubsystemReset,
@@ -14,8 +15,11 @@ use eh1_0_alpha::i2c as eh1;
 
 use super::{i2c_reserved_addr, Controller, Error, SclPin, SdaPin, I2C};
 
-impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: PinId + BankPinId>
-    I2C<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Controller>
+impl<
+        T: SubsystemReset + GatedPeripheral + Deref<Target = Block>,
+        Sda: PinId + BankPinId,
+        Scl: PinId + BankPinId,
+    > I2C<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Controller>
 {
     /// Configures the I2C peripheral to work in controller mode
     pub fn new_controller(
@@ -52,56 +56,13 @@ impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: Pin
         i2c.ic_tx_tl.write(|w| unsafe { w.tx_tl().bits(0) });
         i2c.ic_rx_tl.write(|w| unsafe { w.rx_tl().bits(0) });
 
-        let freq_in = system_clock.to_Hz();
-
-        // There are some subtleties to I2C timing which we are completely ignoring here
-        // See: https://github.com/raspberrypi/pico-sdk/blob/bfcbefafc5d2a210551a4d9d80b4303d4ae0adf7/src/rp2_common/hardware_i2c/i2c.c#L69
-        let period = (freq_in + freq / 2) / freq;
-        let lcnt = period * 3 / 5; // spend 3/5 (60%) of the period low
-        let hcnt = period - lcnt; // and 2/5 (40%) of the period high
-
-        // Check for out-of-range divisors:
-        assert!(hcnt <= 0xffff);
-        assert!(lcnt <= 0xffff);
-        assert!(hcnt >= 8);
-        assert!(lcnt >= 8);
-
-        // Per I2C-bus specification a device in standard or fast mode must
-        // internally provide a hold time of at least 300ns for the SDA signal to
-        // bridge the undefined region of the falling edge of SCL. A smaller hold
-        // time of 120ns is used for fast mode plus.
-        let sda_tx_hold_count = if freq < 1000000 {
-            // sda_tx_hold_count = freq_in [cycles/s] * 300ns * (1s / 1e9ns)
-            // Reduce 300/1e9 to 3/1e7 to avoid numbers that don't fit in uint.
-            // Add 1 to avoid division truncation.
-            ((freq_in * 3) / 10000000) + 1
-        } else {
-            // fast mode plus requires a clk_in > 32MHz
-            assert!(freq_in >= 32_000_000);
-
-            // sda_tx_hold_count = freq_in [cycles/s] * 120ns * (1s / 1e9ns)
-            // Reduce 120/1e9 to 3/25e6 to avoid numbers that don't fit in uint.
-            // Add 1 to avoid division truncation.
-            ((freq_in * 3) / 25000000) + 1
-        };
-        assert!(sda_tx_hold_count <= lcnt - 2);
-
-        unsafe {
-            i2c.ic_fs_scl_hcnt
-                .write(|w| w.ic_fs_scl_hcnt().bits(hcnt as u16));
-            i2c.ic_fs_scl_lcnt
-                .write(|w| w.ic_fs_scl_lcnt().bits(lcnt as u16));
-            i2c.ic_fs_spklen.write(|w| {
-                w.ic_fs_spklen()
-                    .bits(if lcnt < 16 { 1 } else { (lcnt / 16) as u8 })
-            });
-            i2c.ic_sda_hold
-                .modify(|_r, w| w.ic_sda_tx_hold().bits(sda_tx_hold_count as u16));
-        }
+        set_timing(&i2c, freq, system_clock.to_Hz());
 
         // Enable I2C block
         i2c.ic_enable.write(|w| w.enable().enabled());
 
+        crate::sleep::peripheral_enabled::<T>();
+
         Self {
             i2c,
             pins: (sda_pin, scl_pin),
@@ -109,7 +70,72 @@ impl<T: SubsystemReset + Deref<Target = Block>, Sda: PinId + BankPinId, Scl: Pin
         }
     }
 }
+
+/// Configure the SCL timing for a bus frequency of `freq`, with `freq_in` the frequency of
+/// clk_sys. The block has to be disabled.
+fn set_timing(i2c: &Block, freq: u32, freq_in: u32) {
+    // There are some subtleties to I2C timing which we are completely ignoring here
+    // See: https://github.com/raspberrypi/pico-sdk/blob/bfcbefafc5d2a210551a4d9d80b4303d4ae0adf7/src/rp2_common/hardware_i2c/i2c.c#L69
+    let period = (freq_in + freq / 2) / freq;
+    let lcnt = period * 3 / 5; // spend 3/5 (60%) of the period low
+    let hcnt = period - lcnt; // and 2/5 (40%) of the period high
+
+    // Check for out-of-range divisors:
+    assert!(hcnt <= 0xffff);
+    assert!(lcnt <= 0xffff);
+    assert!(hcnt >= 8);
+    assert!(lcnt >= 8);
+
+    // Per I2C-bus specification a device in standard or fast mode must
+    // internally provide a hold time of at least 300ns for the SDA signal to
+    // bridge the undefined region of the falling edge of SCL. A smaller hold
+    // time of 120ns is used for fast mode plus.
+    let sda_tx_hold_count = if freq < 1000000 {
+        // sda_tx_hold_count = freq_in [cycles/s] * 300ns * (1s / 1e9ns)
+        // Reduce 300/1e9 to 3/1e7 to avoid numbers that don't fit in uint.
+        // Add 1 to avoid division truncation.
+        ((freq_in * 3) / 10000000) + 1
+    } else {
+        // fast mode plus requires a clk_in > 32MHz
+        assert!(freq_in >= 32_000_000);
+
+        // sda_tx_hold_count = freq_in [cycles/s] * 120ns * (1s / 1e9ns)
+        // Reduce 120/1e9 to 3/25e6 to avoid numbers that don't fit in uint.
+        // Add 1 to avoid division truncation.
+        ((freq_in * 3) / 25000000) + 1
+    };
+    assert!(sda_tx_hold_count <= lcnt - 2);
+
+    unsafe {
+        i2c.ic_fs_scl_hcnt
+            .write(|w| w.ic_fs_scl_hcnt().bits(hcnt as u16));
+        i2c.ic_fs_scl_lcnt
+            .write(|w| w.ic_fs_scl_lcnt().bits(lcnt as u16));
+        i2c.ic_fs_spklen.write(|w| {
+            w.ic_fs_spklen()
+                .bits(if lcnt < 16 { 1 } else { (lcnt / 16) as u8 })
+        });
+        i2c.ic_sda_hold
+            .modify(|_r, w| w.ic_sda_tx_hold().bits(sda_tx_hold_count as u16));
+    }
+}
+
 impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
+    /// Change the bus frequency, given the frequency of the system clock
+    ///
+    /// Call this after clk_sys changed, e.g. with
+    /// [`ClocksManager::set_sys_clock`](crate::clocks::ClocksManager::set_sys_clock), to keep
+    /// the bus frequency. It must not be called during a transfer.
+    pub fn set_bus_frequency(&mut self, freq: HertzU32, system_clock: HertzU32) {
+        let freq = freq.to_Hz();
+        assert!(freq <= 1_000_000);
+        assert!(freq > 0);
+
+        self.i2c.ic_enable.write(|w| w.enable().disabled());
+        set_timing(&self.i2c, freq, system_clock.to_Hz());
+        self.i2c.ic_enable.write(|w| w.enable().enabled());
+    }
+
     fn validate(
         addr: u16,
         opt_tx_empty: Option<bool>,
@@ -168,21 +194,7 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
             // wait until there is space in the FIFO to write the next byte
             while self.tx_fifo_full() {}
 
-            self.i2c.ic_data_cmd.write(|w| {
-                if force_restart && first {
-                    w.restart().enable();
-                } else {
-                    w.restart().disable();
-                }
-
-                if do_stop && last {
-                    w.stop().enable();
-                } else {
-                    w.stop().disable();
-                }
-
-                w.cmd().read()
-            });
+            self.push_read(force_restart && first, do_stop && last);
 
             while self.i2c.ic_rxflr.read().bits() == 0 {
                 if let Some(abort_reason) = self.read_and_clear_abort_reason() {
@@ -196,18 +208,42 @@ impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
         Ok(())
     }
 
+    /// Queue the read of a byte
+    fn push_read(&mut self, restart: bool, stop: bool) {
+        self.i2c.ic_data_cmd.write(|w| {
+            if restart {
+                w.restart().enable();
+            } else {
+                w.restart().disable();
+            }
+
+            if stop {
+                w.stop().enable();
+            } else {
+                w.stop().disable();
+            }
+
+            w.cmd().read()
+        });
+    }
+
+    /// Queue the write of `byte`
+    fn push_write(&mut self, byte: u8, stop: bool) {
+        self.i2c.ic_data_cmd.write(|w| {
+            if stop {
+                w.stop().enable();
+            } else {
+                w.stop().disable();
+            }
+            unsafe { w.dat().bits(byte) }
+        });
+    }
+
     fn write_internal(&mut self, bytes: &[u8], do_stop: bool) -> Result<(), Error> {
         for (i, byte) in bytes.iter().enumerate() {
             let last = i == bytes.len() - 1;
 
-            self.i2c.ic_data_cmd.write(|w| {
-                if do_stop && last {
-                    w.stop().enable();
-                } else {
-                    w.stop().disable();
-                }
-                unsafe { w.dat().bits(*byte) }
-            });
+            self.push_write(*byte, do_stop && last);
 
             // Wait until the transmission of the address/data from the internal
             // shift register has completed. For this to function correctly, the
@@ -361,3 +397,174 @@ impl<T: Deref<Target = Block>, PINS> eh1::blocking::I2c for I2C<T, PINS, Control
         Ok(())
     }
 }
+
+#[cfg(feature = "eh1_0")]
+impl<T: Deref<Target = Block>, PINS> eh1_0::i2c::ErrorType for I2C<T, PINS, Controller> {
+    type Error = Error;
+}
+
+#[cfg(feature = "eh1_0")]
+impl<T: Deref<Target = Block>, PINS> eh1_0::i2c::I2c for I2C<T, PINS, Controller> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [eh1_0::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use eh1_0::i2c::Operation;
+
+        let addr: u16 = address.into();
+        Self::validate_operations(addr, operations)?;
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        self.setup(addr);
+        let lastindex = operations.len() - 1;
+        let mut after_write = false;
+        for (i, operation) in operations.iter_mut().enumerate() {
+            let last = i == lastindex;
+            match operation {
+                // Consecutive reads continue the same read, a read after a write restarts
+                Operation::Read(buf) => {
+                    self.read_internal(buf, after_write, last)?;
+                    after_write = false;
+                }
+                Operation::Write(buf) => {
+                    self.write_internal(buf, last)?;
+                    after_write = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh1_0")]
+impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
+    fn validate_operations(
+        addr: u16,
+        operations: &[eh1_0::i2c::Operation<'_>],
+    ) -> Result<(), Error> {
+        use eh1_0::i2c::Operation;
+
+        Self::validate(addr, None, None)?;
+        for operation in operations.iter() {
+            match operation {
+                Operation::Read(buf) if buf.is_empty() => {
+                    return Err(Error::InvalidReadBufferLength)
+                }
+                Operation::Write(buf) if buf.is_empty() => {
+                    return Err(Error::InvalidWriteBufferLength)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: Deref<Target = Block>, PINS> I2C<T, PINS, Controller> {
+    /// Wait until one of the raw interrupts in `mask` is active
+    async fn wait_for(&mut self, mask: u32) {
+        let waker = super::waker(&self.i2c);
+        let i2c = &self.i2c;
+        crate::async_utils::wait_for(
+            waker,
+            || i2c.ic_raw_intr_stat.read().bits() & mask != 0,
+            // Safety: the mask only selects which interrupts are raised
+            || i2c.ic_intr_mask.write(|w| unsafe { w.bits(mask) }),
+        )
+        .await
+    }
+
+    async fn read_internal_async(
+        &mut self,
+        buffer: &mut [u8],
+        force_restart: bool,
+        do_stop: bool,
+    ) -> Result<(), Error> {
+        let lastindex = buffer.len() - 1;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            let first = i == 0;
+            let last = i == lastindex;
+
+            if self.tx_fifo_full() {
+                self.wait_for(super::INTR_TX_EMPTY).await;
+            }
+
+            self.push_read(force_restart && first, do_stop && last);
+
+            while self.i2c.ic_rxflr.read().bits() == 0 {
+                if let Some(abort_reason) = self.read_and_clear_abort_reason() {
+                    return Err(Error::Abort(abort_reason));
+                }
+                self.wait_for(super::INTR_RX_FULL | super::INTR_TX_ABRT)
+                    .await;
+            }
+
+            *byte = self.i2c.ic_data_cmd.read().dat().bits();
+        }
+
+        Ok(())
+    }
+
+    async fn write_internal_async(&mut self, bytes: &[u8], do_stop: bool) -> Result<(), Error> {
+        for (i, byte) in bytes.iter().enumerate() {
+            let last = i == bytes.len() - 1;
+
+            self.push_write(*byte, do_stop && last);
+
+            // The same sequence as write_internal, waiting for interrupts
+            self.wait_for(super::INTR_TX_EMPTY).await;
+
+            let abort_reason = self.read_and_clear_abort_reason();
+
+            if abort_reason.is_some() || (do_stop && last) {
+                self.wait_for(super::INTR_STOP_DET).await;
+
+                self.i2c.ic_clr_stop_det.read().clr_stop_det();
+            }
+
+            if let Some(abort_reason) = abort_reason {
+                return Err(Error::Abort(abort_reason));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: Deref<Target = Block>, PINS> embedded_hal_async::i2c::I2c for I2C<T, PINS, Controller> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal_async::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use embedded_hal_async::i2c::Operation;
+
+        let addr: u16 = address.into();
+        Self::validate_operations(addr, operations)?;
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        self.setup(addr);
+        let lastindex = operations.len() - 1;
+        let mut after_write = false;
+        for (i, operation) in operations.iter_mut().enumerate() {
+            let last = i == lastindex;
+            match operation {
+                Operation::Read(buf) => {
+                    self.read_internal_async(buf, after_write, last).await?;
+                    after_write = false;
+                }
+                Operation::Write(buf) => {
+                    self.write_internal_async(buf, last).await?;
+                    after_write = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use core::{marker::PhantomData, ops::Deref};
 
 use crate::{
+    clocks::GatedPeripheral,
     gpio::pin::bank0::BankPinId,
     gpio::pin::{FunctionI2C, Pin, PinId},
     resets::SubsystemReset,
@@ -11,6 +12,7 @@ use super::{Peripheral, SclPin, SdaPin, I2C};
 
 /// I2C bus events
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum I2CEvent {
     /// Start condition has been detected.
     Start,
@@ -40,7 +42,7 @@ pub struct I2CPeripheralEventIterator<Block, Pins> {
 
 impl<T, Sda, Scl> I2C<T, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>), Peripheral>
 where
-    T: SubsystemReset + Deref<Target = I2CBlock>,
+    T: SubsystemReset + GatedPeripheral + Deref<Target = I2CBlock>,
     Sda: PinId + BankPinId,
     Scl: PinId + BankPinId,
 {
@@ -87,6 +89,8 @@ where
         // Enable I2C block
         i2c.ic_enable.write(|w| w.enable().enabled());
 
+        crate::sleep::peripheral_enabled::<T>();
+
         I2CPeripheralEventIterator {
             i2c: Self {
                 i2c,
@@ -187,7 +191,7 @@ impl<T: Deref<Target = I2CBlock>, PINS> Iterator for I2CPeripheralEventIterator<
 impl<Block, Sda, Scl>
     I2CPeripheralEventIterator<Block, (Pin<Sda, FunctionI2C>, Pin<Scl, FunctionI2C>)>
 where
-    Block: SubsystemReset + Deref<Target = I2CBlock>,
+    Block: SubsystemReset + GatedPeripheral + Deref<Target = I2CBlock>,
     Sda: PinId + BankPinId,
     Scl: PinId + BankPinId,
 {
@@ -2,6 +2,25 @@
 //!
 //! This is an implementation of the [`embedded-hal`] traits for the RP2040 microcontroller
 //! NOTE This HAL is still under active development. This API will remain volatile until 1.0.0
+//!
+//! ## Critical sections
+//!
+//! With the `critical-section-impl` feature, the HAL provides the implementation of the
+//! [`critical-section`](https://docs.rs/critical-section) crate. It disables interrupts and
+//! takes a hardware spinlock, so that data shared through a `critical_section::Mutex` is also
+//! protected from the other core. `cortex_m::interrupt::free` only masks the interrupts of the
+//! current core, and isn't enough once core 1 is running. Spinlock 31 is reserved for this.
+//!
+//! ## Floating point
+//!
+//! The RP2040 has no FPU, and the soft-float routines of the bootrom are much faster than
+//! those of `compiler-builtins`. The HAL overrides the compiler intrinsics for the `f32` and
+//! `f64` arithmetic, comparisons and conversions, and for math functions such as `sqrtf`, with
+//! the bootrom routines, simply by being linked in. The overrides relying on the `f64` routines
+//! only found in the V2 bootrom are enabled with the `rom-v2-intrinsics` feature, for boards
+//! known to have one. The `disable-intrinsics`
+//! feature goes back to the `compiler-builtins` routines, and disables the overrides of the
+//! integer division and the `mem*` functions as well.
 
 #![warn(missing_docs)]
 #![no_std]
@@ -17,10 +36,16 @@ pub extern crate rp2040_pac as pac;
 mod intrinsics;
 
 pub mod adc;
+#[cfg(feature = "async")]
+mod async_utils;
 pub(crate) mod atomic_register_access;
+#[cfg(feature = "boot2")]
+pub mod boot2;
 pub mod clocks;
+#[cfg(feature = "critical-section-impl")]
 mod critical_section_impl;
 pub mod dma;
+pub mod flash;
 mod float;
 pub mod gpio;
 pub mod i2c;
@@ -34,14 +59,18 @@ pub mod rom_data;
 pub mod rosc;
 pub mod rtc;
 pub mod sio;
+pub mod sleep;
 pub mod spi;
+pub mod sram;
 pub mod ssi;
 pub mod timer;
 pub mod typelevel;
 pub mod uart;
 pub mod usb;
 pub mod vector_table;
+pub mod vreg;
 pub mod watchdog;
+pub mod xip;
 pub mod xosc;
 
 // Provide access to common datastructures to avoid repeating ourselves
@@ -57,6 +86,26 @@ pub use i2c::I2C;
 /// of `main`. As spinlocks are not automatically unlocked on software resets,
 /// this can prevent unexpected deadlocks when running from a debugger.
 pub use rp2040_hal_macros::entry;
+/// Attribute to run a function from RAM instead of flash
+///
+/// The function is placed in the `.data.ram_func` section. cortex-m-rt copies `.data` and its
+/// subsections from flash to RAM at startup, so this needs no change to `memory.x`. The
+/// function isn't inlined, to not end up in a caller running from flash.
+///
+/// RAM functions run at full speed whatever the state of the XIP cache, and keep running while
+/// the flash is unavailable, as long as they only call other RAM functions and the bootrom:
+/// calls to functions in flash, including panics and formatting, still go to flash.
+///
+/// ```no_run
+/// #[rp2040_hal::ram_function]
+/// fn toggle_fast(sio: &rp2040_hal::pac::SIO) {
+///     sio.gpio_out_xor.write(|w| unsafe { w.bits(1 << 2) });
+/// }
+/// ```
+///
+/// The `#[interrupt]` attribute of cortex-m-rt rejects other attributes, so interrupt handlers
+/// are placed in RAM with `#[link_section = ".data.ram_func"]` under `#[interrupt]` instead.
+pub use rp2040_hal_macros::ram_function;
 pub use sio::Sio;
 pub use spi::Spi;
 pub use timer::Timer;
@@ -30,24 +30,88 @@
 //!
 //! ```
 //!
-//! For inter-processor communications, see [`crate::sio::SioFifo`] and [`crate::sio::Spinlock0`]
+//! Core 1 can be stopped with [`Core::reset`], which sends it back to the bootrom to wait for
+//! a new entry point, or [`Core::shutdown`], which keeps it in reset.
+//!
+//! ## Stack placement
+//!
+//! The stack can be any `static`, see [`Stack`]. It can also go into one of the two 4kB SRAM
+//! banks SRAM4 and SRAM5, which the usual `memory.x` leaves out of RAM, with
+//! [`scratch_stack`]: core1 then doesn't compete with core0 for the main banks while accessing
+//! its stack.
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, sio::Sio, multicore::{scratch_stack, Multicore, ScratchBank}};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(pac.SIO);
+//! let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+//! let cores = mc.cores();
+//! // Safety: memory.x only covers the main banks, and SRAM4 isn't used anywhere else
+//! let stack = unsafe { scratch_stack(ScratchBank::Sram4) };
+//! let _test = cores[1].spawn(stack, || loop {});
+//! ```
+//!
+//! Data shared between the cores through a `critical_section::Mutex` is only protected from
+//! the other core with the `critical-section-impl` feature, see the [crate level
+//! documentation](crate#critical-sections).
+//!
+//! For inter-processor communications, see [`crate::sio::SioFifo`] and [`crate::sio::Spinlock0`],
+//! or [`channel`] to pass values of any type.
 //!
 //! For a detailed example, see [examples/multicore_fifo_blink.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/multicore_fifo_blink.rs)
 
 use core::mem::ManuallyDrop;
 use core::sync::atomic::compiler_fence;
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use crate::pac;
 use crate::Sio;
 
+pub mod channel;
+
 /// Errors for multicore operations.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Operation is invalid on this core.
     InvalidCore,
     /// Core was unresponsive to commands.
     Unresponsive,
+    /// The stack is smaller than [`MIN_STACK_SIZE`].
+    StackTooSmall,
+}
+
+/// Smallest stack accepted by [`Core::spawn`], in words
+///
+/// The stack guard takes up to 64 bytes at the bottom of the stack, and the arguments of the
+/// entry point are pushed on top of it.
+pub const MIN_STACK_SIZE: usize = 64;
+
+/// SRAM bank which can hold the stack of core1, see [`scratch_stack`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScratchBank {
+    /// SRAM4, at 0x20040000
+    Sram4,
+    /// SRAM5, at 0x20041000
+    Sram5,
+}
+
+/// Use a whole 4kB SRAM bank outside of the main RAM as a stack
+///
+/// # Safety
+///
+/// The bank must not be part of the memory regions given to the linker, which is the case with
+/// the usual `memory.x` of 256kB of RAM starting at 0x20000000. It must not be used by
+/// anything else either, including another call to this function with the same bank.
+pub unsafe fn scratch_stack(bank: ScratchBank) -> &'static mut [usize] {
+    const BANK_SIZE: usize = 4096 / core::mem::size_of::<usize>();
+
+    let start = match bank {
+        ScratchBank::Sram4 => 0x2004_0000,
+        ScratchBank::Sram5 => 0x2004_1000,
+    };
+    core::slice::from_raw_parts_mut(start as *mut usize, BANK_SIZE)
 }
 
 #[inline(always)]
@@ -124,6 +188,46 @@ impl<'p> Multicore<'p> {
     }
 }
 
+// Whether core 1 runs code spawned by `Core::spawn`
+static CORE1_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if core 1 runs code spawned by [`Core::spawn`]
+pub(crate) fn core1_running() -> bool {
+    CORE1_RUNNING.load(Ordering::Relaxed)
+}
+
+/// Hold core 1 in reset
+fn hold_core1(psm: &mut pac::PSM) {
+    psm.frce_off.modify(|_, w| w.proc1().set_bit());
+    while !psm.frce_off.read().proc1().bit_is_set() {
+        cortex_m::asm::nop();
+    }
+    CORE1_RUNNING.store(false, Ordering::Relaxed);
+    crate::sio::core1_reset();
+    // Core 1 may have been reset within a critical section
+    #[cfg(feature = "critical-section-impl")]
+    unsafe {
+        crate::critical_section_impl::release_core1_lock();
+    }
+}
+
+/// Run `f` with the FIFO interrupt of this core masked, so that its handler doesn't take the
+/// words of the handshake with the bootrom of core 1
+fn with_fifo_interrupt_masked<R>(
+    fifo: &mut crate::sio::SioFifo,
+    f: impl FnOnce(&mut crate::sio::SioFifo) -> R,
+) -> R {
+    let interrupt = crate::sio::SioFifo::interrupt();
+    let enabled = pac::NVIC::is_enabled(interrupt);
+    pac::NVIC::mask(interrupt);
+    let result = f(fifo);
+    if enabled {
+        // Safety: the interrupt was enabled before
+        unsafe { pac::NVIC::unmask(interrupt) };
+    }
+    result
+}
+
 /// A handle for controlling a logical core.
 pub struct Core<'p> {
     inner: Option<(
@@ -142,11 +246,54 @@ impl<'p> Core<'p> {
         }
     }
 
+    /// Returns true if this core runs code, for core 1 since it was spawned
+    ///
+    /// Core 1 waits in the bootrom after a reset, until [`spawn`](Self::spawn) is called.
+    pub fn is_running(&self) -> bool {
+        match self.inner {
+            None => true,
+            Some(..) => CORE1_RUNNING.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset this core, so that it waits in the bootrom for a new entry point
+    ///
+    /// The code running on core 1 is stopped wherever it is. It doesn't run any destructors,
+    /// and the peripherals it uses are left as they are. Only core 1 can be reset.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if let Some((psm, _, fifo)) = self.inner.as_mut() {
+            hold_core1(psm);
+            psm.frce_off.modify(|_, w| w.proc1().clear_bit());
+            // The bootrom writes 0 to the FIFO once it's ready for commands
+            with_fifo_interrupt_masked(fifo, |fifo| fifo.read_blocking());
+            Ok(())
+        } else {
+            Err(Error::InvalidCore)
+        }
+    }
+
+    /// Stop this core and keep it in reset, to save power
+    ///
+    /// [`spawn`](Self::spawn) starts it again. Only core 1 can be shut down.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        if let Some((psm, _, _)) = self.inner.as_mut() {
+            hold_core1(psm);
+            Ok(())
+        } else {
+            Err(Error::InvalidCore)
+        }
+    }
+
     /// Spawn a function on this core.
+    ///
+    /// `stack` has to hold at least [`MIN_STACK_SIZE`] words.
     pub fn spawn<F>(&mut self, stack: &'static mut [usize], entry: F) -> Result<(), Error>
     where
         F: FnOnce() -> bad::Never + Send + 'static,
     {
+        if stack.len() < MIN_STACK_SIZE {
+            return Err(Error::StackTooSmall);
+        }
         if let Some((psm, ppb, fifo)) = self.inner.as_mut() {
             // The first two ignored `u64` parameters are there to take up all of the registers,
             // which means that the rest of the arguments are taken from the stack,
@@ -173,10 +320,7 @@ impl<'p> Core<'p> {
             }
 
             // Reset the core
-            psm.frce_off.modify(|_, w| w.proc1().set_bit());
-            while !psm.frce_off.read().proc1().bit_is_set() {
-                cortex_m::asm::nop();
-            }
+            hold_core1(psm);
             psm.frce_off.modify(|_, w| w.proc1().clear_bit());
 
             // Set up the stack
@@ -218,35 +362,42 @@ impl<'p> Core<'p> {
                 core1_startup::<F> as usize,
             ];
 
-            let mut seq = 0;
-            let mut fails = 0;
-            loop {
-                let cmd = cmd_seq[seq] as u32;
-                if cmd == 0 {
-                    fifo.drain();
-                    cortex_m::asm::sev();
-                }
-                fifo.write_blocking(cmd);
-                let response = fifo.read_blocking();
-                if cmd == response {
-                    seq += 1;
-                } else {
-                    seq = 0;
-                    fails += 1;
-                    if fails > 16 {
-                        // The second core isn't responding, and isn't going to take the entrypoint,
-                        // so we have to drop it ourselves.
-                        drop(ManuallyDrop::into_inner(entry));
-                        return Err(Error::Unresponsive);
+            let launched = with_fifo_interrupt_masked(fifo, |fifo| {
+                let mut seq = 0;
+                let mut fails = 0;
+                loop {
+                    let cmd = cmd_seq[seq] as u32;
+                    if cmd == 0 {
+                        fifo.drain();
+                        cortex_m::asm::sev();
+                    }
+                    fifo.write_blocking(cmd);
+                    let response = fifo.read_blocking();
+                    if cmd == response {
+                        seq += 1;
+                    } else {
+                        seq = 0;
+                        fails += 1;
+                        if fails > 16 {
+                            return false;
+                        }
+                    }
+                    if seq >= cmd_seq.len() {
+                        break;
                     }
                 }
-                if seq >= cmd_seq.len() {
-                    break;
-                }
-            }
 
-            // Wait until the other core has copied `entry` before returning.
-            fifo.read_blocking();
+                // Wait until the other core has copied `entry` before returning.
+                fifo.read_blocking();
+                true
+            });
+            if !launched {
+                // The second core isn't responding, and isn't going to take the entrypoint,
+                // so we have to drop it ourselves.
+                drop(ManuallyDrop::into_inner(entry));
+                return Err(Error::Unresponsive);
+            }
+            CORE1_RUNNING.store(true, Ordering::Relaxed);
 
             Ok(())
         } else {
@@ -0,0 +1,204 @@
+//! Bounded channel between the cores
+//!
+//! A [`Channel`] is a ring buffer of `N` values of any type, to be placed in RAM shared by both
+//! cores. It's split into a [`Sender`] and a [`Receiver`], of which one is moved to core1:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, sio::Sio, multicore::{channel::Channel, Multicore, Stack}};
+//!
+//! struct Reading {
+//!     channel: u8,
+//!     value: u16,
+//! }
+//!
+//! static mut CORE1_STACK: Stack<4096> = Stack::new();
+//!
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut sio = Sio::new(pac.SIO);
+//! let channel = cortex_m::singleton!(: Channel<Reading, 8> = Channel::new()).unwrap();
+//! let (mut tx, mut rx) = channel.split();
+//!
+//! let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+//! let cores = mc.cores();
+//! let _test = cores[1].spawn(unsafe { &mut CORE1_STACK.mem }, move || loop {
+//!     let _ = tx.send(Reading { channel: 0, value: 42 });
+//! });
+//!
+//! let reading = rx.recv_blocking();
+//! ```
+//!
+//! The indices of the ring are only written by one side each, so neither side ever waits for
+//! the other. [`Sender::send`] signals an event after each value, which wakes up a receiver
+//! waiting with [`Receiver::recv_blocking`]. Alternatively [`Sender::send_and_notify`] also
+//! writes a word to the inter-core FIFO, to raise the FIFO interrupt of the receiving core as
+//! a doorbell, see [`SioFifo::enable_interrupt`].
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::sio::SioFifo;
+
+/// A bounded single-producer, single-consumer channel of up to `N` values
+pub struct Channel<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    // Count of values received modulo 2 * N, only written by the receiver
+    head: AtomicUsize,
+    // Count of values sent modulo 2 * N, only written by the sender
+    tail: AtomicUsize,
+}
+
+// Safety: the values are moved from the sender to the receiver, and each slot of the buffer is
+// only accessed by one of them at a time
+unsafe impl<T: Send, const N: usize> Sync for Channel<T, N> {}
+
+impl<T, const N: usize> Channel<T, N> {
+    /// Create an empty channel
+    pub const fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split the channel into its sending and receiving sides
+    ///
+    /// To move one of them to core1, the channel has to be `'static`, for example with
+    /// `cortex_m::singleton!`.
+    pub fn split(&mut self) -> (Sender<'_, T, N>, Receiver<'_, T, N>) {
+        let channel = &*self;
+        (Sender { channel }, Receiver { channel })
+    }
+
+    /// Returns the number of values in the channel
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        Self::distance(self.head.load(Ordering::Acquire), tail)
+    }
+
+    /// Returns true if the channel holds no values
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of values the channel can hold
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    // The indices wrap around at 2 * N rather than at usize::MAX, which keeps the slots in
+    // sequence for any N, while still telling a full channel from an empty one
+    fn next(index: usize) -> usize {
+        if index + 1 == 2 * N {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn distance(head: usize, tail: usize) -> usize {
+        if tail >= head {
+            tail - head
+        } else {
+            tail + 2 * N - head
+        }
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        (self.buffer.get() as *mut T).wrapping_add(index % N)
+    }
+}
+
+impl<T, const N: usize> Default for Channel<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Channel<T, N> {
+    fn drop(&mut self) {
+        let tail = *self.tail.get_mut();
+        let mut head = *self.head.get_mut();
+        while head != tail {
+            // Safety: the values between head and tail were sent and not received
+            unsafe { self.slot(head).drop_in_place() };
+            head = Self::next(head);
+        }
+    }
+}
+
+/// The sending side of a [`Channel`]
+pub struct Sender<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<'a, T, const N: usize> Sender<'a, T, N> {
+    /// Send a value, or give it back if the channel is full
+    pub fn send(&mut self, value: T) -> Result<(), T> {
+        let tail = self.channel.tail.load(Ordering::Relaxed);
+        if Channel::<T, N>::distance(self.channel.head.load(Ordering::Acquire), tail) >= N {
+            return Err(value);
+        }
+        // Safety: the slot is free, as the receiver is done with it, and the receiver only
+        // reads it once the tail moved past it
+        unsafe { self.channel.slot(tail).write(value) };
+        self.channel
+            .tail
+            .store(Channel::<T, N>::next(tail), Ordering::Release);
+        cortex_m::asm::sev();
+        Ok(())
+    }
+
+    /// Send a value, then write `token` to the inter-core FIFO to interrupt the other core
+    ///
+    /// `fifo` is the FIFO of the sending core. The token isn't written if the channel is full,
+    /// and this waits for room in the FIFO otherwise.
+    pub fn send_and_notify(&mut self, value: T, fifo: &mut SioFifo, token: u32) -> Result<(), T> {
+        self.send(value)?;
+        fifo.write_blocking(token);
+        Ok(())
+    }
+
+    /// Returns true if the channel is full
+    pub fn is_full(&self) -> bool {
+        self.channel.len() >= N
+    }
+}
+
+/// The receiving side of a [`Channel`]
+pub struct Receiver<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<'a, T, const N: usize> Receiver<'a, T, N> {
+    /// Receive a value, if the channel isn't empty
+    pub fn recv(&mut self) -> Option<T> {
+        let head = self.channel.head.load(Ordering::Relaxed);
+        if self.channel.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+        // Safety: the sender wrote the slot before moving the tail past it, and doesn't touch
+        // it again until the head moved past it
+        let value = unsafe { self.channel.slot(head).read() };
+        self.channel
+            .head
+            .store(Channel::<T, N>::next(head), Ordering::Release);
+        Some(value)
+    }
+
+    /// Wait for a value, sleeping until the sender signals an event
+    pub fn recv_blocking(&mut self) -> T {
+        loop {
+            if let Some(value) = self.recv() {
+                return value;
+            }
+            cortex_m::asm::wfe();
+        }
+    }
+
+    /// Returns true if the channel is empty
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_empty()
+    }
+}
@@ -2,6 +2,7 @@
 //! See [Chapter 3 of the datasheet](https://rptl.io/rp2040-datasheet#section_pio) for more details.
 use crate::{
     atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    clocks::GatedPeripheral,
     resets::SubsystemReset,
 };
 use pio::{Program, SideSet, Wrap};
@@ -11,7 +12,11 @@ const PIO_INSTRUCTION_COUNT: usize = 32;
 
 /// PIO Instance
 pub trait PIOExt:
-    core::ops::Deref<Target = rp2040_pac::pio0::RegisterBlock> + SubsystemReset + Sized + Send
+    core::ops::Deref<Target = rp2040_pac::pio0::RegisterBlock>
+    + SubsystemReset
+    + GatedPeripheral
+    + Sized
+    + Send
 {
     /// Create a new PIO wrapper and split the state machines into individual objects.
     #[allow(clippy::type_complexity)] // Required for symmetry with PIO::free().
@@ -27,6 +32,7 @@ pub trait PIOExt:
     ) {
         self.reset_bring_down(resets);
         self.reset_bring_up(resets);
+        crate::sleep::peripheral_enabled::<Self>();
 
         let sm0 = UninitStateMachine {
             block: self.deref(),
@@ -110,6 +116,7 @@ impl<P: PIOExt> PIO<P> {
         _sm3: UninitStateMachine<(P, SM3)>,
     ) -> P {
         // All state machines have already been stopped.
+        crate::sleep::peripheral_disabled::<P>();
         self.pio
     }
 
@@ -401,6 +408,7 @@ impl<P: PIOExt, SM: StateMachineIndex> ValidStateMachine for (P, SM) {
 ///
 /// Note the GPIO is able to override/invert that.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PinState {
     /// Pin in Low state.
     High,
@@ -412,6 +420,7 @@ pub enum PinState {
 ///
 /// Note the GPIO is able to override/invert that.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PinDir {
     /// Pin set as an Input
     Input,
@@ -1556,6 +1565,7 @@ impl<P: PIOExt> Interrupt<P> {
 
 /// Provides easy access for decoding PIO's interrupt state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct InterruptState(u32);
 
 macro_rules! raw_interrupt_accessor {
@@ -1585,6 +1595,7 @@ impl InterruptState {
 
 /// Comparison used for `mov x, status` instruction.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MovStatusConfig {
     /// The `mov x, status` instruction returns all ones if TX FIFO level is below the set status, otherwise all zeros.
     Tx(u8),
@@ -1594,6 +1605,7 @@ pub enum MovStatusConfig {
 
 /// Shift direction for input and output shifting.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ShiftDirection {
     /// Shift register to left.
     Left,
@@ -1663,6 +1675,7 @@ pub struct PIOBuilder<P> {
 
 /// Buffer sharing configuration.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Buffers {
     /// No sharing.
     RxTx,
@@ -1674,6 +1687,7 @@ pub enum Buffers {
 
 /// Errors that occurred during `PIO::install`.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum InstallError {
     /// There was not enough space for the instructions on the selected PIO.
     NoSpace,
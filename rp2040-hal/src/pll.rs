@@ -24,6 +24,7 @@ pub struct Disabled {
     post_div1: u8,
     post_div2: u8,
     frequency: HertzU32,
+    xosc_frequency: HertzU32,
 }
 
 /// PLL is configured, started and locking into its designated frequency.
@@ -31,11 +32,13 @@ pub struct Locking {
     post_div1: u8,
     post_div2: u8,
     frequency: HertzU32,
+    xosc_frequency: HertzU32,
 }
 
 /// PLL is locked : it delivers a steady frequency.
 pub struct Locked {
     frequency: HertzU32,
+    xosc_frequency: HertzU32,
 }
 
 impl State for Disabled {}
@@ -73,6 +76,7 @@ impl<S: State, D: PhaseLockedLoopDevice> PhaseLockedLoop<S, D> {
 
 /// Error type for the PLL module.
 /// See Chapter 2, Section 18 §2 for details on constraints triggering these errors.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Proposed VCO frequency is out of range.
     VcoFreqOutOfRange,
@@ -91,6 +95,7 @@ pub enum Error {
 }
 
 /// Parameters for a PLL.
+#[derive(Clone, Copy, Debug)]
 pub struct PLLConfig {
     /// Voltage Controlled Oscillator frequency.
     pub vco_freq: HertzU32,
@@ -105,6 +110,21 @@ pub struct PLLConfig {
     pub post_div2: u8,
 }
 
+// HertzU32 doesn't implement defmt::Format
+#[cfg(feature = "defmt")]
+impl defmt::Format for PLLConfig {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "PLLConfig {{ vco_freq: {} Hz, refdiv: {}, post_div1: {}, post_div2: {} }}",
+            self.vco_freq.to_Hz(),
+            self.refdiv,
+            self.post_div1,
+            self.post_div2
+        )
+    }
+}
+
 /// Common configs for the two PLLs. Both assume the XOSC is cadenced at 12MHz !
 /// See Chapter 2, Section 18, §2
 pub mod common_configs {
@@ -128,13 +148,9 @@ pub mod common_configs {
     };
 }
 
-impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
-    /// Instantiates a new Phase-Locked-Loop device.
-    pub fn new(
-        dev: D,
-        xosc_frequency: HertzU32,
-        config: PLLConfig,
-    ) -> Result<PhaseLockedLoop<Disabled, D>, Error> {
+impl Disabled {
+    /// Check `config`, and compute the register values for it
+    fn new(xosc_frequency: HertzU32, config: PLLConfig) -> Result<Disabled, Error> {
         const VCO_FREQ_RANGE: RangeInclusive<HertzU32> = HertzU32::MHz(400)..=HertzU32::MHz(1_600);
         const POSTDIV_RANGE: Range<u8> = 1..7;
         const FBDIV_RANGE: Range<u16> = 16..320;
@@ -177,17 +193,29 @@ impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
         let refdiv = config.refdiv;
         let post_div1 = config.post_div1;
         let post_div2 = config.post_div2;
-        let frequency: HertzU32 = ((ref_freq_hz / u32::from(refdiv)) * u32::from(fbdiv))
-            / (u32::from(post_div1) * u32::from(post_div2));
+        let frequency: HertzU32 =
+            (ref_freq_hz * u32::from(fbdiv)) / (u32::from(post_div1) * u32::from(post_div2));
 
+        Ok(Disabled {
+            refdiv,
+            fbdiv,
+            post_div1,
+            post_div2,
+            frequency,
+            xosc_frequency,
+        })
+    }
+}
+
+impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
+    /// Instantiates a new Phase-Locked-Loop device.
+    pub fn new(
+        dev: D,
+        xosc_frequency: HertzU32,
+        config: PLLConfig,
+    ) -> Result<PhaseLockedLoop<Disabled, D>, Error> {
         Ok(PhaseLockedLoop {
-            state: Disabled {
-                refdiv,
-                fbdiv,
-                post_div1,
-                post_div2,
-                frequency,
-            },
+            state: Disabled::new(xosc_frequency, config)?,
             device: dev,
         })
     }
@@ -220,11 +248,13 @@ impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
         let post_div1 = self.state.post_div1;
         let post_div2 = self.state.post_div2;
         let frequency = self.state.frequency;
+        let xosc_frequency = self.state.xosc_frequency;
 
         self.transition(Locking {
             post_div1,
             post_div2,
             frequency,
+            xosc_frequency,
         })
     }
 }
@@ -262,8 +292,12 @@ impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Locking, D> {
         });
 
         let frequency = self.state.frequency;
+        let xosc_frequency = self.state.xosc_frequency;
 
-        self.transition(Locked { frequency })
+        self.transition(Locked {
+            frequency,
+            xosc_frequency,
+        })
     }
 }
 
@@ -272,6 +306,84 @@ impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Locked, D> {
     pub fn operating_frequency(&self) -> HertzU32 {
         self.state.frequency
     }
+
+    /// Get the frequency of the crystal oscillator the PLL runs from
+    pub fn xosc_frequency(&self) -> HertzU32 {
+        self.state.xosc_frequency
+    }
+
+    /// Change the configuration of the running PLL.
+    ///
+    /// The output of the PLL is stopped until it is locked again at the new VCO frequency, so no
+    /// clock may run from it in the meantime. See
+    /// [`ClocksManager::overclock_to`](crate::clocks::ClocksManager::overclock_to) for a
+    /// complete sequence for PLL_SYS.
+    pub fn reconfigure(&mut self, config: PLLConfig) -> Result<(), Error> {
+        let params = Disabled::new(self.state.xosc_frequency, config)?;
+
+        // Power the PLL down, so that LOCK is only set again once the VCO runs at the new
+        // frequency
+        self.device.pwr.reset();
+
+        self.device.cs.write(|w| unsafe {
+            w.refdiv().bits(params.refdiv);
+            w
+        });
+        self.device.fbdiv_int.write(|w| unsafe {
+            w.fbdiv_int().bits(params.fbdiv);
+            w
+        });
+
+        self.device.pwr.modify(|_, w| {
+            w.pd().clear_bit();
+            w.vcopd().clear_bit();
+            w
+        });
+        while self.device.cs.read().lock().bit_is_clear() {
+            cortex_m::asm::nop();
+        }
+
+        self.device.prim.write(|w| unsafe {
+            w.postdiv1().bits(params.post_div1);
+            w.postdiv2().bits(params.post_div2);
+            w
+        });
+        self.device.pwr.modify(|_, w| w.postdivpd().clear_bit());
+
+        self.state.frequency = params.frequency;
+        Ok(())
+    }
+}
+
+/// Find a configuration generating exactly `frequency` from a crystal at `xosc_frequency`.
+///
+/// Like the C SDK, this prefers the highest VCO frequency, for the lowest jitter, and doesn't
+/// divide the reference. Returns None if the frequency can't be reached exactly.
+pub fn find_config(xosc_frequency: HertzU32, frequency: HertzU32) -> Option<PLLConfig> {
+    const VCO_FREQ_RANGE: RangeInclusive<u32> = 750_000_000..=1_600_000_000;
+
+    let ref_freq = xosc_frequency.to_Hz();
+    let target = frequency.to_Hz();
+    for fbdiv in (16..320u32).rev() {
+        let vco_freq = match ref_freq.checked_mul(fbdiv) {
+            Some(vco_freq) if VCO_FREQ_RANGE.contains(&vco_freq) => vco_freq,
+            _ => continue,
+        };
+        for post_div1 in (1..7u8).rev() {
+            for post_div2 in (1..=post_div1).rev() {
+                let divider = u32::from(post_div1) * u32::from(post_div2);
+                if vco_freq % divider == 0 && vco_freq / divider == target {
+                    return Some(PLLConfig {
+                        vco_freq: vco_freq.Hz(),
+                        refdiv: 1,
+                        post_div1,
+                        post_div2,
+                    });
+                }
+            }
+        }
+    }
+    None
 }
 
 /// Blocking helper method to setup the PLL without going through all the steps.
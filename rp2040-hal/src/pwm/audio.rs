@@ -0,0 +1,168 @@
+//! Audio output using PWM and DMA
+//!
+//! [`PwmAudio`] plays samples by writing them to the compare register of a
+//! slice, one sample per PWM period. The samples are fed by a DMA channel
+//! from two buffers in turn, restarted on the other buffer by a second
+//! channel: while one buffer is played, the other one can be refilled. Filtered by an RC low-pass, or simply by a speaker, the output is
+//! an analog signal.
+//!
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{dma::DMAExt, gpio::Pins, pac, pwm::{PwmAudio, Slices}, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let pwm_slices = Slices::new(peripherals.PWM, &mut peripherals.RESETS);
+//! let mut pwm = pwm_slices.pwm0;
+//! let _pin = pwm.channel_a.output_to(pins.gpio0);
+//!
+//! // 8 bit samples at 8kHz, e.g. converted from a WAV file and included with `include_bytes!`
+//! static SOUND: &[u8] = &[0x80; 8000];
+//! static mut BUFFERS: [[u16; 256]; 2] = [[0; 256]; 2];
+//! // Safety: the buffers are only used by the audio output
+//! let [buf_a, buf_b] = unsafe { &mut BUFFERS };
+//!
+//! let mut audio = PwmAudio::new(
+//!     pwm,
+//!     125.MHz(),
+//!     8.kHz(),
+//!     (dma.ch0, dma.ch1),
+//!     [buf_a, buf_b],
+//! )
+//! .unwrap();
+//! let top = u32::from(audio.top()) + 1;
+//! let mut samples = SOUND.iter();
+//! audio.start();
+//! loop {
+//!     // Called each time a buffer has been played
+//!     audio.refill(|buffer| {
+//!         for out in buffer.iter_mut() {
+//!             let sample = u32::from(*samples.next().unwrap_or(&0x80));
+//!             *out = (sample * top / 256) as u16;
+//!         }
+//!     });
+//! }
+//! ```
+
+use fugit::HertzU32;
+
+use super::{reg::RegisterInterface, Error, FreeRunning, Slice, SliceId};
+use crate::dma::{
+    Channel as DmaChannel, ChannelConfig, ChannelIndex, DoubleBuffer, TransferSize, DREQ_PWM_WRAP0,
+};
+
+/// Audio output through a PWM slice, fed by DMA
+///
+/// Each sample is a compare value between 0 and [`top`](Self::top) + 1. As
+/// the compare register is written as a whole, channels A and B of the slice
+/// output the same signal.
+pub struct PwmAudio<I, CH1, CH2, const N: usize>
+where
+    I: SliceId,
+    CH1: ChannelIndex,
+    CH2: ChannelIndex,
+{
+    slice: Slice<I, FreeRunning>,
+    dma: DoubleBuffer<CH1, CH2>,
+    buffers: [&'static mut [u16; N]; 2],
+}
+
+impl<I, CH1, CH2, const N: usize> PwmAudio<I, CH1, CH2, N>
+where
+    I: SliceId,
+    CH1: ChannelIndex,
+    CH2: ChannelIndex,
+{
+    /// Configure the slice to run at `sample_rate` and prepare the DMA channels
+    ///
+    /// The PWM frequency equals the sample rate, so the resolution of the
+    /// samples given by [`top`](Self::top) decreases with higher rates. The
+    /// buffers should be filled with the first samples before calling
+    /// [`start`](Self::start).
+    pub fn new(
+        mut slice: Slice<I, FreeRunning>,
+        clk_sys_freq: HertzU32,
+        sample_rate: HertzU32,
+        channels: (DmaChannel<CH1>, DmaChannel<CH2>),
+        buffers: [&'static mut [u16; N]; 2],
+    ) -> Result<Self, Error> {
+        slice.disable();
+        slice.clr_ph_correct();
+        slice.set_freq_hz(clk_sys_freq, sample_rate)?;
+
+        let (ch1, ch2) = channels;
+        let cc_addr = slice.regs.ch().cc.as_ptr() as u32;
+        let config = ChannelConfig {
+            size: TransferSize::HalfWord,
+            incr_read: true,
+            incr_write: false,
+            treq: DREQ_PWM_WRAP0 + I::DYN.num,
+            chain_to: None,
+            read_ring: 0,
+        };
+        // Safety: the buffers are owned by the audio output and the compare
+        // register is valid for the lifetime of the program.
+        let dma = unsafe {
+            DoubleBuffer::new(
+                ch1,
+                ch2,
+                &config,
+                cc_addr,
+                [buffers[0].as_ptr() as u32, buffers[1].as_ptr() as u32],
+                N as u32,
+            )
+        };
+
+        Ok(PwmAudio {
+            slice,
+            dma,
+            buffers,
+        })
+    }
+
+    /// The TOP value of the slice, samples range from 0 to TOP + 1
+    pub fn top(&self) -> u16 {
+        self.slice.get_top()
+    }
+
+    /// Start playing, beginning with the first buffer
+    pub fn start(&mut self) {
+        self.slice.set_counter(0);
+        self.dma.start();
+        self.slice.enable();
+    }
+
+    /// Returns true if a buffer was played since the last call to [`refill`](Self::refill)
+    pub fn is_ready(&self) -> bool {
+        self.dma.is_ready()
+    }
+
+    /// Wait until a buffer has been played and pass it to `fill`
+    ///
+    /// While `fill` runs, the other buffer is played. It has to return before
+    /// that buffer is finished, i.e. within `N` sample periods, otherwise the
+    /// buffer is played again while it's being refilled, mixing old and new
+    /// samples. The DMA channels never read outside of the buffers.
+    pub fn refill<F: FnOnce(&mut [u16; N])>(&mut self, fill: F) {
+        let index = self.dma.wait();
+
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        fill(&mut *self.buffers[index]);
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Stop playing and return the slice, DMA channels and buffers
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        mut self,
+    ) -> (
+        Slice<I, FreeRunning>,
+        (DmaChannel<CH1>, DmaChannel<CH2>),
+        [&'static mut [u16; N]; 2],
+    ) {
+        let channels = self.dma.free();
+        self.slice.disable();
+        (self.slice, channels, self.buffers)
+    }
+}
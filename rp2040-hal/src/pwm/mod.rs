@@ -50,6 +50,7 @@
 //! // Set duty cycle
 //! channel_a.set_duty(0x00ff);
 //! channel_a.get_duty();
+//! channel_a.set_duty_percent(25); // Independent of TOP
 //! channel_a.set_inverted(); // Invert the output
 //! channel_a.clr_inverted(); // Don't invert the output
 //! ```
@@ -58,6 +59,7 @@
 //!
 //! ```no_run
 //! # use rp2040_hal::{prelude::*, pwm::Slices};
+//! # use fugit::RateExtU32;
 //! # let mut pac = rp2040_pac::Peripherals::take().unwrap();
 //! # let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
 //! # let mut pwm = pwm_slices.pwm4;
@@ -66,12 +68,43 @@
 //!
 //! pwm.set_div_int(1u8); // To set integer part of clock divider
 //! pwm.set_div_frac(0u8); // To set fractional part of clock divider
+//! pwm.set_div(2u8, 8u8); // To set the clock divider to 2.5
 //!
 //! pwm.get_top(); // To get the TOP register
 //! pwm.set_top(u16::MAX); // To set the TOP register
 //!
+//! // Compute divider and TOP for a 25kHz output, with a 125MHz system clock
+//! let freq = pwm.set_freq_hz(125.MHz(), 25.kHz()).unwrap();
 //! ```
 //!
+//! ## Input
+//!
+//! Channel B can be used as input, to measure an external signal. The slice
+//! mode selects what the counter does:
+//!
+//! - [`InputHighRunning`]: count while the B pin is high, see [`Slice::measure_duty_cycle`]
+//! - [`CountRisingEdge`] and [`CountFallingEdge`]: count edges of the B pin, see [`Slice::measure_freq`]
+//!
+//! ```no_run
+//! # use rp2040_hal::{prelude::*, gpio::Pins, Sio, pwm::{CountRisingEdge, Slices}};
+//! # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(pac.SIO);
+//! # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//! # let core = cortex_m::Peripherals::take().unwrap();
+//! let mut delay = cortex_m::delay::Delay::new(core.SYST, 125_000_000);
+//! let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! let mut pwm = pwm_slices.pwm4.into_mode::<CountRisingEdge>();
+//! let _pin = pwm.input_from(pins.gpio25);
+//! // Count edges for 100ms
+//! let freq = pwm.measure_freq(&mut delay, 100_000);
+//! ```
+//!
+//! In [`InputHighRunning`] mode, the B pin gates the counter: it only counts
+//! while the pin is high, so the counter accumulates the time the pin was high,
+//! e.g. how long a light gate was interrupted. [`Slice::high_time`] converts the
+//! count to a duration. In the edge counting modes, the B pin clocks the counter
+//! instead, and channel A still outputs a PWM signal, timed by that external clock.
+//!
 //! default_config() sets ph_correct to false, the clock divider to 1, does not invert the output, sets top to 65535, and resets the counter.
 //! min_config() leaves those registers in the state they were before it was called (Careful, this can lead to unexpected behavior)
 //! It's recommended to only call min_config() after calling default_config() on a pin that shares a PWM block.
@@ -87,7 +120,8 @@ use crate::{
     resets::SubsystemReset,
     typelevel::Sealed,
 };
-use embedded_hal::PwmPin;
+use embedded_hal::{blocking::delay::DelayUs, PwmPin};
+use fugit::{HertzU32, MicrosDurationU64};
 use pac::PWM;
 
 use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
@@ -95,6 +129,12 @@ use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
 pub mod dyn_slice;
 pub use dyn_slice::*;
 
+pub mod audio;
+pub use audio::PwmAudio;
+
+pub mod tone;
+pub use tone::Tone;
+
 mod reg;
 
 use reg::RegisterInterface;
@@ -312,7 +352,76 @@ where
         self.regs.retard_phase()
     }
 
+    /// Returns true if a phase advance or retard has been requested but not yet applied
+    #[inline]
+    pub fn is_phase_shift_pending(&self) -> bool {
+        self.regs.is_phase_shift_pending()
+    }
+
+    /// Advance the phase by `counts` counts, waiting until each one was applied
+    ///
+    /// A count is skipped at the next edge of the divided clock, so the slice
+    /// must be running freely at less than full speed. If the slice is
+    /// disabled, the counter is changed directly instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PhaseShiftUnavailable`] without changing the phase if
+    /// the slice is enabled with a divider of 1, or in another mode than
+    /// [`FreeRunning`], as the counts might never be applied.
+    pub fn advance_phase_by(&mut self, counts: u16) -> Result<(), Error> {
+        if !self.regs.read_enable() {
+            let top = u32::from(self.get_top()) + 1;
+            let ctr = (u32::from(self.get_counter()) + u32::from(counts)) % top;
+            self.set_counter(ctr as u16);
+            return Ok(());
+        }
+        if M::DYN != DynSliceMode::FreeRunning || self.regs.read_div_16() == 16 {
+            return Err(Error::PhaseShiftUnavailable);
+        }
+        for _ in 0..counts {
+            self.regs.advance_phase();
+            while self.regs.is_phase_shift_pending() {}
+        }
+        Ok(())
+    }
+
+    /// Retard the phase by `counts` counts, waiting until each one was applied
+    ///
+    /// A count is held back at the next edge of the divided clock, so the
+    /// slice must be running freely. If the slice is disabled, the counter is
+    /// changed directly instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PhaseShiftUnavailable`] without changing the phase if
+    /// the slice is enabled in another mode than [`FreeRunning`], as the
+    /// counts might never be applied.
+    pub fn retard_phase_by(&mut self, counts: u16) -> Result<(), Error> {
+        if !self.regs.read_enable() {
+            let top = u32::from(self.get_top()) + 1;
+            let counts = u32::from(counts) % top;
+            let ctr = (u32::from(self.get_counter()) + top - counts) % top;
+            self.set_counter(ctr as u16);
+            return Ok(());
+        }
+        if M::DYN != DynSliceMode::FreeRunning {
+            return Err(Error::PhaseShiftUnavailable);
+        }
+        for _ in 0..counts {
+            self.regs.retard_phase();
+            while self.regs.is_phase_shift_pending() {}
+        }
+        Ok(())
+    }
+
     /// Enable phase correct mode
+    ///
+    /// In phase correct mode, the counter counts up to TOP and then back down
+    /// to 0, instead of wrapping to 0. Outputs are centre-aligned: the pulses
+    /// of both channels are centred on the same point in time, which is needed
+    /// e.g. for complementary switching in motor drivers. As a consequence,
+    /// the period is doubled, which is taken into account by [`Slice::freq`].
     #[inline]
     pub fn set_ph_correct(&mut self) {
         self.regs.write_ph_correct(true)
@@ -324,6 +433,24 @@ where
         self.regs.write_ph_correct(false)
     }
 
+    /// Returns true if phase correct mode is enabled
+    #[inline]
+    pub fn is_ph_correct(&self) -> bool {
+        self.regs.read_ph_correct()
+    }
+
+    /// Calculate the frequency of the PWM output, given the frequency of the system clock
+    ///
+    /// This takes into account the clock divider, TOP and phase correct mode,
+    /// which doubles the period. It is only meaningful in [`FreeRunning`] mode.
+    pub fn freq(&self, clk_sys_freq: HertzU32) -> HertzU32 {
+        let mut period = self.regs.read_div_16() * (u64::from(self.regs.read_top()) + 1);
+        if self.is_ph_correct() {
+            period *= 2;
+        }
+        HertzU32::from_raw((u64::from(clk_sys_freq.to_Hz()) * 16 / period) as u32)
+    }
+
     /// Enable slice
     #[inline]
     pub fn enable(&mut self) {
@@ -348,6 +475,69 @@ where
         self.regs.write_div_frac(value)
     }
 
+    /// Sets the clock divider to `int + frac / 16`
+    ///
+    /// `int` must be at least 1, `frac` must be below 16.
+    #[inline]
+    pub fn set_div(&mut self, int: u8, frac: u8) {
+        debug_assert!(int >= 1 && frac < 16);
+        self.regs.write_div_int(int);
+        self.regs.write_div_frac(frac);
+    }
+
+    /// Configure the clock divider and TOP to produce the given output frequency
+    ///
+    /// The smallest possible divider is used, so TOP, and therefore the duty
+    /// cycle resolution, is as large as possible. Phase correct mode is taken
+    /// into account. Returns the frequency actually achieved, which can differ
+    /// from the requested one due to the limited resolution of the divider and TOP.
+    ///
+    /// The compare values are scaled to keep the duty cycles, see
+    /// [`set_top_keep_duty`](Self::set_top_keep_duty).
+    pub fn set_freq_hz(
+        &mut self,
+        clk_sys_freq: HertzU32,
+        target: HertzU32,
+    ) -> Result<HertzU32, Error> {
+        let clk_16 = u64::from(clk_sys_freq.to_Hz()) * 16;
+        let mut target = u64::from(target.to_Hz());
+        if self.is_ph_correct() {
+            target *= 2;
+        }
+        if target == 0 {
+            return Err(Error::FrequencyTooLow);
+        }
+        // Smallest divider, in 1/16ths, for which the period fits into TOP
+        let div_16 = core::cmp::max((clk_16 + target * 0x10000 - 1) / (target * 0x10000), 16);
+        if div_16 > 0xfff {
+            return Err(Error::FrequencyTooLow);
+        }
+        // Period in counts, rounded to the nearest value
+        let period = (clk_16 + div_16 * target / 2) / (div_16 * target);
+        if period < 2 {
+            return Err(Error::FrequencyTooHigh);
+        }
+        self.set_div((div_16 >> 4) as u8, (div_16 & 0xf) as u8);
+        self.set_top_keep_duty((period - 1) as u16);
+        Ok(self.freq(clk_sys_freq))
+    }
+
+    /// Set TOP, scaling the compare values of both channels to keep their duty cycles
+    ///
+    /// The duty cycle stored by a disabled channel is not scaled.
+    pub fn set_top_keep_duty(&mut self, top: u16) {
+        let old_period = u32::from(self.regs.read_top()) + 1;
+        let new_period = u32::from(top) + 1;
+        let scale = |cc: u16| {
+            let cc = (u32::from(cc) * new_period + old_period / 2) / old_period;
+            core::cmp::min(cc, u32::from(u16::MAX)) as u16
+        };
+        let (cc_a, cc_b) = (scale(self.regs.read_cc_a()), scale(self.regs.read_cc_b()));
+        self.regs.write_top(top);
+        self.regs.write_cc_a(cc_a);
+        self.regs.write_cc_b(cc_b);
+    }
+
     /// Get the counter register value
     #[inline]
     pub fn get_counter(&self) -> u16 {
@@ -405,6 +595,16 @@ where
         unsafe { (*pac::PWM::ptr()).ints.read().bits() & mask == mask }
     }
 
+    /// Did the counter of this slice wrap since the interrupt was last cleared?
+    ///
+    /// Unlike [`has_overflown`](Self::has_overflown), this does not depend on
+    /// the interrupt being enabled.
+    #[inline]
+    pub fn has_wrapped(&self) -> bool {
+        let mask = self.bitmask();
+        unsafe { (*pac::PWM::ptr()).intr.read().bits() & mask == mask }
+    }
+
     /// Mark the interrupt handled for this slice.
     #[inline]
     pub fn clear_interrupt(&mut self) {
@@ -434,6 +634,116 @@ where
     }
 }
 
+impl<I, M> Slice<I, M>
+where
+    I: SliceId,
+    M: SliceMode + ValidSliceInputMode<I>,
+{
+    /// Count for `gate_time_us` microseconds, starting from 0
+    ///
+    /// Returns `None` if the counter wrapped.
+    fn count_for<D: DelayUs<u32>>(&mut self, delay: &mut D, gate_time_us: u32) -> Option<u16> {
+        self.disable();
+        self.set_top(u16::MAX);
+        self.set_counter(0);
+        self.clear_interrupt();
+        self.enable();
+        delay.delay_us(gate_time_us);
+        self.disable();
+
+        if self.has_wrapped() {
+            return None;
+        }
+        Some(self.get_counter())
+    }
+}
+
+impl<I: SliceId> Slice<I, CountRisingEdge> {
+    /// Measure the frequency of the signal on the B pin
+    ///
+    /// Rising edges are counted for `gate_time_us` microseconds, so the
+    /// resolution is `1_000_000 / gate_time_us` Hz. Returns `None` if more
+    /// than 65535 edges were seen. This leaves the slice disabled, with the
+    /// clock divider set to 1.
+    pub fn measure_freq<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        gate_time_us: u32,
+    ) -> Option<HertzU32> {
+        self.set_div_int(1);
+        self.set_div_frac(0);
+        let edges = self.count_for(delay, gate_time_us)?;
+        let freq = u64::from(edges) * 1_000_000 / u64::from(gate_time_us.max(1));
+        Some(HertzU32::from_raw(freq as u32))
+    }
+}
+
+impl<I: SliceId> Slice<I, InputHighRunning> {
+    /// Time the B pin was high while the slice was enabled, since the counter was last reset
+    ///
+    /// Calculated from the counter and the clock divider. Returns `None` if the
+    /// counter wrapped since the interrupt flag was last cleared, see
+    /// [`has_wrapped`](Self::has_wrapped). With a 125MHz system clock, the
+    /// counter wraps after 524µs with a divider of 1, or 134ms with the
+    /// largest divider.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::{prelude::*, gpio::Pins, Sio, pwm::{InputHighRunning, Slices}};
+    /// # use fugit::RateExtU32;
+    /// # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+    /// # let sio = Sio::new(pac.SIO);
+    /// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+    /// let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+    /// let mut pwm = pwm_slices.pwm4.into_mode::<InputHighRunning>();
+    /// let _pin = pwm.input_from(pins.gpio25);
+    /// pwm.set_div(255, 15);
+    /// pwm.set_top(u16::MAX);
+    /// pwm.set_counter(0);
+    /// pwm.clear_interrupt();
+    /// pwm.enable();
+    /// // ...
+    /// let time_high = pwm.high_time(125.MHz());
+    /// ```
+    pub fn high_time(&self, clk_sys_freq: HertzU32) -> Option<MicrosDurationU64> {
+        if self.has_wrapped() {
+            return None;
+        }
+        let ticks = u64::from(self.get_counter()) * self.regs.read_div_16();
+        Some(MicrosDurationU64::from_ticks(
+            ticks * 1_000_000 / (u64::from(clk_sys_freq.to_Hz()) * 16),
+        ))
+    }
+
+    /// Measure the duty cycle of the signal on the B pin
+    ///
+    /// The counter runs while the B pin is high, during `gate_time_us`
+    /// microseconds. The clock divider is chosen so that the counter does not
+    /// wrap. Returns the fraction of time the pin was high, between 0 and 1,
+    /// or `None` if the gate time is too long to be measured. This leaves the
+    /// slice disabled.
+    ///
+    /// The gate time should span many periods of the signal, otherwise the
+    /// result depends on where in the period the measurement started.
+    pub fn measure_duty_cycle<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        clk_sys_freq: HertzU32,
+        gate_time_us: u32,
+    ) -> Option<f32> {
+        // Counts of the undivided system clock during the gate time
+        let ticks = u64::from(clk_sys_freq.to_Hz()) * u64::from(gate_time_us) / 1_000_000;
+        // Smallest divider, in 1/16ths, which keeps the counter below 65536
+        let div_16 = core::cmp::max((ticks * 16 + 0xfffe) / 0xffff, 16);
+        if div_16 > 0xfff || ticks == 0 {
+            return None;
+        }
+        self.set_div_int((div_16 >> 4) as u8);
+        self.set_div_frac((div_16 & 0xf) as u8);
+        let high = self.count_for(delay, gate_time_us)?;
+        Some((u64::from(high) * div_16) as f32 / (ticks * 16) as f32)
+    }
+}
+
 macro_rules! pwm {
     ($PWMX:ident, [
         $($SXi:ident: ($slice:literal, [$($pin_a:ident, $pin_b:ident),*], $i:expr)),+
@@ -463,6 +773,7 @@ macro_rules! pwm {
                 /// Take ownership of the PAC peripheral and split it into discrete [`Slice`]s
                 pub fn new(pwm: $PWMX, reset : &mut pac::RESETS) -> Self {
                     pwm.reset_bring_up(reset);
+                    crate::sleep::peripheral_enabled::<$PWMX>();
                     unsafe {
                         Self {
                             _pwm: pwm,
@@ -490,9 +801,177 @@ pwm! {
     ]
 }
 
-/// Marker trait for valid output pins
-pub trait ValidPwmInputPin<S: SliceId>: Sealed {}
+mod group {
+    pub trait Sealed {}
+}
+
+/// A group of slices, which can be enabled or disabled in the same clock cycle
+///
+/// This is implemented for mutable references to slices, and for tuples of up
+/// to eight groups.
+pub trait SliceGroup: group::Sealed {
+    /// Bitmask of the slices in this group, bit n for slice n
+    fn mask(&self) -> u8;
+}
+
+impl<'a, I: SliceId, M: SliceMode + ValidSliceMode<I>> group::Sealed for &'a mut Slice<I, M> {}
+impl<'a, I: SliceId, M: SliceMode + ValidSliceMode<I>> SliceGroup for &'a mut Slice<I, M> {
+    fn mask(&self) -> u8 {
+        1 << I::DYN.num
+    }
+}
+
+macro_rules! slice_group_tuple {
+    ($($G:ident $g:ident),+) => {
+        impl<$($G: SliceGroup),+> group::Sealed for ($($G,)+) {}
+        impl<$($G: SliceGroup),+> SliceGroup for ($($G,)+) {
+            fn mask(&self) -> u8 {
+                let ($($g,)+) = self;
+                0 $(| $g.mask())+
+            }
+        }
+    };
+}
+
+slice_group_tuple!(G0 g0, G1 g1);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2, G3 g3);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2, G3 g3, G4 g4);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2, G3 g3, G4 g4, G5 g5);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2, G3 g3, G4 g4, G5 g5, G6 g6);
+slice_group_tuple!(G0 g0, G1 g1, G2 g2, G3 g3, G4 g4, G5 g5, G6 g6, G7 g7);
+
+/// Enable several slices in the same clock cycle
+///
+/// Slices enabled together stay in lockstep if they run with the same
+/// configuration. To align their counters, set them to the same value first:
+///
+/// ```no_run
+/// use rp2040_hal::pwm::{enable_simultaneous, Slices};
+/// let mut pac = rp2040_pac::Peripherals::take().unwrap();
+/// let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// pwm_slices.pwm0.set_counter(0);
+/// pwm_slices.pwm1.set_counter(0);
+/// pwm_slices.pwm2.set_counter(0);
+/// enable_simultaneous((&mut pwm_slices.pwm0, &mut pwm_slices.pwm1, &mut pwm_slices.pwm2));
+/// ```
+#[inline]
+pub fn enable_simultaneous<G: SliceGroup>(group: G) {
+    unsafe {
+        let pwm = &(*pac::PWM::ptr());
+        write_bitmask_set(pwm.en.as_ptr(), u32::from(group.mask()));
+    }
+}
+
+/// Disable several slices in the same clock cycle
+#[inline]
+pub fn disable_simultaneous<G: SliceGroup>(group: G) {
+    unsafe {
+        let pwm = &(*pac::PWM::ptr());
+        write_bitmask_clear(pwm.en.as_ptr(), u32::from(group.mask()));
+    }
+}
+
+/// Bitmask of the slices with a pending PWM_IRQ_WRAP interrupt, bit n for slice n
+///
+/// This allows a shared interrupt handler to find out which slices fired:
+///
+/// ```no_run
+/// for slice in 0..8 {
+///     if rp2040_hal::pwm::pending_interrupts() & (1 << slice) != 0 {
+///         // handle the wrap of this slice
+///     }
+/// }
+/// rp2040_hal::pwm::clear_interrupts(rp2040_hal::pwm::pending_interrupts());
+/// ```
+#[inline]
+pub fn pending_interrupts() -> u8 {
+    unsafe { (*pac::PWM::ptr()).ints.read().bits() as u8 }
+}
+
+/// Mark the interrupts of the slices in `mask` as handled, bit n for slice n
+#[inline]
+pub fn clear_interrupts(mask: u8) {
+    unsafe { (*pac::PWM::ptr()).intr.write(|w| w.bits(u32::from(mask))) };
+}
+
+/// PWM Error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The requested frequency is too low, even with the largest divider and TOP
+    FrequencyTooLow,
+    /// The requested frequency is too high to be generated from the system clock
+    FrequencyTooHigh,
+    /// The phase can't be shifted, as the slice runs at full speed or doesn't run freely
+    PhaseShiftUnavailable,
+}
+
 /// Marker trait for valid input pins (Channel B only)
+///
+/// Only the pins connected to channel B of slice `S` implement this trait, so
+/// [`Channel::input_from`] and [`Slice::input_from`] reject any other pin at
+/// compile time. GPIO 25 is channel B of slice 4:
+///
+/// ```no_run
+/// # use rp2040_hal::{gpio::Pins, pac, Sio, pwm::{InputHighRunning, Slices}};
+/// # let mut pac = pac::Peripherals::take().unwrap();
+/// # let sio = Sio::new(pac.SIO);
+/// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// let mut pwm = pwm_slices.pwm4.into_mode::<InputHighRunning>();
+/// pwm.channel_b.input_from(pins.gpio25);
+/// ```
+///
+/// GPIO 24 is channel A of slice 4, which can't be used as input:
+///
+/// ```compile_fail
+/// # use rp2040_hal::{gpio::Pins, pac, Sio, pwm::{InputHighRunning, Slices}};
+/// # let mut pac = pac::Peripherals::take().unwrap();
+/// # let sio = Sio::new(pac.SIO);
+/// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// let mut pwm = pwm_slices.pwm4.into_mode::<InputHighRunning>();
+/// pwm.channel_b.input_from(pins.gpio24);
+/// ```
+pub trait ValidPwmInputPin<S: SliceId>: Sealed {}
+
+/// Marker trait for valid output pins
+///
+/// Only the pins connected to channel `C` of slice `S` implement this trait,
+/// so [`Channel::output_to`] rejects pins of other slices or of the other
+/// channel at compile time. GPIO 24 is channel A of slice 4:
+///
+/// ```no_run
+/// # use rp2040_hal::{gpio::Pins, pac, Sio, pwm::Slices};
+/// # let mut pac = pac::Peripherals::take().unwrap();
+/// # let sio = Sio::new(pac.SIO);
+/// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// pwm_slices.pwm4.channel_a.output_to(pins.gpio24);
+/// ```
+///
+/// GPIO 25 is channel B of slice 4, not channel A:
+///
+/// ```compile_fail
+/// # use rp2040_hal::{gpio::Pins, pac, Sio, pwm::Slices};
+/// # let mut pac = pac::Peripherals::take().unwrap();
+/// # let sio = Sio::new(pac.SIO);
+/// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// pwm_slices.pwm4.channel_a.output_to(pins.gpio25);
+/// ```
+///
+/// GPIO 0 belongs to slice 0:
+///
+/// ```compile_fail
+/// # use rp2040_hal::{gpio::Pins, pac, Sio, pwm::Slices};
+/// # let mut pac = pac::Peripherals::take().unwrap();
+/// # let sio = Sio::new(pac.SIO);
+/// # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+/// pwm_slices.pwm4.channel_a.output_to(pins.gpio0);
+/// ```
 pub trait ValidPwmOutputPin<S: SliceId, C: ChannelId>: Sealed {}
 
 /// Make sure we can't free an GPIO pin while still keeping it attached to pwm
@@ -528,21 +1007,10 @@ impl<G: PinId + BankPinId> PwmPinToken<G> {
 impl Slices {
     /// Free the pwm registers from the pwm hal struct while consuming it.
     pub fn free(self) -> PWM {
+        crate::sleep::peripheral_disabled::<PWM>();
         self._pwm
     }
 
-    //     /// Enable multiple slices at the same time to make their counters sync up.
-    //     ///
-    //     /// You still need to call `slice` to get an actual slice
-    //     pub fn enable_simultaneous<S: SliceId>(&mut self, bits: u8) {
-    //         // Enable all slices at the same time
-    //         unsafe {
-    //             &(*pac::PWM::ptr())
-    //                 .en
-    //                 .modify(|r, w| w.bits(((r.bits() as u8) | bits) as u32));
-    //         }
-    //     }
-
     // /// Get pwm slice based on gpio pin
     // pub fn borrow_mut_from_pin<
     //     S: SliceId,
@@ -588,6 +1056,36 @@ impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C> {
     }
 }
 
+impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C>
+where
+    Self: PwmPin<Duty = u16>,
+{
+    /// Set the duty cycle as a fraction of the period, from 0 (0%) to `u16::MAX` (100%)
+    ///
+    /// The compare value is scaled against the current TOP. With TOP at 0xffff,
+    /// a duty cycle of 100% can't be reached.
+    pub fn set_duty_fraction(&mut self, fraction: u16) {
+        let period = u32::from(self.regs.read_top()) + 1;
+        let duty = (u32::from(fraction) * period + u32::from(u16::MAX) / 2) / u32::from(u16::MAX);
+        self.set_duty(core::cmp::min(duty, u32::from(u16::MAX)) as u16);
+    }
+
+    /// Set the duty cycle in percent, values above 100 are treated as 100
+    ///
+    /// See [`set_duty_fraction`](Self::set_duty_fraction).
+    pub fn set_duty_percent(&mut self, percent: u8) {
+        let percent = u32::from(core::cmp::min(percent, 100));
+        self.set_duty_fraction((percent * u32::from(u16::MAX) / 100) as u16);
+    }
+
+    /// The duty cycle as a fraction of the period, from 0 (0%) to `u16::MAX` (100%)
+    pub fn get_duty_fraction(&self) -> u16 {
+        let period = u32::from(self.regs.read_top()) + 1;
+        let fraction = u32::from(self.get_duty()) * u32::from(u16::MAX) / period;
+        core::cmp::min(fraction, u32::from(u16::MAX)) as u16
+    }
+}
+
 impl<S: SliceId, M: SliceMode, C: ChannelId> Sealed for Channel<S, M, C> {}
 
 impl<S: SliceId, M: SliceMode> PwmPin for Channel<S, M, A> {
@@ -670,6 +1168,41 @@ impl<S: SliceId, M: SliceMode> PwmPin for Channel<S, M, B> {
     }
 }
 
+#[cfg(feature = "eh1_0")]
+impl<S: SliceId, M: SliceMode, C: ChannelId> eh1_0::pwm::ErrorType for Channel<S, M, C> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "eh1_0")]
+impl<S: SliceId, M: SliceMode> eh1_0::pwm::SetDutyCycle for Channel<S, M, A> {
+    /// TOP + 1, as a compare value above TOP keeps the output high for the whole period
+    ///
+    /// With TOP at 0xffff, a duty cycle of 100% can't be reached.
+    fn max_duty_cycle(&self) -> u16 {
+        self.regs.read_top().saturating_add(1)
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        PwmPin::set_duty(self, duty);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh1_0")]
+impl<S: SliceId, M: SliceMode> eh1_0::pwm::SetDutyCycle for Channel<S, M, B> {
+    /// TOP + 1, as a compare value above TOP keeps the output high for the whole period
+    ///
+    /// With TOP at 0xffff, a duty cycle of 100% can't be reached.
+    fn max_duty_cycle(&self) -> u16 {
+        self.regs.read_top().saturating_add(1)
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        PwmPin::set_duty(self, duty);
+        Ok(())
+    }
+}
+
 impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Channel<S, M, A> {
     /// Capture a gpio pin and use it as pwm output for channel A
     pub fn output_to<
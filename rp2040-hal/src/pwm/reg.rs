@@ -28,6 +28,17 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().csr.modify(|_, w| w.ph_ret().set_bit())
     }
 
+    #[inline]
+    fn is_phase_shift_pending(&self) -> bool {
+        let csr = self.ch().csr.read();
+        csr.ph_adv().bit_is_set() || csr.ph_ret().bit_is_set()
+    }
+
+    #[inline]
+    fn read_enable(&self) -> bool {
+        self.ch().csr.read().en().bit_is_set()
+    }
+
     #[inline]
     fn do_change_mode(&mut self, mode: DynSliceMode) {
         self.ch().csr.modify(|_, w| match mode {
@@ -53,6 +64,11 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().csr.modify(|_, w| w.ph_correct().bit(value));
     }
 
+    #[inline]
+    fn read_ph_correct(&self) -> bool {
+        self.ch().csr.read().ph_correct().bit_is_set()
+    }
+
     #[inline]
     fn write_enable(&mut self, value: bool) {
         self.ch().csr.modify(|_, w| w.en().bit(value));
@@ -67,6 +83,20 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().div.modify(|_, w| unsafe { w.frac().bits(value) });
     }
 
+    #[inline]
+    fn read_div(&self) -> (u8, u8) {
+        let div = self.ch().div.read();
+        (div.int().bits(), div.frac().bits())
+    }
+
+    /// The clock divider in 1/16ths, with an integer part of 0 counting as 256
+    #[inline]
+    fn read_div_16(&self) -> u64 {
+        let (int, frac) = self.read_div();
+        let int = if int == 0 { 256 } else { u64::from(int) };
+        (int << 4) | u64::from(frac)
+    }
+
     #[inline]
     fn write_ctr(&mut self, value: u16) {
         self.ch().ctr.write(|w| unsafe { w.ctr().bits(value) });
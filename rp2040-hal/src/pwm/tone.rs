@@ -0,0 +1,125 @@
+//! Tone generation for piezo buzzers and speakers
+//!
+//! [`Tone`] drives both channels of a slice with a square wave of the
+//! requested frequency, so the buzzer can be connected to either pin.
+//!
+//! ```no_run
+//! use fugit::{ExtU32, RateExtU32};
+//! use rp2040_hal::{gpio::Pins, pac, pwm::{Slices, Tone}, timer::Timer, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+//! let mut alarm = timer.alarm_0().unwrap();
+//! let pwm_slices = Slices::new(peripherals.PWM, &mut peripherals.RESETS);
+//! let mut pwm = pwm_slices.pwm0;
+//! let _pin = pwm.channel_a.output_to(pins.gpio0);
+//!
+//! let mut tone = Tone::new(pwm, 125.MHz());
+//! // Beep at 440Hz for 200ms, without blocking
+//! tone.play_for(440.Hz(), 200.millis(), &mut alarm).unwrap();
+//! loop {
+//!     tone.stop_if_finished(&mut alarm);
+//! }
+//! ```
+
+use embedded_hal::PwmPin;
+use fugit::{HertzU32, MicrosDurationU32};
+
+use super::{Error, FreeRunning, Slice, SliceId};
+use crate::timer::{Alarm, ScheduleAlarmError};
+
+/// Error returned by [`Tone::play_for`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ToneError {
+    /// The frequency can't be generated
+    Pwm(Error),
+    /// The alarm could not be scheduled for the duration
+    Alarm(ScheduleAlarmError),
+}
+
+/// Square wave generator on a PWM slice
+pub struct Tone<I: SliceId> {
+    slice: Slice<I, FreeRunning>,
+    clk_sys_freq: HertzU32,
+    playing: bool,
+}
+
+impl<I: SliceId> Tone<I> {
+    /// Use the slice to generate tones, given the frequency of the system clock
+    pub fn new(mut slice: Slice<I, FreeRunning>, clk_sys_freq: HertzU32) -> Self {
+        slice.disable();
+        slice.default_config();
+        Tone {
+            slice,
+            clk_sys_freq,
+            playing: false,
+        }
+    }
+
+    /// Start playing a tone, returning the frequency actually generated
+    pub fn play(&mut self, freq: HertzU32) -> Result<HertzU32, Error> {
+        let achieved = self.slice.set_freq_hz(self.clk_sys_freq, freq)?;
+        self.slice.channel_a.set_duty_fraction(u16::MAX / 2);
+        self.slice.channel_b.set_duty_fraction(u16::MAX / 2);
+        self.slice.enable();
+        self.playing = true;
+        Ok(achieved)
+    }
+
+    /// Start playing a tone, scheduling `alarm` to fire after `duration`
+    ///
+    /// The tone keeps playing until [`stop`](Self::stop) or
+    /// [`stop_if_finished`](Self::stop_if_finished) is called, e.g. from the
+    /// interrupt handler of the alarm.
+    pub fn play_for<A: Alarm>(
+        &mut self,
+        freq: HertzU32,
+        duration: MicrosDurationU32,
+        alarm: &mut A,
+    ) -> Result<HertzU32, ToneError> {
+        alarm.schedule(duration).map_err(ToneError::Alarm)?;
+        self.play(freq).map_err(ToneError::Pwm)
+    }
+
+    /// Stop the tone if `alarm` has fired, clearing its interrupt
+    ///
+    /// Returns true if the tone was stopped.
+    pub fn stop_if_finished<A: Alarm>(&mut self, alarm: &mut A) -> bool {
+        if self.playing && alarm.finished() {
+            alarm.clear_interrupt();
+            self.stop();
+            return true;
+        }
+        false
+    }
+
+    /// Stop playing, leaving both outputs low
+    pub fn stop(&mut self) {
+        self.slice.disable();
+        self.slice.channel_a.set_duty(0);
+        self.slice.channel_b.set_duty(0);
+        self.slice.set_counter(0);
+        self.playing = false;
+    }
+
+    /// Change the frequency of the system clock, used by the next tone
+    ///
+    /// Call this after clk_sys changed, e.g. with
+    /// [`ClocksManager::set_sys_clock`](crate::clocks::ClocksManager::set_sys_clock).
+    pub fn set_clk_sys_freq(&mut self, clk_sys_freq: HertzU32) {
+        self.clk_sys_freq = clk_sys_freq;
+    }
+
+    /// Returns true while a tone is playing
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Stop playing and return the slice
+    pub fn free(mut self) -> Slice<I, FreeRunning> {
+        self.stop();
+        self.slice
+    }
+}
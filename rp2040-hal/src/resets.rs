@@ -1,16 +1,54 @@
 //! Subsystem Resets
-// See [Chapter 2 Section 14](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! Each peripheral has its own reset, which the drivers cycle in their constructors and assert
+//! when they are freed. The functions of this module reset a peripheral explicitly, e.g. to
+//! recover an I2C block wedged by a misbehaving device before building the driver again:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, resets};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! resets::reset_cycle(&pac.I2C0, &mut pac.RESETS);
+//! assert!(resets::is_reset_done(&pac.I2C0, &pac.RESETS));
+//! ```
+//!
+//! Taking the PAC peripheral ensures that no driver uses it: a driver has to be freed to get
+//! the peripheral back.
+//!
+//! See [Chapter 2 Section 14](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
 use rp2040_pac as pac;
 
 mod private {
     pub trait SubsystemReset {
         fn reset_bring_up(&self, resets: &mut pac::RESETS);
         fn reset_bring_down(&self, resets: &mut pac::RESETS);
+        fn is_reset_done(&self, resets: &pac::RESETS) -> bool;
     }
 }
 
 pub(crate) use private::SubsystemReset;
 
+/// Hold `peripheral` in reset, until [`unreset`]
+pub fn reset<P: SubsystemReset>(peripheral: &P, resets: &mut pac::RESETS) {
+    peripheral.reset_bring_down(resets);
+}
+
+/// Take `peripheral` out of reset, and wait until it's ready
+pub fn unreset<P: SubsystemReset>(peripheral: &P, resets: &mut pac::RESETS) {
+    peripheral.reset_bring_up(resets);
+}
+
+/// Reset `peripheral` and take it out of reset, bringing all its registers back to their
+/// reset values
+pub fn reset_cycle<P: SubsystemReset>(peripheral: &P, resets: &mut pac::RESETS) {
+    peripheral.reset_bring_down(resets);
+    peripheral.reset_bring_up(resets);
+}
+
+/// Returns true if `peripheral` is out of reset and ready
+pub fn is_reset_done<P: SubsystemReset>(peripheral: &P, resets: &pac::RESETS) -> bool {
+    peripheral.is_reset_done(resets)
+}
+
 macro_rules! generate_reset {
     ($MODULE:ident, $module:ident) => {
         impl SubsystemReset for pac::$MODULE {
@@ -21,6 +59,9 @@ macro_rules! generate_reset {
             fn reset_bring_down(&self, resets: &mut pac::RESETS) {
                 resets.reset.modify(|_, w| w.$module().set_bit());
             }
+            fn is_reset_done(&self, resets: &pac::RESETS) -> bool {
+                resets.reset_done.read().$module().bit_is_set()
+            }
         }
     };
 }
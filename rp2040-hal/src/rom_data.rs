@@ -7,6 +7,26 @@
 //! > on the device, as well as highly optimized versions of certain key
 //! > functionality that would otherwise have to take up space in most user
 //! > binaries.
+//!
+//! The functions are looked up by their two-character code in a table of the bootrom. Each one
+//! is declared here with its signature: it can be called directly, which looks it up on each
+//! call unless the `rom-func-cache` feature is enabled, or its pointer can be looked up once
+//! with `ptr()`:
+//!
+//! ```no_run
+//! use rp2040_hal::rom_data;
+//!
+//! assert_eq!(rom_data::popcount32(0xf000_0001), 5);
+//! let reverse32 = rom_data::reverse32::ptr();
+//! assert_eq!(reverse32(1), 0x8000_0000);
+//!
+//! let src = [0x1234_5678; 64];
+//! let mut dest = [0; 64];
+//! rom_data::copy_words(&mut dest, &src);
+//! ```
+//!
+//! Functions not declared here can be looked up with [`rom_func_lookup`], and the public data
+//! of the bootrom with [`rom_data_lookup`].
 
 /// A bootrom function table code.
 pub type RomFnTableCode = [u8; 2];
@@ -39,6 +59,23 @@ fn rom_table_lookup<T>(table: *const u16, tag: RomFnTableCode) -> T {
     }
 }
 
+/// Look up a function of the bootrom by its code, e.g. `*b"P3"` for `popcount32`
+///
+/// Returns null if the bootrom has no such function. See Table 178 in the RP2040 datasheet for
+/// the codes.
+pub fn rom_func_lookup(tag: RomFnTableCode) -> *const u32 {
+    rom_table_lookup(FUNC_TABLE, tag)
+}
+
+/// Look up an entry of the public data of the bootrom by its code, e.g. `*b"GR"` for the git
+/// revision
+///
+/// Returns null if the bootrom has no such entry. See Table 179 in the RP2040 datasheet for the
+/// codes.
+pub fn rom_data_lookup(tag: RomFnTableCode) -> *const u32 {
+    rom_table_lookup(DATA_TABLE, tag)
+}
+
 /// To save space, the ROM likes to store memory pointers (which are 32-bit on
 /// the Cortex-M0+) using only the bottom 16-bits. The assumption is that the
 /// values they point at live in the first 64 KiB of ROM, and the ROM is mapped
@@ -279,6 +316,73 @@ rom_functions! {
     b"WV" unsafe fn wait_for_vector() -> !;
 }
 
+/// Copy `src` to `dest` with [`memcpy44`], the fast copy of word aligned memory
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths.
+pub fn copy_words(dest: &mut [u32], src: &[u32]) {
+    assert_eq!(dest.len(), src.len(), "slices of different lengths");
+    // Safety: both slices are word aligned, and can't overlap as dest is borrowed mutably
+    unsafe {
+        memcpy44(dest.as_mut_ptr(), src.as_ptr(), (src.len() * 4) as u32);
+    }
+}
+
+/// Set all bytes of `dest` to `value` with [`memset4`], the fast fill of word aligned memory
+pub fn fill_words(dest: &mut [u32], value: u8) {
+    // Safety: the slice is word aligned
+    unsafe {
+        memset4(dest.as_mut_ptr(), value, (dest.len() * 4) as u32);
+    }
+}
+
+/// The USB interfaces of the BOOTSEL mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsbBootInterfaces {
+    /// The mass storage drive to copy UF2 files to, and the PICOBOOT interface of picotool
+    Both,
+    /// Only the mass storage drive
+    MassStorage,
+    /// Only the PICOBOOT interface
+    Picoboot,
+}
+
+/// Reset into the BOOTSEL mode, the USB bootloader of the bootrom
+///
+/// This is [`reset_to_usb_boot`] with typed options: the USB interfaces to expose, and
+/// optionally the GPIO of an LED to light on mass storage activity. GPIOs above 29 are ignored.
+/// Firmware can offer to load new firmware without unplugging the board:
+///
+/// ```no_run
+/// use embedded_hal::digital::v2::InputPin;
+/// use rp2040_hal::{gpio::Pins, pac, rom_data::{self, UsbBootInterfaces}, Sio};
+/// let mut pac = pac::Peripherals::take().unwrap();
+/// let sio = Sio::new(pac.SIO);
+/// let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+/// let button = pins.gpio15.into_pull_up_input();
+/// if button.is_low().unwrap() {
+///     // The LED of the Pico is on GPIO25
+///     rom_data::reboot_to_usb_boot(UsbBootInterfaces::Both, Some(25));
+/// }
+/// ```
+pub fn reboot_to_usb_boot(interfaces: UsbBootInterfaces, activity_led: Option<u8>) -> ! {
+    let disable_interface_mask = match interfaces {
+        UsbBootInterfaces::Both => 0,
+        UsbBootInterfaces::MassStorage => 2,
+        UsbBootInterfaces::Picoboot => 1,
+    };
+    let gpio_activity_pin_mask = activity_led
+        .filter(|&pin| pin < 30)
+        .map_or(0, |pin| 1 << pin);
+    reset_to_usb_boot(gpio_activity_pin_mask, disable_interface_mask);
+    // The watchdog resets the chip right away
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
 // Various C intrinsics in the ROM
 intrinsics! {
     #[alias = __popcountdi2]
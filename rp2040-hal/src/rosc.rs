@@ -3,6 +3,8 @@
 
 use fugit::HertzU32;
 
+use crate::clocks::{ClocksManager, FrequencyCounterSource};
+
 /// State of the Ring Oscillator (typestate trait)
 pub trait State {}
 
@@ -21,6 +23,35 @@ impl State for Disabled {}
 impl State for Enabled {}
 impl State for Dormant {}
 
+/// Frequency range of the ROSC, selecting the number of stages of the ring
+///
+/// The frequency of each range varies a lot from chip to chip, and with the voltage and the
+/// temperature. The nominal frequencies are only estimates, based on the typical 6.5MHz of the
+/// [`Low`](Self::Low) range with a divider of 16.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrequencyRange {
+    /// 8 stages, about 104MHz before the divider
+    Low,
+    /// 6 stages, about 140MHz before the divider
+    Medium,
+    /// 4 stages, about 210MHz before the divider
+    High,
+    /// 2 stages, about 420MHz before the divider. Not recommended, the chip may not run this fast
+    TooHigh,
+}
+
+impl FrequencyRange {
+    fn nominal_freq(self) -> HertzU32 {
+        match self {
+            FrequencyRange::Low => HertzU32::MHz(104),
+            FrequencyRange::Medium => HertzU32::MHz(140),
+            FrequencyRange::High => HertzU32::MHz(210),
+            FrequencyRange::TooHigh => HertzU32::MHz(420),
+        }
+    }
+}
+
 /// A Ring Oscillator.
 pub struct RingOscillator<S: State> {
     device: rp2040_pac::ROSC,
@@ -60,6 +91,17 @@ impl RingOscillator<Disabled> {
             freq_hz: 6_500_000u32.Hz(),
         })
     }
+
+    /// Initializes the ROSC in the given frequency range, divided by `div`.
+    ///
+    /// `div` has to be between 1 and 32, it is clamped otherwise. The frequency returned by
+    /// [`operating_frequency`](RingOscillator::operating_frequency) is an estimate, see
+    /// [`FrequencyRange`].
+    pub fn initialize_with(self, range: FrequencyRange, div: u8) -> RingOscillator<Enabled> {
+        let mut rosc = self.initialize();
+        rosc.set_frequency(range, div);
+        rosc
+    }
 }
 
 impl RingOscillator<Enabled> {
@@ -68,6 +110,124 @@ impl RingOscillator<Enabled> {
         self.state.freq_hz
     }
 
+    /// Change the frequency range and the divider of the ROSC.
+    ///
+    /// The range is changed one step at a time, as recommended by the datasheet. `div` has to
+    /// be between 1 and 32, it is clamped otherwise. The ROSC keeps running while its frequency
+    /// changes, but clocks running from it should be reconfigured afterwards.
+    pub fn set_frequency(&mut self, range: FrequencyRange, div: u8) {
+        // The divider is written as an offset of this value, with 32 encoded as the value
+        // itself
+        const DIV_PASS: u32 = 0xaa0;
+        let div = div.max(1).min(32);
+        self.device
+            .div
+            .write(|w| unsafe { w.bits(DIV_PASS + (div as u32 % 32)) });
+
+        let mut current = self.frequency_range();
+        while current != range {
+            current = match (current < range, current) {
+                (true, FrequencyRange::Low) => FrequencyRange::Medium,
+                (true, FrequencyRange::Medium) => FrequencyRange::High,
+                (true, _) => FrequencyRange::TooHigh,
+                (false, FrequencyRange::TooHigh) => FrequencyRange::High,
+                (false, FrequencyRange::High) => FrequencyRange::Medium,
+                (false, _) => FrequencyRange::Low,
+            };
+            self.device.ctrl.modify(|_, w| match current {
+                FrequencyRange::Low => w.freq_range().low(),
+                FrequencyRange::Medium => w.freq_range().medium(),
+                FrequencyRange::High => w.freq_range().high(),
+                FrequencyRange::TooHigh => w.freq_range().toohigh(),
+            });
+        }
+
+        self.state.freq_hz = range.nominal_freq() / div as u32;
+    }
+
+    /// Set the drive strength of the eight stages of the ring, from 0 to 3.
+    ///
+    /// Higher drive strengths make the ROSC faster, within its frequency range. Values above 3
+    /// are clamped. The stages used by the current range are the first ones: 8 for
+    /// [`FrequencyRange::Low`] down to 2 for [`FrequencyRange::TooHigh`].
+    pub fn set_drive_strengths(&mut self, strengths: [u8; 8]) {
+        // The registers can only be written with this password in their upper half
+        const PASSWD: u32 = 0x9696 << 16;
+        // Each drive strength level enables one more bit of the stage's field
+        let field = |stage: usize| ((1u32 << strengths[stage].min(3)) - 1) << (stage % 4 * 4);
+        let freqa = (0..4).fold(PASSWD, |bits, stage| bits | field(stage));
+        let freqb = (4..8).fold(PASSWD, |bits, stage| bits | field(stage));
+        self.device.freqa.write(|w| unsafe { w.bits(freqa) });
+        self.device.freqb.write(|w| unsafe { w.bits(freqb) });
+    }
+
+    /// Drive strength of the eight stages of the ring, from 0 to 3.
+    pub fn drive_strengths(&self) -> [u8; 8] {
+        let freqa = self.device.freqa.read().bits();
+        let freqb = self.device.freqb.read().bits();
+        let mut strengths = [0; 8];
+        for (stage, strength) in strengths.iter_mut().enumerate() {
+            let bits = if stage < 4 { freqa } else { freqb };
+            *strength = ((bits >> (stage % 4 * 4)) & 0b111).count_ones() as u8;
+        }
+        strengths
+    }
+
+    /// Measure the frequency of the ROSC with the frequency counter, and use it from now on.
+    ///
+    /// clk_ref has to run from an accurate source, like the XOSC, for the measurement to be
+    /// meaningful. Clocks already running from the ROSC keep their previous frequency until
+    /// they are reconfigured.
+    pub fn calibrate(&mut self, clocks: &mut ClocksManager) -> HertzU32 {
+        self.state.freq_hz = clocks.measure_frequency(FrequencyCounterSource::Rosc);
+        self.state.freq_hz
+    }
+
+    /// Trim the ROSC toward `target`, and return the frequency reached.
+    ///
+    /// Starting with all drive strengths at 0, the stages are sped up one level at a time, as
+    /// long as the frequency stays at or below `target`. The range and the divider have to be
+    /// chosen so that the ROSC is slower than `target` with the lowest drive strengths.
+    ///
+    /// Like [`calibrate`](Self::calibrate), this measures the ROSC against clk_ref. It should
+    /// not be the source of clk_ref or clk_sys while trimming, as the frequency changes.
+    pub fn trim_to(&mut self, target: HertzU32, clocks: &mut ClocksManager) -> HertzU32 {
+        let mut strengths = [0; 8];
+        self.set_drive_strengths(strengths);
+        let mut freq = self.calibrate(clocks);
+
+        let steps = (0..8).flat_map(|stage| (1..=3).map(move |level| (stage, level)));
+        for (stage, level) in steps {
+            strengths[stage] = level;
+            self.set_drive_strengths(strengths);
+            let trimmed = self.calibrate(clocks);
+            if trimmed > target {
+                strengths[stage] = level - 1;
+                self.set_drive_strengths(strengths);
+                break;
+            }
+            freq = trimmed;
+        }
+
+        self.state.freq_hz = freq;
+        freq
+    }
+
+    /// Current frequency range of the ROSC.
+    pub fn frequency_range(&self) -> FrequencyRange {
+        let ctrl = self.device.ctrl.read();
+        let range = ctrl.freq_range();
+        if range.is_medium() {
+            FrequencyRange::Medium
+        } else if range.is_high() {
+            FrequencyRange::High
+        } else if range.is_toohigh() {
+            FrequencyRange::TooHigh
+        } else {
+            FrequencyRange::Low
+        }
+    }
+
     /// Disables the ROSC
     pub fn disable(self) -> RingOscillator<Disabled> {
         self.device.ctrl.modify(|_r, w| w.enable().disable());
@@ -81,6 +241,11 @@ impl RingOscillator<Enabled> {
         self.device.randombit.read().randombit().bit()
     }
 
+    /// Get a random number generator with whitened output, see [`RoscRng`]
+    pub fn rng(&self) -> RoscRng<'_> {
+        RoscRng { rosc: self }
+    }
+
     /// Put the ROSC in DORMANT state.
     ///
     /// # Safety
@@ -98,6 +263,83 @@ impl RingOscillator<Enabled> {
     }
 }
 
+/// Random number generator based on the random bit of the ROSC
+///
+/// The raw random bits are biased, and depend on the configuration of the ROSC. This applies
+/// a von Neumann extractor: the bits are read in pairs, `01` and `10` give a 0 and a 1, and
+/// equal pairs are discarded. This removes the bias, at the cost of reading at least four raw
+/// bits per output bit.
+///
+/// A ROSC stuck on one value would never give an unequal pair, so a bit is given up after
+/// [`RoscRng::MAX_PAIRS`] equal pairs: [`try_fill_bytes`](rand_core::RngCore::try_fill_bytes)
+/// then returns an error, and [`fill_bytes`](rand_core::RngCore::fill_bytes) panics.
+///
+/// The entropy of the random bit has not been characterized, so this is better used to seed a
+/// cryptographically secure generator than directly for keys or nonces.
+///
+/// ```no_run
+/// use rand_core::RngCore;
+/// use rp2040_hal::{pac, rosc::RingOscillator};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let rosc = RingOscillator::new(peripherals.ROSC).initialize();
+/// let mut seed = [0u8; 32];
+/// rosc.rng().fill_bytes(&mut seed);
+/// ```
+pub struct RoscRng<'a> {
+    rosc: &'a RingOscillator<Enabled>,
+}
+
+impl RoscRng<'_> {
+    /// Number of pairs of raw bits read for one whitened bit before giving up
+    pub const MAX_PAIRS: u32 = 1024;
+
+    /// Error code of [`rand_core::Error`] returned if the raw bits don't change
+    pub const ERROR_STUCK: u32 = rand_core::Error::CUSTOM_START;
+
+    /// Get a whitened random bit
+    ///
+    /// Returns `None` if the [`MAX_PAIRS`](Self::MAX_PAIRS) pairs of raw bits read were all
+    /// equal.
+    pub fn get_bit(&mut self) -> Option<bool> {
+        for _ in 0..Self::MAX_PAIRS {
+            let first = self.rosc.get_random_bit();
+            let second = self.rosc.get_random_bit();
+            if first != second {
+                return Some(first);
+            }
+        }
+        None
+    }
+}
+
+impl rand_core::RngCore for RoscRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("the random bit of the ROSC doesn't change")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        for chunk in dest.iter_mut() {
+            for _ in 0..8 {
+                let bit = self.get_bit().ok_or_else(|| {
+                    rand_core::Error::from(core::num::NonZeroU32::new(Self::ERROR_STUCK).unwrap())
+                })?;
+                *chunk = (*chunk << 1) | bit as u8;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Uses the raw random bits, see [`RoscRng`] for a whitened output
 impl rand_core::RngCore for RingOscillator<Enabled> {
     fn next_u32(&mut self) -> u32 {
         rand_core::impls::next_u32_via_fill(self)
@@ -10,6 +10,7 @@ pub type DayOfWeek = chrono::Weekday;
 ///
 /// [`DateTimeFilter`]: struct.DateTimeFilter.html
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// The [DateTime] has an invalid year. The year must be between 0 and 4095.
     InvalidYear,
@@ -33,6 +34,10 @@ pub(crate) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
     }
 }
 
+pub(super) fn year(dt: &DateTime) -> u16 {
+    dt.year() as u16
+}
+
 pub(super) fn write_setup_0(dt: &DateTime, w: &mut setup_0::W) {
     // Safety: the `.bits()` fields are marked `unsafe` but all bit values are valid
     unsafe {
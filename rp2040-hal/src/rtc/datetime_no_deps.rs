@@ -4,6 +4,7 @@ use rp2040_pac::rtc::{rtc_0, rtc_1, setup_0, setup_1};
 ///
 /// [`DateTimeFilter`]: struct.DateTimeFilter.html
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// The [DateTime] contains an invalid year value. Must be between `0..=4095`.
     InvalidYear,
@@ -25,6 +26,9 @@ pub enum Error {
 }
 
 /// Structure containing date and time information
+///
+/// [`DateTime::new`] computes the day of the week from the date, so it is always consistent.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct DateTime {
     /// 0..4095
     pub year: u16,
@@ -42,9 +46,42 @@ pub struct DateTime {
     pub second: u8,
 }
 
+impl DateTime {
+    /// Create a new `DateTime`, computing the day of the week from the date.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if any of the fields is out of range, including days which don't
+    /// exist in the given month.
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+    ) -> Result<Self, Error> {
+        let dt = DateTime {
+            year,
+            month,
+            day,
+            day_of_week: DayOfWeek::Sunday,
+            hour,
+            minute,
+            second,
+        };
+        validate_datetime(&dt)?;
+        Ok(DateTime {
+            day_of_week: DayOfWeek::from_date(year, month, day),
+            ..dt
+        })
+    }
+}
+
 /// A day of the week
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[allow(missing_docs)]
 pub enum DayOfWeek {
     Sunday = 0,
@@ -56,6 +93,24 @@ pub enum DayOfWeek {
     Saturday = 6,
 }
 
+impl DayOfWeek {
+    /// The day of the week of a date in the Gregorian calendar.
+    ///
+    /// The date is expected to be valid, i.e. `month` in `1..=12` and `day` in `1..=31`.
+    pub fn from_date(year: u16, month: u8, day: u8) -> Self {
+        // Sakamoto's method
+        const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let month = month.clamp(1, 12);
+        let year = i32::from(year) - i32::from(month < 3);
+        let days = year + year.div_euclid(4) - year.div_euclid(100)
+            + year.div_euclid(400)
+            + OFFSETS[usize::from(month - 1)]
+            + i32::from(day);
+        // The remainder is in 0..7, which are all valid days of the week
+        day_of_week_from_u8(days.rem_euclid(7) as u8).unwrap_or(DayOfWeek::Sunday)
+    }
+}
+
 fn day_of_week_from_u8(v: u8) -> Result<DayOfWeek, Error> {
     Ok(match v {
         0 => DayOfWeek::Sunday,
@@ -78,7 +133,7 @@ pub(super) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
         Err(Error::InvalidYear)
     } else if dt.month < 1 || dt.month > 12 {
         Err(Error::InvalidMonth)
-    } else if dt.day < 1 || dt.day > 31 {
+    } else if dt.day < 1 || dt.day > super::days_in_month(dt.year, dt.month) {
         Err(Error::InvalidDay)
     } else if dt.hour > 23 {
         Err(Error::InvalidHour)
@@ -91,6 +146,10 @@ pub(super) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
     }
 }
 
+pub(super) fn year(dt: &DateTime) -> u16 {
+    dt.year
+}
+
 pub(super) fn write_setup_0(dt: &DateTime, w: &mut setup_0::W) {
     // Safety: the `.bits()` fields are marked `unsafe` but all bit values are valid
     unsafe {
@@ -23,6 +23,21 @@ pub struct DateTimeFilter {
 }
 
 impl DateTimeFilter {
+    /// A filter matching every day at `hour`:`minute`:00
+    pub fn every_day_at(hour: u8, minute: u8) -> Self {
+        Self::default().hour(hour).minute(minute).second(0)
+    }
+
+    /// A filter matching every hour at `minute`:00
+    pub fn every_hour_at(minute: u8) -> Self {
+        Self::default().minute(minute).second(0)
+    }
+
+    /// A filter matching once per minute, at :00 seconds
+    pub fn every_minute() -> Self {
+        Self::default().second(0)
+    }
+
     /// Set a filter on the given year
     pub fn year(mut self, year: u16) -> Self {
         self.year = Some(year);
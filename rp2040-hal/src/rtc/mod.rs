@@ -2,18 +2,69 @@
 //!
 //! A [`RealTimeClock`] can be configured with an initial [`DateTime`]. Afterwards the clock will track time automatically. The current `DateTime` can be retrieved by [`RealTimeClock::now()`].
 //!
+//! ## Usage
+//!
+//! ```no_run
+//! # #[cfg(feature = "chrono")]
+//! # fn main() { }
+//! # #[cfg(not(feature = "chrono"))]
+//! # fn main() {
+//! use rp2040_hal::{clocks::init_clocks_and_plls, pac, rtc::{DateTime, DayOfWeek, RealTimeClock}, watchdog::Watchdog};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+//! let clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+//! let initial_date = DateTime {
+//!     year: 2022,
+//!     month: 9,
+//!     day: 1,
+//!     day_of_week: DayOfWeek::Thursday,
+//!     hour: 12,
+//!     minute: 0,
+//!     second: 0,
+//! };
+//! let rtc = RealTimeClock::new(peripherals.RTC, clocks.rtc_clock, &mut peripherals.RESETS, initial_date).unwrap();
+//! let now = rtc.now().unwrap();
+//! # }
+//! ```
+//!
 //! With the **chrono** feature enabled, the following types will be alias for chrono types:
 //! - `DateTime`: `chrono::NaiveDateTime`
 //! - `DayOfWeek`: `chrono::Weekday`
 //!
+//! ## External clock
+//!
+//! For better accuracy, clk_rtc can be driven by a precise 32.768kHz clock, like a TCXO or a
+//! crystal oscillator module, connected to one of the clock inputs:
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{clocks::{Clock, ClocksManager, GPin0}, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+//! let gpin0 = GPin0::new(pins.gpio20.into_mode(), 32768.Hz());
+//! clocks.rtc_clock.configure_clock(&gpin0, 32768.Hz()).unwrap();
+//! // Then pass clocks.rtc_clock to RealTimeClock::new
+//! ```
+//!
+//! ## Sleeping until an alarm
+//!
+//! [`RealTimeClock::sleep_until`] stops all clocks except the ones of the RTC, and waits in
+//! [sleep mode](crate::sleep) for an alarm. The chip draws only a fraction of its running current in the meantime, so a
+//! datalogger can sleep between measurements and wake up at a given wall-clock time. clk_rtc has
+//! to be driven by a clock which keeps running during sleep, like the XOSC, a PLL or a clock
+//! input.
+//!
 //! # Notes
 //!
 //! There are some things to take into account. As per the datasheet:
 //!
 //! - **Day of week**: The RTC will not compute the correct day of the week; it will only increment the existing value.
 //!   - With the `chrono` feature, the day of week is calculated by chrono and should be correct. The value from the rp2040 itself is not used.
+//!   - Without it, `DateTime::new` computes the day of week from the date.
 //! - **Leap year**: If the current year is evenly divisible by 4, a leap year is detected, then Feb 28th is followed by Feb 29th instead  of  March  1st.
 //!   - There are cases where this is incorrect, e.g. century years have no leap day, but the chip will still add a Feb 29th.
+//!   - [`RealTimeClock::set_datetime`] disables leap year checking when the year being set is such a century year, unless it was configured with `set_leap_year_check`.
 //!   - To disable leap year checking and never have a Feb 29th, call `RealTimeClock::set_leap_year_check(false)`.
 //!
 //! Other limitations:
@@ -23,7 +74,9 @@
 
 use crate::clocks::Clock;
 use crate::clocks::RtcClock;
-use rp2040_pac::{RESETS, RTC};
+use crate::sleep::WakeSources;
+use cortex_m::peripheral::{NVIC, SCB};
+use rp2040_pac::{Interrupt, RESETS, RTC};
 
 mod filter;
 
@@ -38,6 +91,8 @@ pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
 /// A reference to the real time clock of the system
 pub struct RealTimeClock {
     rtc: RTC,
+    // Set once the leap year check was configured by the user, so `set_datetime` keeps it
+    leap_year_check_overridden: bool,
 }
 
 impl RealTimeClock {
@@ -68,16 +123,27 @@ impl RealTimeClock {
         let freq = clock.freq().to_Hz() - 1;
         rtc.clkdiv_m1.write(|w| unsafe { w.bits(freq) });
 
-        let mut result = Self { rtc };
-        result.set_leap_year_check(true); // should be on by default, make sure this is the case.
+        crate::sleep::peripheral_enabled::<RTC>();
+
+        let mut result = Self {
+            rtc,
+            leap_year_check_overridden: false,
+        };
+        result.write_leap_year_check(true); // should be on by default, make sure this is the case.
         result.set_datetime(initial_date)?;
         Ok(result)
     }
 
     /// Enable or disable the leap year check. The rp2040 chip will always add a Feb 29th on every year that is divisable by 4, but this may be incorrect (e.g. on century years). This function allows you to disable this check.
     ///
-    /// Leap year checking is enabled by default.
+    /// Leap year checking is enabled by default. Once this was called, [`set_datetime`](Self::set_datetime)
+    /// doesn't change the setting anymore.
     pub fn set_leap_year_check(&mut self, leap_year_check_enabled: bool) {
+        self.leap_year_check_overridden = true;
+        self.write_leap_year_check(leap_year_check_enabled);
+    }
+
+    fn write_leap_year_check(&mut self, leap_year_check_enabled: bool) {
         self.rtc
             .ctrl
             .modify(|_, w| w.force_notleapyear().bit(!leap_year_check_enabled));
@@ -90,6 +156,10 @@ impl RealTimeClock {
 
     /// Set the datetime to a new value.
     ///
+    /// Unless it was configured with [`set_leap_year_check`](Self::set_leap_year_check), this
+    /// also configures the leap year check for the year being set: it is disabled for century
+    /// years which are not leap years, like 2100, and enabled otherwise.
+    ///
     /// # Errors
     ///
     /// Will return `RtcError::InvalidDateTime` if the datetime is not a valid range.
@@ -110,10 +180,15 @@ impl RealTimeClock {
             self::datetime::write_setup_1(&t, w);
             w
         });
+        if !self.leap_year_check_overridden {
+            let year = self::datetime::year(&t);
+            self.write_leap_year_check(year % 100 != 0 || is_leap_year(year));
+        }
 
-        // Load the new datetime and re-enable RTC
-        self.rtc.ctrl.write(|w| w.load().set_bit());
-        self.rtc.ctrl.write(|w| w.rtc_enable().set_bit());
+        // Load the new datetime and re-enable RTC. CTRL is modified rather than written, so the
+        // leap year setting is kept.
+        self.rtc.ctrl.modify(|_, w| w.load().set_bit());
+        self.rtc.ctrl.modify(|_, w| w.rtc_enable().set_bit());
         while self.rtc.ctrl.read().rtc_active().bit_is_clear() {
             core::hint::spin_loop();
         }
@@ -152,6 +227,10 @@ impl RealTimeClock {
 
     /// Schedule an alarm. The `filter` determines at which point in time this alarm is set.
     ///
+    /// Fields which are not set in the filter are ignored, so the alarm fires for every value of
+    /// them. To fire once per occurrence, set all fields down to the second, e.g. with
+    /// [`DateTimeFilter::every_day_at`].
+    ///
     /// Keep in mind that the filter only triggers on the specified time. If you want to schedule this alarm every minute, you have to call:
     /// ```no_run
     /// # #[cfg(feature = "chrono")]
@@ -193,10 +272,92 @@ impl RealTimeClock {
     pub fn clear_interrupt(&mut self) {
         self.disable_alarm();
     }
+
+    /// Returns true if an alarm is scheduled.
+    pub fn is_alarm_enabled(&self) -> bool {
+        self.rtc.irq_setup_0.read().match_active().bit_is_set()
+    }
+
+    /// Enable the `RTC_IRQ` interrupt, triggered when the scheduled alarm matches.
+    pub fn enable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().set_bit());
+    }
+
+    /// Disable the `RTC_IRQ` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().clear_bit());
+    }
+
+    /// Returns true if the alarm matched and [`clear_interrupt`] hasn't been called since.
+    ///
+    /// This reflects the raw status, so it can also be used to poll for the alarm while the
+    /// interrupt is disabled. The alarm is the only source of the `RTC_IRQ` interrupt.
+    ///
+    /// [`clear_interrupt`]: #method.clear_interrupt
+    pub fn is_interrupt_pending(&self) -> bool {
+        self.rtc.intr.read().rtc().bit_is_set()
+    }
+
+    /// Sleep until the alarm given by `filter` matches.
+    ///
+    /// This gates all clocks except the ones of the RTC and enters deep sleep: the core and all other
+    /// peripherals stop until the alarm wakes the chip. The clocks are restored afterwards, and
+    /// the alarm and its interrupt are cleared, so the `RTC_IRQ` handler is not called.
+    ///
+    /// Interrupts are disabled during the call, other sources don't wake the chip. Core 1, if it
+    /// is running, should be idle as its clock is stopped as well.
+    pub fn sleep_until(&mut self, filter: DateTimeFilter, scb: &mut SCB) {
+        cortex_m::interrupt::free(|_| {
+            let irq_was_enabled = NVIC::is_enabled(Interrupt::RTC_IRQ);
+
+            self.schedule_alarm(filter);
+            self.enable_interrupt();
+            // With interrupts disabled, the pending interrupt still wakes the core from WFI
+            // without being taken.
+            unsafe {
+                NVIC::unmask(Interrupt::RTC_IRQ);
+            }
+
+            let wake = WakeSources::new().rtc();
+            crate::sleep::deep_sleep(scb, &wake, || self.is_interrupt_pending());
+
+            self.clear_interrupt();
+            self.disable_interrupt();
+            NVIC::unpend(Interrupt::RTC_IRQ);
+            if !irq_was_enabled {
+                NVIC::mask(Interrupt::RTC_IRQ);
+            }
+        })
+    }
+
+    /// Stop the real time clock and release the RTC peripheral.
+    pub fn free(self) -> RTC {
+        crate::sleep::peripheral_disabled::<RTC>();
+        self.rtc.ctrl.modify(|_, w| w.rtc_enable().clear_bit());
+        while self.rtc.ctrl.read().rtc_active().bit_is_set() {
+            core::hint::spin_loop();
+        }
+        self.rtc
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+#[cfg_attr(feature = "chrono", allow(dead_code))]
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
 }
 
 /// Errors that can occur on methods on [RtcClock]
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum RtcError {
     /// An invalid DateTime was given or stored on the hardware.
     InvalidDateTime(DateTimeError),
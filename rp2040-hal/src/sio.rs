@@ -17,20 +17,69 @@
 //! # let sio = Sio::new(peripherals.SIO);
 //! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
 //! ```
+//!
+//! ## Hardware divider
+//!
+//! [`HwDivider`] computes the quotient and the remainder of a 32-bit division at once, in 8
+//! cycles:
+//!
+//! ```no_run
+//! # use rp2040_hal::{pac, sio::Sio};
+//! # let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let result = sio.hwdivider.signed(-7, 2);
+//! assert_eq!((result.quotient, result.remainder), (-3, -1));
+//! ```
+//!
+//! Unless the `disable-intrinsics` feature is enabled, the compiler's division intrinsics
+//! (`__aeabi_uidiv` and friends) use the divider too, so `/` and `%` on 32-bit integers are
+//! accelerated everywhere.
+//!
+//! Each core has its own divider, whose state is only valid until the next division. If an
+//! interrupt handler divides while the interrupted code is waiting for a result, the result
+//! would be lost: the divider sets its DIRTY flag when it's written, and every division done
+//! through the HAL first saves the state of a dirty divider and restores it afterwards. This
+//! makes them safe to use from interrupt handlers. Code accessing the divider registers
+//! directly has to do the same, or run with interrupts disabled.
 
 use super::*;
+use core::cell::RefCell;
 use core::convert::Infallible;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 /// Marker struct for ownership of SIO gpio bank0
 pub struct SioGpioBank0 {
     _private: (),
 }
 
-/// Marker struct for ownership of SIO FIFO
+/// Ownership of the inter-core FIFO of the current core
 pub struct SioFifo {
-    _private: (),
+    callback: Option<fn(u32)>,
+}
+
+/// Errors flagged by the inter-core FIFO, see [`SioFifo::check_errors`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FifoError {
+    /// A value was written while the FIFO was full, and was lost (WOF)
+    Overflow,
+    /// The FIFO was read while empty, returning garbage (ROE)
+    Underflow,
+    /// Both of the above happened
+    OverflowAndUnderflow,
 }
 
+/// Word sent through the FIFO to lock the receiving core out of the flash
+///
+/// [`SioFifo::on_interrupt`] doesn't pass it to the callback, see
+/// [`enable_interrupt`](SioFifo::enable_interrupt).
+pub const LOCKOUT_START: u32 = 0x73a8_831e;
+/// Word sent through the FIFO to release the core locked out with [`LOCKOUT_START`]
+pub const LOCKOUT_END: u32 = !LOCKOUT_START;
+
+// Whether core 0 and core 1 handle their FIFO interrupt, and so can be locked out
+static LOCKOUT_READY: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
 /// Marker struct for ownership of SIO gpio qspi
 pub struct SioGpioQspi {
     _private: (),
@@ -42,6 +91,8 @@ pub struct HwDivider {
 }
 
 /// Result of divide/modulo operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct DivResult<T> {
     /// The quotient of divide/modulo operation
     pub quotient: T,
@@ -73,7 +124,7 @@ impl Sio {
             _sio: sio,
             gpio_bank0: SioGpioBank0 { _private: () },
             gpio_qspi: SioGpioQspi { _private: () },
-            fifo: SioFifo { _private: () },
+            fifo: SioFifo { callback: None },
             hwdivider: HwDivider { _private: () },
             interp0: Interp0 {
                 lane0: Interp0Lane0 { _private: () },
@@ -91,6 +142,73 @@ impl Sio {
         // Safety: it is always safe to read this read-only register
         unsafe { (*pac::SIO::ptr()).cpuid.read().bits() as u8 }
     }
+
+    /// Returns the core we are running on, as a [`CpuId`].
+    pub fn cpu_id() -> CpuId {
+        if Self::core() == 0 {
+            CpuId::Core0
+        } else {
+            CpuId::Core1
+        }
+    }
+}
+
+/// Identifies one of the two cores, see [`Sio::cpu_id`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CpuId {
+    /// Core 0, which runs the application after boot
+    Core0 = 0,
+    /// Core 1, started with [`Multicore`](crate::multicore::Multicore)
+    Core1 = 1,
+}
+
+/// A value for each core, of which each core only accesses its own
+///
+/// This lets code shared by both cores keep per-core state in a `static`, such as statistics
+/// updated by an interrupt handler running on either core:
+///
+/// ```no_run
+/// use rp2040_hal::sio::CoreLocal;
+///
+/// static IRQ_COUNT: CoreLocal<u32> = CoreLocal::new(0, 0);
+///
+/// fn on_irq() {
+///     IRQ_COUNT.with(|count| *count += 1);
+/// }
+/// ```
+///
+/// The value is borrowed with the interrupts of the current core disabled, so it can be
+/// accessed from interrupt handlers too. The other core never touches it.
+pub struct CoreLocal<T> {
+    values: [RefCell<T>; 2],
+}
+
+// Safety: each core only accesses its own value, with its interrupts disabled
+unsafe impl<T: Send> Sync for CoreLocal<T> {}
+
+impl<T> CoreLocal<T> {
+    /// Create the values of core 0 and core 1
+    pub const fn new(core0: T, core1: T) -> Self {
+        Self {
+            values: [RefCell::new(core0), RefCell::new(core1)],
+        }
+    }
+
+    /// Run `f` with the value of the current core
+    ///
+    /// # Panics
+    ///
+    /// If `f` calls `with` on the same `CoreLocal` again.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        cortex_m::interrupt::free(|_| f(&mut self.values[Sio::core() as usize].borrow_mut()))
+    }
+
+    /// Consume the `CoreLocal`, returning the values of core 0 and core 1
+    pub fn into_inner(self) -> (T, T) {
+        let [core0, core1] = self.values;
+        (core0.into_inner(), core1.into_inner())
+    }
 }
 
 impl SioFifo {
@@ -118,6 +236,124 @@ impl SioFifo {
         sio.fifo_st.read().bits()
     }
 
+    /// Unmask the FIFO interrupt of this core in the NVIC
+    ///
+    /// SIO_IRQ_PROC0 on core 0, or SIO_IRQ_PROC1 on core 1, is pending while the FIFO has data
+    /// for this core or an error flag is set. The other core can use it as a doorbell, writing
+    /// to the FIFO to interrupt this one. The handler should read the FIFO until it's empty,
+    /// e.g. with [`on_interrupt`](Self::on_interrupt), so the FIFO is shared with the handler:
+    ///
+    /// ```no_run
+    /// use core::cell::RefCell;
+    /// use critical_section::Mutex;
+    /// use rp2040_hal::{pac::interrupt, sio::{Sio, SioFifo}};
+    ///
+    /// static FIFO: Mutex<RefCell<Option<SioFifo>>> = Mutex::new(RefCell::new(None));
+    ///
+    /// fn new_buffer(index: u32) {
+    ///     // ...
+    /// }
+    ///
+    /// let mut peripherals = rp2040_hal::pac::Peripherals::take().unwrap();
+    /// let mut sio = Sio::new(peripherals.SIO);
+    /// sio.fifo.set_callback(Some(new_buffer));
+    /// sio.fifo.enable_interrupt();
+    /// critical_section::with(|cs| FIFO.borrow(cs).replace(Some(sio.fifo)));
+    ///
+    /// #[interrupt]
+    /// fn SIO_IRQ_PROC0() {
+    ///     critical_section::with(|cs| {
+    ///         if let Some(fifo) = FIFO.borrow(cs).borrow_mut().as_mut() {
+    ///             fifo.on_interrupt();
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    ///
+    /// [`Core::spawn`](crate::multicore::Core::spawn) and [`Core::reset`](crate::multicore::Core::reset)
+    /// mask the interrupt while they talk to the bootrom of core 1 through the FIFO.
+    ///
+    /// While the interrupt is enabled, the other core can lock this one out to erase or program
+    /// the [flash](crate::flash): the handler has to call [`on_interrupt`](Self::on_interrupt),
+    /// which then spins in RAM until the flash is available again. The words
+    /// [`LOCKOUT_START`] and [`LOCKOUT_END`] are reserved for this.
+    pub fn enable_interrupt(&mut self) {
+        LOCKOUT_READY[Sio::core() as usize].store(true, Ordering::Relaxed);
+        // Safety: the handler of the interrupt only accesses the FIFO of this core
+        unsafe {
+            pac::NVIC::unmask(Self::interrupt());
+        }
+    }
+
+    /// Mask the FIFO interrupt of this core in the NVIC
+    pub fn disable_interrupt(&mut self) {
+        pac::NVIC::mask(Self::interrupt());
+        LOCKOUT_READY[Sio::core() as usize].store(false, Ordering::Relaxed);
+    }
+
+    /// Clear the error flags, and the pending FIFO interrupt of this core in the NVIC
+    ///
+    /// The interrupt stays pending while the FIFO has data.
+    pub fn clear_interrupt(&mut self) {
+        let _ = self.check_errors();
+        pac::NVIC::unpend(Self::interrupt());
+    }
+
+    /// Call `callback` with each value received by this core in [`on_interrupt`](Self::on_interrupt)
+    pub fn set_callback(&mut self, callback: Option<fn(u32)>) {
+        self.callback = callback;
+    }
+
+    /// Handle the FIFO interrupt of the current core
+    ///
+    /// This reads the FIFO until it's empty, giving each value to the callback registered with
+    /// [`set_callback`](Self::set_callback), or dropping it without a callback. It also clears
+    /// the error flags.
+    ///
+    /// On [`LOCKOUT_START`], it acknowledges the word and spins in RAM with the interrupts
+    /// disabled, until the other core sends [`LOCKOUT_END`].
+    pub fn on_interrupt(&mut self) {
+        while let Some(value) = self.read() {
+            if value == LOCKOUT_START {
+                // Safety: the lockout only accesses the FIFO of this core, from RAM
+                cortex_m::interrupt::free(|_| unsafe { wait_for_lockout_end() });
+            } else if let Some(callback) = self.callback {
+                callback(value);
+            }
+        }
+        let _ = self.check_errors();
+    }
+
+    /// The FIFO interrupt of the current core
+    pub(crate) fn interrupt() -> pac::Interrupt {
+        if Sio::core() == 0 {
+            pac::Interrupt::SIO_IRQ_PROC0
+        } else {
+            pac::Interrupt::SIO_IRQ_PROC1
+        }
+    }
+
+    /// Check whether a write to a full FIFO, or a read from an empty FIFO, happened on this
+    /// core since the last check, and clear the flags.
+    ///
+    /// The methods of `SioFifo` check the FIFO state before accessing it, so this only
+    /// happens with [`write`](Self::write) without checking for space first, or with another
+    /// driver accessing the FIFO.
+    pub fn check_errors(&mut self) -> Result<(), FifoError> {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        let status = sio.fifo_st.read();
+        let (overflow, underflow) = (status.wof().bit_is_set(), status.roe().bit_is_set());
+        // Writing any value clears both flags
+        sio.fifo_st.write(|w| unsafe { w.bits(0xff) });
+
+        match (overflow, underflow) {
+            (false, false) => Ok(()),
+            (true, false) => Err(FifoError::Overflow),
+            (false, true) => Err(FifoError::Underflow),
+            (true, true) => Err(FifoError::OverflowAndUnderflow),
+        }
+    }
+
     /// Write to the inter-core FIFO.
     ///
     /// You must ensure the FIFO has space by calling `is_write_ready`
@@ -180,6 +416,75 @@ impl SioFifo {
     }
 }
 
+/// Acknowledge a lockout, and spin until it ends
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn wait_for_lockout_end() {
+    let sio = &*pac::SIO::ptr();
+    while sio.fifo_st.read().rdy().bit_is_clear() {}
+    sio.fifo_wr.write(|w| w.bits(LOCKOUT_START));
+    loop {
+        while sio.fifo_st.read().vld().bit_is_clear() {}
+        if sio.fifo_rd.read().bits() == LOCKOUT_END {
+            break;
+        }
+    }
+    while sio.fifo_st.read().rdy().bit_is_clear() {}
+    sio.fifo_wr.write(|w| w.bits(LOCKOUT_END));
+}
+
+/// Send `word` to the other core, and wait for it to send it back
+///
+/// Other words received in the meantime are dropped.
+fn lockout_handshake(word: u32) {
+    let sio = unsafe { &*pac::SIO::ptr() };
+    while sio.fifo_st.read().rdy().bit_is_clear() {}
+    sio.fifo_wr.write(|w| unsafe { w.bits(word) });
+    cortex_m::asm::sev();
+    loop {
+        while sio.fifo_st.read().vld().bit_is_clear() {}
+        if sio.fifo_rd.read().bits() == word {
+            break;
+        }
+    }
+}
+
+/// The other core, locked out of the flash by [`lock_out_other_core`] until this is dropped
+pub(crate) struct Lockout {
+    active: bool,
+}
+
+impl Drop for Lockout {
+    fn drop(&mut self) {
+        if self.active {
+            lockout_handshake(LOCKOUT_END);
+        }
+    }
+}
+
+/// Forget whether core 1 handles its FIFO interrupt, once it's reset
+pub(crate) fn core1_reset() {
+    LOCKOUT_READY[1].store(false, Ordering::Relaxed);
+}
+
+/// Make the other core spin in RAM, if it runs code
+///
+/// The interrupts of this core have to be disabled, so that its own FIFO handler doesn't take
+/// the acknowledgement. Returns `None` if the other core runs code, but doesn't handle its FIFO
+/// interrupt to be locked out, see [`SioFifo::enable_interrupt`].
+pub(crate) fn lock_out_other_core(_cs: &cortex_m::interrupt::CriticalSection) -> Option<Lockout> {
+    let other = 1 - Sio::core() as usize;
+    let running = other == 0 || crate::multicore::core1_running();
+    if !running {
+        return Some(Lockout { active: false });
+    }
+    if !LOCKOUT_READY[other].load(Ordering::Relaxed) {
+        return None;
+    }
+    lockout_handshake(LOCKOUT_START);
+    Some(Lockout { active: true })
+}
+
 // This takes advantage of how AAPCS defines a 64-bit return on 32-bit registers
 // by packing it into r0[0:31] and r1[32:63].  So all we need to do is put
 // the remainder in the high order 32 bits of a 64 bit result.   We can also
@@ -337,14 +642,35 @@ fn divider_signed(n: i32, d: i32) -> DivResult<i32> {
 
 impl HwDivider {
     /// Perform hardware unsigned divide/modulo operation
+    ///
+    /// Dividing by zero doesn't panic: the quotient is `u32::MAX`, and the remainder is the
+    /// dividend.
     pub fn unsigned(&self, dividend: u32, divisor: u32) -> DivResult<u32> {
         divider_unsigned(dividend, divisor)
     }
 
     /// Perform hardware signed divide/modulo operation
+    ///
+    /// The quotient is rounded towards zero, like Rust's `/`. Dividing by zero doesn't panic:
+    /// the quotient is -1 for a dividend of 0 or more and 1 for a negative one, and the
+    /// remainder is the dividend.
     pub fn signed(&self, dividend: i32, divisor: i32) -> DivResult<i32> {
         divider_signed(dividend, divisor)
     }
+
+    /// Perform hardware unsigned divide/modulo operation, or return `None` if `divisor` is 0
+    pub fn checked_unsigned(&self, dividend: u32, divisor: u32) -> Option<DivResult<u32>> {
+        (divisor != 0).then(|| divider_unsigned(dividend, divisor))
+    }
+
+    /// Perform hardware signed divide/modulo operation, or return `None` if `divisor` is 0
+    ///
+    /// Like Rust's `checked_div`, this also returns `None` for `i32::MIN / -1`, which
+    /// overflows.
+    pub fn checked_signed(&self, dividend: i32, divisor: i32) -> Option<DivResult<i32>> {
+        (divisor != 0 && !(dividend == i32::MIN && divisor == -1))
+            .then(|| divider_signed(dividend, divisor))
+    }
 }
 
 intrinsics! {
@@ -678,6 +1004,8 @@ pub unsafe fn spinlock_reset() {
 }
 
 /// Configuration struct for one lane of the interpolator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct LaneCtrl {
     /// Bit 22 - Only present on INTERP1 on each core. If CLAMP mode is enabled:  
     /// - LANE0 result is shifted and masked ACCUM0, clamped by a lower bound of  
@@ -813,6 +1141,18 @@ pub trait Interp {
     fn get_base(&self) -> u32;
 }
 
+/// Saved state of an interpolator, see `Interp0::save`
+///
+/// An interrupt handler using an interpolator which is also used by the interrupted code has
+/// to save its state first, and restore it before returning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterpState {
+    accum: [u32; 2],
+    base: [u32; 3],
+    ctrl: [u32; 2],
+}
+
 macro_rules! interpolators {
     (
         $($interp:ident : ( $( [ $lane:ident,$lane_id:expr ] ),+ ) ),+
@@ -882,6 +1222,45 @@ macro_rules! interpolators {
                                 &mut self.[<$lane:lower>]
                             }
                         )+
+
+                        /// Write the lower 16 bits of `v` to BASE0 and the upper 16 bits to
+                        /// BASE1 at once, sign-extending them if the lanes are signed
+                        pub fn set_base_1and0(&mut self, v: u32) {
+                            let sio = unsafe { &*pac::SIO::ptr() };
+                            sio.[<$interp:lower _base_1and0>].write(|w| unsafe { w.bits(v) });
+                        }
+
+                        /// Save the configuration and the state of the interpolator
+                        pub fn save(&self) -> InterpState {
+                            let sio = unsafe { &*pac::SIO::ptr() };
+                            InterpState {
+                                accum: [
+                                    sio.[<$interp:lower _accum0>].read().bits(),
+                                    sio.[<$interp:lower _accum1>].read().bits(),
+                                ],
+                                base: [
+                                    sio.[<$interp:lower _base0>].read().bits(),
+                                    sio.[<$interp:lower _base1>].read().bits(),
+                                    sio.[<$interp:lower _base2>].read().bits(),
+                                ],
+                                ctrl: [
+                                    sio.[<$interp:lower _ctrl_lane0>].read().bits(),
+                                    sio.[<$interp:lower _ctrl_lane1>].read().bits(),
+                                ],
+                            }
+                        }
+
+                        /// Restore a configuration and state returned by [`save`](Self::save)
+                        pub fn restore(&mut self, state: &InterpState) {
+                            let sio = unsafe { &*pac::SIO::ptr() };
+                            sio.[<$interp:lower _accum0>].write(|w| unsafe { w.bits(state.accum[0]) });
+                            sio.[<$interp:lower _accum1>].write(|w| unsafe { w.bits(state.accum[1]) });
+                            sio.[<$interp:lower _base0>].write(|w| unsafe { w.bits(state.base[0]) });
+                            sio.[<$interp:lower _base1>].write(|w| unsafe { w.bits(state.base[1]) });
+                            sio.[<$interp:lower _base2>].write(|w| unsafe { w.bits(state.base[2]) });
+                            sio.[<$interp:lower _ctrl_lane0>].write(|w| unsafe { w.bits(state.ctrl[0]) });
+                            sio.[<$interp:lower _ctrl_lane1>].write(|w| unsafe { w.bits(state.ctrl[1]) });
+                        }
                     }
                     impl Interp for $interp{
                         fn pop(&mut self) ->u32{
@@ -912,3 +1291,88 @@ interpolators!(
     Interp0 : ([Lane0,0],[Lane1,1]),
     Interp1 : ([Lane0,0],[Lane1,1])
 );
+
+impl Interp0 {
+    /// Configure the interpolator for blend mode, see [`blend`](Self::blend)
+    ///
+    /// With `signed`, BASE0 and BASE1 are interpreted as signed values.
+    pub fn configure_blend(&mut self, signed: bool) {
+        const LANE0: u32 = LaneCtrl {
+            blend: true,
+            ..LaneCtrl::new()
+        }
+        .encode();
+        let lane1 = LaneCtrl {
+            signed,
+            ..LaneCtrl::new()
+        };
+
+        self.lane0.set_ctrl(LANE0);
+        self.lane1.set_ctrl(lane1.encode());
+    }
+
+    /// Interpolate linearly between `a` and `b`, by `frac`/256
+    ///
+    /// This returns `a + (b - a) * frac / 256`, computed in a single cycle: 0 returns `a`, and
+    /// 255 returns nearly `b`. The interpolator has to be configured with
+    /// [`configure_blend`](Self::configure_blend) first, with `signed` set if `a` and `b` are
+    /// actually `i32` values.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{pac, sio::Sio};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut sio = Sio::new(peripherals.SIO);
+    /// sio.interp0.configure_blend(false);
+    /// // Fade the brightness of a LED from 500 to 1000
+    /// let brightness = sio.interp0.blend(500, 1000, 128); // 750
+    /// ```
+    pub fn blend(&mut self, a: u32, b: u32, frac: u8) -> u32 {
+        self.lane0.set_base(a);
+        self.lane1.set_base(b);
+        self.lane1.set_accum(u32::from(frac));
+        self.lane1.peek()
+    }
+}
+
+impl Interp1 {
+    /// Configure the interpolator for clamp mode, see [`clamp`](Self::clamp)
+    ///
+    /// The value is shifted right by `shift` bits before being clamped, e.g. to drop the
+    /// fractional bits of a fixed-point value. With `signed`, the comparisons are signed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shift` is larger than 31.
+    pub fn configure_clamp(&mut self, shift: u8, signed: bool) {
+        assert!(shift < 32, "the shift has to be smaller than 32");
+        let lane0 = LaneCtrl {
+            clamp: true,
+            signed,
+            shift,
+            mask_msb: 31 - shift,
+            ..LaneCtrl::new()
+        };
+
+        self.lane0.set_ctrl(lane0.encode());
+    }
+
+    /// Clamp `value`, shifted as configured, to `min..=max` in a single cycle
+    ///
+    /// The interpolator has to be configured with [`configure_clamp`](Self::configure_clamp)
+    /// first, with `signed` set if the values are actually `i32` values.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{pac, sio::Sio};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut sio = Sio::new(peripherals.SIO);
+    /// sio.interp1.configure_clamp(0, true);
+    /// // Limit an audio sample to the range of a 12-bit DAC
+    /// let sample = sio.interp1.clamp(-3000i32 as u32, -2048i32 as u32, 2047) as i32; // -2048
+    /// ```
+    pub fn clamp(&mut self, value: u32, min: u32, max: u32) -> u32 {
+        self.lane0.set_base(min);
+        self.lane1.set_base(max);
+        self.lane0.set_accum(value);
+        self.lane0.peek()
+    }
+}
@@ -0,0 +1,302 @@
+//! Sleep and dormant modes
+//!
+//! In sleep mode, both cores wait for an interrupt with the clocks of all peripherals stopped,
+//! except for the ones needed by the wake sources and the drivers. This saves several mA compared to an idle
+//! loop, without losing any state: execution continues where it stopped.
+//!
+//! [`WakeSources`] selects the peripherals which keep their clocks, and [`sleep`] enters sleep
+//! mode until an interrupt is pending. The interrupt of the wake source has to be enabled in
+//! the peripheral and unmasked in the NVIC, its handler runs right after waking up:
+//!
+//! ```no_run
+//! use fugit::ExtU32;
+//! use rp2040_hal::{pac, sleep::{self, WakeSources}, timer::{Alarm, Timer}};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut core = pac::CorePeripherals::take().unwrap();
+//! let mut timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+//! let mut alarm = timer.alarm_0().unwrap();
+//! alarm.schedule(500_000.micros()).unwrap();
+//! alarm.enable_interrupt();
+//! unsafe {
+//!     pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+//! }
+//! // From here on, only the timer is clocked until its alarm fires
+//! sleep::sleep(&mut core.SCB, &WakeSources::new().timer());
+//! ```
+//!
+//! The drivers which are constructed, like a [`Timer`](crate::timer::Timer) or an enabled
+//! [`UartPeripheral`](crate::uart::UartPeripheral), keep their clocks automatically: a UART
+//! keeps receiving, and its interrupt can wake the chip. Dropping a driver doesn't remove its
+//! clocks, it has to be disabled or freed.
+//!
+//! The clocks themselves keep running during sleep. Lowering their frequency, or running
+//! from the ring oscillator, saves more power.
+//!
+//! ## Dormant mode
+//!
+//! In dormant mode, all oscillators are stopped, bringing the consumption down to a few
+//! hundred µA. Only an edge or level on a GPIO, or an RTC alarm while clk_rtc runs from a
+//! clock input, can wake the chip. [`dormant`] goes through the whole sequence, and restores
+//! the clocks afterwards:
+//!
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{clocks::ClocksManager, gpio::{Interrupt, Pins}, pac, sleep, Sio, xosc::setup_xosc_blocking};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let xosc = setup_xosc_blocking(peripherals.XOSC, 12.MHz()).ok().unwrap();
+//! let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+//! // ... configure the clocks
+//! let mut button = pins.gpio15.into_pull_up_input();
+//! button.set_dormant_wake_enabled(Interrupt::EdgeLow, true);
+//! sleep::dormant(&mut clocks, &xosc).unwrap();
+//! button.clear_interrupt(Interrupt::EdgeLow);
+//! ```
+//!
+//! See [Chapter 2 Section 11](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use crate::clocks::{ClockError, ClocksManager, GatedPeripheral};
+use crate::xosc::{CrystalOscillator, Stable};
+use core::cell::Cell;
+use cortex_m::peripheral::SCB;
+use critical_section::Mutex;
+use pac::{CLOCKS, PLL_SYS, PLL_USB, ROSC, XOSC};
+
+// Bits of SLEEP_EN0
+const EN0_CLK_SYS_IO: u32 = 1 << 8;
+const EN0_CLK_SYS_PADS: u32 = 1 << 11;
+const EN0_CLK_RTC_RTC: u32 = 1 << 21;
+const EN0_CLK_SYS_RTC: u32 = 1 << 22;
+
+// Bits of SLEEP_EN1
+const EN1_CLK_SYS_TIMER: u32 = 1 << 5;
+const EN1_CLK_SYS_USBCTRL: u32 = 1 << 10;
+const EN1_CLK_USB_USBCTRL: u32 = 1 << 11;
+const EN1_CLK_SYS_WATCHDOG: u32 = 1 << 12;
+
+// Clocks of the drivers which are currently constructed
+static DRIVER_CLOCKS: Mutex<Cell<WakeSources>> = Mutex::new(Cell::new(WakeSources::new()));
+
+/// Peripherals which keep their clocks in sleep mode, so that they can wake the chip
+///
+/// The clocks of the drivers which are currently constructed, see [`active_drivers`], are
+/// always added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeSources {
+    sleep_en0: u32,
+    sleep_en1: u32,
+}
+
+impl WakeSources {
+    /// No wake sources, all peripheral clocks stop
+    pub const fn new() -> Self {
+        WakeSources {
+            sleep_en0: 0,
+            sleep_en1: 0,
+        }
+    }
+
+    /// Wake up from a timer alarm
+    ///
+    /// This also keeps the watchdog clocked, as it generates the tick of the timer.
+    pub const fn timer(mut self) -> Self {
+        self.sleep_en1 |= EN1_CLK_SYS_TIMER | EN1_CLK_SYS_WATCHDOG;
+        self
+    }
+
+    /// Wake up from a GPIO interrupt
+    pub const fn gpio(mut self) -> Self {
+        self.sleep_en0 |= EN0_CLK_SYS_IO | EN0_CLK_SYS_PADS;
+        self
+    }
+
+    /// Wake up from an RTC alarm
+    pub const fn rtc(mut self) -> Self {
+        self.sleep_en0 |= EN0_CLK_RTC_RTC | EN0_CLK_SYS_RTC;
+        self
+    }
+
+    /// Wake up from USB activity
+    pub const fn usb(mut self) -> Self {
+        self.sleep_en1 |= EN1_CLK_SYS_USBCTRL | EN1_CLK_USB_USBCTRL;
+        self
+    }
+
+    /// Keep the clocks of a peripheral running, e.g. `peripheral::<pac::UART0>()`
+    pub fn peripheral<P: GatedPeripheral>(self) -> Self {
+        self.with_raw(P::EN0, P::EN1)
+    }
+
+    /// Keep additional clocks running, given as raw values of SLEEP_EN0 and SLEEP_EN1
+    ///
+    /// This allows other peripherals, like a UART, to keep running or to wake the chip.
+    pub const fn with_raw(mut self, sleep_en0: u32, sleep_en1: u32) -> Self {
+        self.sleep_en0 |= sleep_en0;
+        self.sleep_en1 |= sleep_en1;
+        self
+    }
+
+    const fn without(mut self, other: WakeSources) -> Self {
+        self.sleep_en0 &= !other.sleep_en0;
+        self.sleep_en1 &= !other.sleep_en1;
+        self
+    }
+}
+
+/// The clocks needed by the drivers which are currently constructed
+///
+/// A driver, like a UART, adds its clocks when it is enabled and removes them when it is
+/// disabled or freed: it keeps working in sleep mode, and can wake the chip with its
+/// interrupts.
+pub fn active_drivers() -> WakeSources {
+    critical_section::with(|cs| DRIVER_CLOCKS.borrow(cs).get())
+}
+
+/// Keep `clocks` running in sleep mode, as a driver needs them
+pub(crate) fn driver_enabled(clocks: WakeSources) {
+    critical_section::with(|cs| {
+        let active = DRIVER_CLOCKS.borrow(cs);
+        active.set(active.get().with_raw(clocks.sleep_en0, clocks.sleep_en1));
+    })
+}
+
+/// Release the clocks added by [`driver_enabled`]
+///
+/// Each peripheral is owned by a single driver, so the clocks aren't shared between drivers.
+pub(crate) fn driver_disabled(clocks: WakeSources) {
+    critical_section::with(|cs| {
+        let active = DRIVER_CLOCKS.borrow(cs);
+        active.set(active.get().without(clocks));
+    })
+}
+
+/// Shorthand for [`driver_enabled`] with the clocks of a peripheral
+pub(crate) fn peripheral_enabled<P: GatedPeripheral>() {
+    driver_enabled(WakeSources::new().peripheral::<P>());
+}
+
+/// Shorthand for [`driver_disabled`] with the clocks of a peripheral
+pub(crate) fn peripheral_disabled<P: GatedPeripheral>() {
+    driver_disabled(WakeSources::new().peripheral::<P>());
+}
+
+/// Sleep until an interrupt is pending
+///
+/// Only the clocks of `wake` and of the [active drivers](active_drivers) keep running, the
+/// others are restored on wake up. The interrupt
+/// which woke the chip is handled before this function returns.
+///
+/// Core 1, if it is running, should be waiting for an interrupt as well, as the peripherals it
+/// uses may be stopped.
+pub fn sleep(scb: &mut SCB, wake: &WakeSources) {
+    // With interrupts disabled, a pending interrupt still ends the WFI without being taken.
+    // This avoids missing an interrupt which fires before the WFI, it is taken when leaving
+    // the critical section.
+    cortex_m::interrupt::free(|_| deep_sleep(scb, wake, || true));
+}
+
+/// Wait in sleep mode until `done` returns true, checking it after each interrupt
+///
+/// This has to be called with interrupts disabled.
+pub(crate) fn deep_sleep(scb: &mut SCB, wake: &WakeSources, mut done: impl FnMut() -> bool) {
+    // Safety: the sleep enable registers are only changed here, and restored before returning
+    let clocks = unsafe { &*CLOCKS::ptr() };
+    let sleep_en0 = clocks.sleep_en0.read().bits();
+    let sleep_en1 = clocks.sleep_en1.read().bits();
+    let wake = active_drivers().with_raw(wake.sleep_en0, wake.sleep_en1);
+
+    clocks
+        .sleep_en0
+        .write(|w| unsafe { w.bits(wake.sleep_en0) });
+    clocks
+        .sleep_en1
+        .write(|w| unsafe { w.bits(wake.sleep_en1) });
+    scb.set_sleepdeep();
+    loop {
+        cortex_m::asm::wfi();
+        if done() {
+            break;
+        }
+    }
+    scb.clear_sleepdeep();
+    clocks.sleep_en0.write(|w| unsafe { w.bits(sleep_en0) });
+    clocks.sleep_en1.write(|w| unsafe { w.bits(sleep_en1) });
+}
+
+/// Enter dormant mode until a wake source fires
+///
+/// The chip runs from the XOSC, which is stopped until a GPIO with
+/// [dormant wake](crate::gpio::Pin::set_dormant_wake_enabled) enabled, or the RTC alarm with
+/// its interrupt enabled, wakes it. The sequence is:
+/// 1. clk_ref and clk_sys are switched to the XOSC, clk_usb and clk_adc are stopped
+/// 2. the PLLs and the ROSC are powered down
+/// 3. the XOSC goes dormant, and restarts when a wake source fires
+/// 4. the PLLs and the ROSC are restarted, and the clocks are restored
+///
+/// The timer and the watchdog don't count while dormant, as their tick comes from clk_ref.
+/// clk_peri and clk_rtc keep their source, so the RTC only keeps the time if it runs from a
+/// clock input.
+///
+/// Interrupts are disabled during the call. The interrupt of the wake source, if enabled, is
+/// handled before this function returns.
+pub fn dormant(
+    clocks: &mut ClocksManager,
+    xosc: &CrystalOscillator<Stable>,
+) -> Result<(), ClockError> {
+    // Taken from the C SDK
+    const DORMANT_VALUE: u32 = 0x636f6d61;
+
+    cortex_m::interrupt::free(|_| {
+        let saved = clocks.run_from_xosc(xosc)?;
+
+        // Safety: the PLLs and the ROSC are no longer used by the clocks, and are restored to
+        // their previous state before leaving the critical section
+        let pll_sys = unsafe { &*PLL_SYS::ptr() };
+        let pll_usb = unsafe { &*PLL_USB::ptr() };
+        let rosc = unsafe { &*ROSC::ptr() };
+        let pll_sys_pwr = pll_sys.pwr.read().bits();
+        let pll_usb_pwr = pll_usb.pwr.read().bits();
+        let rosc_ctrl = rosc.ctrl.read().bits();
+        pll_sys.pwr.reset();
+        pll_usb.pwr.reset();
+        rosc.ctrl.modify(|_, w| w.enable().disable());
+
+        // Safety: the XOSC is only stopped until a wake source fires, and stable again when
+        // `xosc` is used next
+        let xosc_dev = unsafe { &*XOSC::ptr() };
+        xosc_dev.dormant.write(|w| unsafe { w.bits(DORMANT_VALUE) });
+        while xosc_dev.status.read().stable().bit_is_clear() {
+            cortex_m::asm::nop();
+        }
+
+        rosc.ctrl.write(|w| unsafe { w.bits(rosc_ctrl) });
+        if rosc.ctrl.read().enable().is_enable() {
+            while rosc.status.read().stable().bit_is_clear() {
+                cortex_m::asm::nop();
+            }
+        }
+        restart_pll(pll_sys, pll_sys_pwr);
+        restart_pll(pll_usb, pll_usb_pwr);
+
+        clocks.restore(&saved);
+        Ok(())
+    })
+}
+
+/// Power a PLL up again to `pwr`, waiting for it to lock before enabling its output
+fn restart_pll(pll: &pac::pll_sys::RegisterBlock, pwr: u32) {
+    const PWR_PD: u32 = 1 << 0;
+    const PWR_POSTDIVPD: u32 = 1 << 3;
+
+    if pwr & PWR_PD != 0 {
+        // The PLL was powered down already
+        return;
+    }
+    pll.pwr.write(|w| unsafe { w.bits(pwr | PWR_POSTDIVPD) });
+    while pll.cs.read().lock().bit_is_clear() {
+        cortex_m::asm::nop();
+    }
+    pll.pwr.write(|w| unsafe { w.bits(pwr) });
+}
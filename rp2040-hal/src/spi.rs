@@ -18,7 +18,24 @@
 //!
 //! let spi = Spi::<_, _, 8>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
 //! ```
+//!
+//! ## Async
+//!
+//! With the `async` feature, enabled buses implement the `SpiBus` trait of
+//! embedded-hal-async, waiting for the received words with interrupts. Call
+//! [`on_spi0_interrupt`] or [`on_spi1_interrupt`] from the interrupt handler of the block,
+//! and unmask the interrupt in the NVIC:
+//! ```ignore
+//! #[interrupt]
+//! fn SPI0_IRQ() {
+//!     rp2040_hal::spi::on_spi0_interrupt();
+//! }
+//! ```
+//!
+//! The words are exchanged in bursts of up to 8, the depth of the FIFOs. The last words of a
+//! burst are only signalled by the receive timeout, 32 bit periods after they were received.
 
+use crate::clocks::GatedPeripheral;
 use crate::resets::SubsystemReset;
 use core::{convert::Infallible, marker::PhantomData, ops::Deref};
 #[cfg(feature = "eh1_0_alpha")]
@@ -45,7 +62,10 @@ impl State for Disabled {}
 impl State for Enabled {}
 
 /// Pac SPI device
-pub trait SpiDevice: Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset {}
+pub trait SpiDevice:
+    Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset + GatedPeripheral
+{
+}
 
 impl SpiDevice for pac::SPI0 {}
 impl SpiDevice for pac::SPI1 {}
@@ -72,6 +92,7 @@ impl<S: State, D: SpiDevice, const DS: u8> Spi<S, D, DS> {
 
     /// Releases the underlying device.
     pub fn free(self) -> D {
+        crate::sleep::peripheral_disabled::<D>();
         self.device
     }
 
@@ -167,6 +188,8 @@ impl<D: SpiDevice, const DS: u8> Spi<Disabled, D, DS> {
         // Finally enable the SPI
         self.device.sspcr1.modify(|_, w| w.sse().set_bit());
 
+        crate::sleep::peripheral_enabled::<D>();
+
         self.transition(Enabled { __private: () })
     }
 }
@@ -183,6 +206,8 @@ impl<D: SpiDevice, const DS: u8> Spi<Enabled, D, DS> {
     pub fn disable(self) -> Spi<Disabled, D, DS> {
         self.device.sspcr1.modify(|_, w| w.sse().clear_bit());
 
+        crate::sleep::peripheral_disabled::<D>();
+
         self.transition(Disabled { __private: () })
     }
 }
@@ -272,10 +297,188 @@ macro_rules! impl_write {
             }
         }
 
+        #[cfg(feature = "eh1_0")]
+        impl<D: SpiDevice> eh1_0::spi::ErrorType for Spi<Enabled, D, $nr> {
+            type Error = Infallible;
+        }
+
+        #[cfg(feature = "eh1_0")]
+        impl<D: SpiDevice> eh1_0::spi::SpiBus<$type> for Spi<Enabled, D, $nr> {
+            fn read(&mut self, words: &mut [$type]) -> Result<(), Infallible> {
+                for word in words.iter_mut() {
+                    *word = self.exchange(0)?;
+                }
+                Ok(())
+            }
+
+            fn write(&mut self, words: &[$type]) -> Result<(), Infallible> {
+                for word in words.iter() {
+                    self.exchange(*word)?;
+                }
+                Ok(())
+            }
+
+            fn transfer(&mut self, read: &mut [$type], write: &[$type]) -> Result<(), Infallible> {
+                for i in 0..read.len().max(write.len()) {
+                    let word = self.exchange(write.get(i).copied().unwrap_or(0))?;
+                    if let Some(r) = read.get_mut(i) {
+                        *r = word;
+                    }
+                }
+                Ok(())
+            }
+
+            fn transfer_in_place(&mut self, words: &mut [$type]) -> Result<(), Infallible> {
+                for word in words.iter_mut() {
+                    *word = self.exchange(*word)?;
+                }
+                Ok(())
+            }
+
+            fn flush(&mut self) -> Result<(), Infallible> {
+                while self.device.sspsr.read().bsy().bit_is_set() {}
+                Ok(())
+            }
+        }
+
+        #[cfg(feature = "eh1_0")]
+        impl<D: SpiDevice> Spi<Enabled, D, $nr> {
+            /// Send a word, and return the word received meanwhile
+            fn exchange(&mut self, word: $type) -> Result<$type, Infallible> {
+                nb::block!(FullDuplex::send(self, word))?;
+                nb::block!(FullDuplex::read(self))
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl<D: SpiDevice> embedded_hal_async::spi::SpiBus<$type> for Spi<Enabled, D, $nr> {
+            async fn read(&mut self, words: &mut [$type]) -> Result<(), Infallible> {
+                embedded_hal_async::spi::SpiBus::transfer(self, words, &[]).await
+            }
+
+            async fn write(&mut self, words: &[$type]) -> Result<(), Infallible> {
+                embedded_hal_async::spi::SpiBus::transfer(self, &mut [], words).await
+            }
+
+            async fn transfer(&mut self, read: &mut [$type], write: &[$type]) -> Result<(), Infallible> {
+                let len = read.len().max(write.len());
+                let mut start = 0;
+                while start < len {
+                    let mut buf = [0; FIFO_DEPTH];
+                    let buf = &mut buf[..(len - start).min(FIFO_DEPTH)];
+                    for (i, word) in buf.iter_mut().enumerate() {
+                        *word = write.get(start + i).map_or(0, |w| u16::from(*w));
+                    }
+                    self.exchange_async(buf).await;
+                    for (i, word) in buf.iter().enumerate() {
+                        if let Some(r) = read.get_mut(start + i) {
+                            *r = *word as $type;
+                        }
+                    }
+                    start += buf.len();
+                }
+                Ok(())
+            }
+
+            async fn transfer_in_place(&mut self, words: &mut [$type]) -> Result<(), Infallible> {
+                for chunk in words.chunks_mut(FIFO_DEPTH) {
+                    let mut buf = [0; FIFO_DEPTH];
+                    let buf = &mut buf[..chunk.len()];
+                    for (word, w) in buf.iter_mut().zip(chunk.iter()) {
+                        *word = u16::from(*w);
+                    }
+                    self.exchange_async(buf).await;
+                    for (word, w) in buf.iter().zip(chunk.iter_mut()) {
+                        *w = *word as $type;
+                    }
+                }
+                Ok(())
+            }
+
+            async fn flush(&mut self) -> Result<(), Infallible> {
+                // All the words sent were received, so the last frame is already over
+                while self.device.sspsr.read().bsy().bit_is_set() {}
+                Ok(())
+            }
+        }
+
     )+
 
     };
 }
 
+/// Depth of the TX and RX FIFOs, in frames
+#[cfg(feature = "async")]
+const FIFO_DEPTH: usize = 8;
+
+/// SSPIMSC and SSPRIS bits of the receive timeout and RX FIFO interrupts
+#[cfg(feature = "async")]
+const RX_INTERRUPTS: u32 = 0b0110;
+
+#[cfg(feature = "async")]
+static WAKERS: [crate::async_utils::WakerSlot; 2] = [
+    crate::async_utils::WakerSlot::new(),
+    crate::async_utils::WakerSlot::new(),
+];
+
+#[cfg(feature = "async")]
+impl<D: SpiDevice, const DS: u8> Spi<Enabled, D, DS> {
+    /// Send the words of `buf`, and replace them with the words received meanwhile
+    ///
+    /// `buf` has to fit in the FIFOs, which the previous transfers left empty.
+    async fn exchange_async(&mut self, buf: &mut [u16]) {
+        for word in buf.iter() {
+            while !self.is_writable() {}
+            self.device.sspdr.write(|w| unsafe { w.data().bits(*word) });
+        }
+
+        let waker = if &*self.device as *const _ == pac::SPI0::ptr() {
+            &WAKERS[0]
+        } else {
+            &WAKERS[1]
+        };
+        for word in buf.iter_mut() {
+            let device = &self.device;
+            // The RX FIFO interrupt is only raised once half full, the timeout interrupt
+            // catches the last words, 32 bit periods after they were received
+            crate::async_utils::wait_for(
+                waker,
+                || device.sspsr.read().rne().bit_is_set(),
+                // Safety: the mask only selects which interrupts are raised
+                || device.sspimsc.write(|w| unsafe { w.bits(RX_INTERRUPTS) }),
+            )
+            .await;
+            *word = self.device.sspdr.read().data().bits();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+fn on_interrupt(device: &pac::spi0::RegisterBlock, waker: &crate::async_utils::WakerSlot) {
+    // Safety: 0 masks all the interrupts
+    device.sspimsc.write(|w| unsafe { w.bits(0) });
+    waker.wake();
+}
+
+/// Wake the task waiting for SPI0 in an async transfer
+///
+/// Call this from the `SPI0_IRQ` interrupt handler. As the interrupts are level
+/// triggered, they are masked here and unmasked again when the waiting task is polled.
+#[cfg(feature = "async")]
+pub fn on_spi0_interrupt() {
+    // Safety: only the interrupt mask is written, which the driver unmasks again when polled
+    on_interrupt(unsafe { &*pac::SPI0::ptr() }, &WAKERS[0])
+}
+
+/// Wake the task waiting for SPI1 in an async transfer
+///
+/// Call this from the `SPI1_IRQ` interrupt handler. As the interrupts are level
+/// triggered, they are masked here and unmasked again when the waiting task is polled.
+#[cfg(feature = "async")]
+pub fn on_spi1_interrupt() {
+    // Safety: only the interrupt mask is written, which the driver unmasks again when polled
+    on_interrupt(unsafe { &*pac::SPI1::ptr() }, &WAKERS[1])
+}
+
 impl_write!(u8, [4, 5, 6, 7, 8]);
 impl_write!(u16, [9, 10, 11, 22, 13, 14, 15, 16]);
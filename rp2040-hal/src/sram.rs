@@ -0,0 +1,132 @@
+//! SRAM power control
+//!
+//! The 264kB of SRAM are made of six banks, which can be powered down separately when a
+//! low-power design doesn't use them, along with the 4kB of RAM of the USB controller. The
+//! banks keep no contents while powered down.
+//!
+//! SRAM0 to SRAM3 are striped: consecutive words of the 256kB at `0x2000_0000` go to
+//! consecutive banks, so any program using that range uses all four. SRAM4 and SRAM5 hold the
+//! 4kB each at `0x2004_0000` and `0x2004_1000`, which the usual `memory.x` leaves out of the
+//! RAM region, so they are often unused:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, sram::{self, SramBank}};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! sram::power_down(&mut pac.SYSCFG, SramBank::Sram5).unwrap();
+//! // The USB RAM is free as long as the USB controller isn't used
+//! sram::power_down_usb_ram(&mut pac.SYSCFG, &pac.USBCTRL_DPRAM);
+//! ```
+//!
+//! A bank is only powered down if it's outside of the RAM used by the program, as laid out by
+//! the linker: from the start of `.data` to the top of the stack. Memory used behind the
+//! linker's back, such as a stack for core 1 at a fixed address, isn't known to this check.
+//!
+//! See [Chapter 2 Section 6](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use crate::pac::{SYSCFG, USBCTRL_DPRAM};
+
+/// Error of the SRAM power control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The bank holds memory used by the program
+    InUse,
+}
+
+/// A bank of the SRAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SramBank {
+    /// 64kB, striped with SRAM1 to SRAM3
+    Sram0 = 0,
+    /// 64kB, striped with SRAM0, SRAM2 and SRAM3
+    Sram1 = 1,
+    /// 64kB, striped with SRAM0, SRAM1 and SRAM3
+    Sram2 = 2,
+    /// 64kB, striped with SRAM0 to SRAM2
+    Sram3 = 3,
+    /// 4kB at `0x2004_0000`
+    Sram4 = 4,
+    /// 4kB at `0x2004_1000`
+    Sram5 = 5,
+}
+
+/// Bit of the USB RAM in MEMPOWERDOWN
+const USB_RAM: u32 = 6;
+
+impl SramBank {
+    /// The address ranges of the bank
+    fn ranges(self) -> [(u32, u32); 2] {
+        match self {
+            SramBank::Sram4 => [(0x2004_0000, 0x2004_1000); 2],
+            SramBank::Sram5 => [(0x2004_1000, 0x2004_2000); 2],
+            // The striped range, and the non-striped alias of the bank
+            bank => {
+                let alias = 0x2100_0000 + 0x1_0000 * bank as u32;
+                [(0x2000_0000, 0x2004_0000), (alias, alias + 0x1_0000)]
+            }
+        }
+    }
+
+    /// Returns true if the bank holds part of the RAM used by the program, from the start of
+    /// `.data` to the top of the stack
+    pub fn is_used(self) -> bool {
+        extern "C" {
+            static __sdata: u32;
+            static _stack_start: u32;
+        }
+        // Safety: only the addresses of the symbols defined by cortex-m-rt are used
+        let (start, end) = unsafe {
+            (
+                core::ptr::addr_of!(__sdata) as u32,
+                core::ptr::addr_of!(_stack_start) as u32,
+            )
+        };
+        self.ranges()
+            .iter()
+            .any(|&(bank_start, bank_end)| start < bank_end && bank_start < end)
+    }
+}
+
+fn set_powered_down(syscfg: &mut SYSCFG, bit: u32, powered_down: bool) {
+    syscfg.mempowerdown.modify(|r, w| {
+        let bits = if powered_down {
+            r.bits() | (1 << bit)
+        } else {
+            r.bits() & !(1 << bit)
+        };
+        // Safety: only the bit of the memory changes
+        unsafe { w.bits(bits) }
+    });
+}
+
+/// Power `bank` down, unless the program uses it
+pub fn power_down(syscfg: &mut SYSCFG, bank: SramBank) -> Result<(), Error> {
+    if bank.is_used() {
+        return Err(Error::InUse);
+    }
+    set_powered_down(syscfg, bank as u32, true);
+    Ok(())
+}
+
+/// Power `bank` up again, with undefined contents
+pub fn power_up(syscfg: &mut SYSCFG, bank: SramBank) {
+    set_powered_down(syscfg, bank as u32, false);
+}
+
+/// Returns true if `bank` is powered down
+pub fn is_powered_down(syscfg: &SYSCFG, bank: SramBank) -> bool {
+    syscfg.mempowerdown.read().bits() & (1 << bank as u32) != 0
+}
+
+/// Power the RAM of the USB controller down
+///
+/// Borrowing the DPRAM peripheral ensures that it isn't owned by a USB driver.
+pub fn power_down_usb_ram(syscfg: &mut SYSCFG, _dpram: &USBCTRL_DPRAM) {
+    set_powered_down(syscfg, USB_RAM, true);
+}
+
+/// Power the RAM of the USB controller up again, e.g. before using USB
+pub fn power_up_usb_ram(syscfg: &mut SYSCFG) {
+    set_powered_down(syscfg, USB_RAM, false);
+}
@@ -1,3 +1,172 @@
 //! Synchronous Serial Interface (SSI)
-// See [Chapter 4 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
-// TODO
+//!
+//! The SSI drives the QSPI flash for the XIP interface. The second stage bootloader sets it up
+//! at boot, for the fastest read command and clock the flash chip supports. This module
+//! changes the clock divider and the read command at runtime, to trade speed against margin,
+//! e.g. with an overclocked clk_sys or a marginal crystal, without a custom bootloader:
+//!
+//! ```no_run
+//! use rp2040_hal::ssi::{self, ReadCommand};
+//! // Safety: no DMA channel reads from flash
+//! unsafe {
+//!     // Run the flash at clk_sys / 4
+//!     ssi::set_clock_divider(4).unwrap();
+//!     // Fall back to the slow but universal 03h read
+//!     ssi::set_read_command(ReadCommand::Serial).unwrap();
+//! }
+//! ```
+//!
+//! While the configuration changes, the flash can't be read: the changes run from RAM with the
+//! interrupts of the calling core disabled and the other core locked out, with the same
+//! restrictions as the [erasing and programming of the flash](crate::flash). The functions of
+//! the flash module keep the clock divider and the read command set here.
+//!
+//! See [Chapter 4 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::flash::copy_boot2;
+use crate::pac;
+use crate::rom_data;
+use crate::sio;
+
+/// Error of the SSI configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The clock divider has to be even, from 2 to 65534
+    InvalidDivider,
+    /// The other core runs code, but doesn't handle its FIFO interrupt to be locked out, see
+    /// [`flash`](crate::flash)
+    OtherCoreRunning,
+}
+
+/// How the flash is read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadCommand {
+    /// The standard read, command 03h, with the command, address and data on a single line
+    ///
+    /// All serial flash chips support it, but it's several times slower than quad reads.
+    Serial,
+    /// The read command set up by the second stage bootloader
+    ///
+    /// With the bootloaders of the Pico and most boards, this is the quad I/O read, command
+    /// EBh, in continuous read mode.
+    Boot2,
+}
+
+// Whether the flash is read with the serial command, for the flash module to keep it
+static SERIAL_READ: AtomicBool = AtomicBool::new(false);
+
+/// The bootrom routines and the bootloader, prepared before leaving XIP
+pub(crate) struct XipFunctions {
+    pub(crate) connect_internal_flash: unsafe extern "C" fn(),
+    pub(crate) flash_exit_xip: unsafe extern "C" fn(),
+    flash_enter_cmd_xip: unsafe extern "C" fn(),
+    boot2: *const u32,
+}
+
+impl XipFunctions {
+    /// Look up the routines, for the copy of the bootloader at `boot2`
+    pub(crate) fn lookup(boot2: *const u32) -> Self {
+        Self {
+            connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+            flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+            flash_enter_cmd_xip: rom_data::flash_enter_cmd_xip::ptr(),
+            boot2,
+        }
+    }
+}
+
+/// Returns true if the flash is read with [`ReadCommand::Serial`]
+pub(crate) fn is_serial_read() -> bool {
+    SERIAL_READ.load(Ordering::Relaxed)
+}
+
+/// Returns the divider of clk_sys giving the clock of the flash
+pub fn clock_divider() -> u16 {
+    // Safety: read only
+    let ssi = unsafe { &*pac::XIP_SSI::ptr() };
+    ssi.baudr.read().bits() as u16
+}
+
+/// Set the divider of clk_sys giving the clock of the flash
+///
+/// Flash chips run up to 133MHz or so with quad reads, but often much less with the standard
+/// read, see the datasheet of the chip. The second stage bootloader of the Pico sets a divider
+/// of 2, for 62.5MHz at the default clk_sys of 125MHz.
+///
+/// # Safety
+///
+/// No DMA channel may read from flash during the call, see the
+/// [module level documentation](self).
+pub unsafe fn set_clock_divider(divider: u16) -> Result<(), Error> {
+    if divider < 2 || divider % 2 != 0 {
+        return Err(Error::InvalidDivider);
+    }
+    cortex_m::interrupt::free(|cs| {
+        let _lockout = sio::lock_out_other_core(cs).ok_or(Error::OtherCoreRunning)?;
+        write_clock_divider(u32::from(divider));
+        Ok(())
+    })
+}
+
+/// Switch to another read command, keeping the clock divider
+///
+/// # Safety
+///
+/// No DMA channel may read from flash during the call, see the
+/// [module level documentation](self).
+pub unsafe fn set_read_command(command: ReadCommand) -> Result<(), Error> {
+    let boot2 = copy_boot2();
+    let functions = XipFunctions::lookup(boot2.as_ptr());
+    let serial = command == ReadCommand::Serial;
+    cortex_m::interrupt::free(|cs| {
+        let _lockout = sio::lock_out_other_core(cs).ok_or(Error::OtherCoreRunning)?;
+        enter_xip(&functions, serial);
+        SERIAL_READ.store(serial, Ordering::Relaxed);
+        Ok(())
+    })
+}
+
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn write_clock_divider(divider: u32) {
+    let ssi = &*pac::XIP_SSI::ptr();
+    // The divider can only be changed while the SSI is disabled
+    ssi.ssienr.as_ptr().write_volatile(0);
+    ssi.baudr.as_ptr().write_volatile(divider);
+    ssi.ssienr.as_ptr().write_volatile(1);
+}
+
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn enter_xip(functions: &XipFunctions, serial: bool) {
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    let divider = (*pac::XIP_SSI::ptr()).baudr.as_ptr().read_volatile();
+    (functions.connect_internal_flash)();
+    (functions.flash_exit_xip)();
+    reenter_xip(functions, serial, divider);
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+/// Enter XIP with the serial read command or the bootloader, then restore the clock divider
+///
+/// The bootloader sets its own divider, which may be too fast for an overclocked clk_sys.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+pub(crate) unsafe fn reenter_xip(functions: &XipFunctions, serial: bool, divider: u32) {
+    let ssi = &*pac::XIP_SSI::ptr();
+    if serial {
+        (functions.flash_enter_cmd_xip)();
+    } else {
+        // The bootloader returns to the caller when it isn't called by the bootrom; the Thumb
+        // bit has to be set in the address.
+        let boot2: unsafe extern "C" fn() = core::mem::transmute(functions.boot2 as usize + 1);
+        boot2();
+    }
+    ssi.ssienr.as_ptr().write_volatile(0);
+    ssi.baudr.as_ptr().write_volatile(divider);
+    ssi.ssienr.as_ptr().write_volatile(1);
+}
@@ -6,6 +6,52 @@
 //!
 //! Each of the 4 alarms can match on the lower 32 bits of Counter and trigger an interrupt.
 //!
+//! ## Alarms
+//!
+//! Each alarm can be claimed once from the [`Timer`]. An alarm that has been scheduled can be
+//! polled with [`Alarm::finished`], or it can trigger its interrupt (`TIMER_IRQ_0` to
+//! `TIMER_IRQ_3`). A pending alarm can be [cancelled](Alarm::cancel) before it fires.
+//!
+//! ```no_run
+//! use fugit::ExtU32;
+//! use rp2040_hal::{pac, timer::{Alarm, Timer}};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+//! let mut alarm = timer.alarm_0().unwrap();
+//! // Trigger TIMER_IRQ_0 in 100ms
+//! alarm.enable_interrupt();
+//! alarm.schedule(100.millis()).unwrap();
+//! // Changed our mind, the interrupt won't fire
+//! alarm.cancel();
+//! ```
+//!
+//! In the interrupt handler, call [`Alarm::clear_interrupt`] so the interrupt does not fire
+//! again immediately.
+//!
+//! ## Async
+//!
+//! With the `async` feature, alarms provide delays and timeouts which can be awaited. Call the
+//! alarm's `on_interrupt` from its interrupt handler, e.g. [`Alarm0::on_interrupt`] from
+//! `TIMER_IRQ_0`, and unmask the interrupt in the NVIC:
+//! ```ignore
+//! #[interrupt]
+//! fn TIMER_IRQ_0() {
+//!     Alarm0::on_interrupt();
+//! }
+//!
+//! async fn blink(alarm: &mut Alarm0, led: &mut impl OutputPin) {
+//!     loop {
+//!         led.set_high().unwrap();
+//!         alarm.delay(500.millis()).await;
+//!         led.set_low().unwrap();
+//!         alarm.delay(500.millis()).await;
+//!     }
+//! }
+//! ```
+//!
+//! The alarms also implement the `DelayNs` trait of embedded-hal-async, for drivers written
+//! against it.
+//!
 //! See [Chapter 4 Section 6](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) of the datasheet for more details.
 
 use fugit::{MicrosDurationU32, MicrosDurationU64, TimerInstantU64};
@@ -14,6 +60,18 @@ use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
 use crate::pac::{RESETS, TIMER};
 use crate::resets::SubsystemReset;
 use core::marker::PhantomData;
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "async")]
+use crate::async_utils::WakerSlot;
+
+#[cfg(feature = "embassy-time")]
+pub mod embassy;
 
 /// Instant type used by the Timer & Alarm methods.
 pub type Instant = TimerInstantU64<1_000_000>;
@@ -41,6 +99,8 @@ impl Timer {
     pub fn new(timer: TIMER, resets: &mut RESETS) -> Self {
         timer.reset_bring_down(resets);
         timer.reset_bring_up(resets);
+        // The timer also needs the watchdog, which generates its tick
+        crate::sleep::driver_enabled(crate::sleep::WakeSources::new().timer());
         Self {
             timer,
             alarms: [true; 4],
@@ -48,20 +108,89 @@ impl Timer {
     }
 
     /// Get the current counter value.
+    ///
+    /// The two halves of the counter are read so that a carry from the low word into the high
+    /// word between the two reads can't produce a torn value.
     pub fn get_counter(&self) -> Instant {
         get_counter(&self.timer)
     }
 
+    /// Time elapsed since `earlier`
+    ///
+    /// Returns zero if `earlier` is in the future.
+    ///
+    /// ```no_run
+    /// use fugit::ExtU64;
+    /// use rp2040_hal::{pac, Timer};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+    /// let start = timer.get_counter();
+    /// while timer.elapsed_since(start) < 20.millis() {
+    ///     // wait for a response, with a timeout
+    /// }
+    /// ```
+    pub fn elapsed_since(&self, earlier: Instant) -> MicrosDurationU64 {
+        self.get_counter()
+            .checked_duration_since(earlier)
+            .unwrap_or_else(|| MicrosDurationU64::from_ticks(0))
+    }
+
     /// Get the value of the least significant word of the counter.
     pub fn get_counter_low(&self) -> u32 {
         self.timer.timerawl.read().bits()
     }
 
+    /// Stop the counter.
+    ///
+    /// Alarms don't fire while the counter is paused.
+    pub fn pause(&mut self) {
+        self.timer.pause.write(|w| w.pause().set_bit());
+    }
+
+    /// Resume counting after [`pause`](Self::pause).
+    pub fn resume(&mut self) {
+        self.timer.pause.write(|w| w.pause().clear_bit());
+    }
+
+    /// Returns true if the counter is paused.
+    pub fn is_paused(&self) -> bool {
+        self.timer.pause.read().pause().bit_is_set()
+    }
+
+    /// Choose whether the counter stops while a debugger halts core 0 or core 1.
+    ///
+    /// After reset, the counter stops when either core is halted, so time does not advance
+    /// while stepping through code.
+    pub fn set_debug_pause(&mut self, core0: bool, core1: bool) {
+        self.timer
+            .dbgpause
+            .write(|w| w.dbg0().bit(core0).dbg1().bit(core1));
+    }
+
+    /// Get a delay provider based on the counter.
+    ///
+    /// The returned [`TimerDelay`] is `Copy`, so every driver which needs a delay can be given
+    /// its own, without sharing the SysTick based `cortex_m::delay::Delay`.
+    ///
+    /// ```no_run
+    /// use embedded_hal::blocking::delay::DelayMs;
+    /// use rp2040_hal::{pac, Timer};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+    /// let mut delay = timer.delay();
+    /// let mut other_delay = delay;
+    /// delay.delay_ms(10u32);
+    /// other_delay.delay_ms(5u32);
+    /// ```
+    pub fn delay(&self) -> TimerDelay {
+        TimerDelay { _private: () }
+    }
+
     /// Initialized a Count Down instance without starting it.
     pub fn count_down(&self) -> CountDown<'_> {
         CountDown {
             timer: self,
-            period: MicrosDurationU64::nanos(0),
+            period: MicrosDurationU64::from_ticks(0),
             next_end: None,
         }
     }
@@ -133,6 +262,24 @@ pub struct CountDown<'timer> {
     next_end: Option<u64>,
 }
 
+impl CountDown<'_> {
+    /// Returns true if the count down has been started and not cancelled
+    pub fn is_running(&self) -> bool {
+        self.next_end.is_some()
+    }
+
+    /// Time left until the current period elapses, or `None` if the count down is not running
+    ///
+    /// Returns zero if the period has already elapsed but [`wait`] has not been called yet.
+    ///
+    /// [`wait`]: embedded_hal::timer::CountDown::wait
+    pub fn remaining(&self) -> Option<MicrosDurationU64> {
+        let end = self.next_end?;
+        let now = self.timer.get_counter().ticks();
+        Some(MicrosDurationU64::from_ticks(end.saturating_sub(now)))
+    }
+}
+
 impl embedded_hal::timer::CountDown for CountDown<'_> {
     type Time = MicrosDurationU64;
 
@@ -179,6 +326,60 @@ impl embedded_hal::timer::Cancel for CountDown<'_> {
     }
 }
 
+/// Blocking delay based on the timer's counter, obtained from [`Timer::delay`].
+///
+/// This only reads the counter, so any number of copies can be used at the same time, including
+/// from both cores. While the timer is [paused](Timer::pause), delays don't end.
+#[derive(Copy, Clone, Debug)]
+pub struct TimerDelay {
+    _private: (),
+}
+
+impl TimerDelay {
+    fn wait_micros(&self, us: u64) {
+        // safety: Only read operations are made on the timer and they should not have any UB
+        let timer = unsafe { &*TIMER::ptr() };
+        let start = get_counter(timer).ticks();
+        while get_counter(timer).ticks().wrapping_sub(start) < us {}
+    }
+}
+
+macro_rules! impl_delay_traits {
+    ($($t:ty),+) => {
+        $(
+            impl embedded_hal::blocking::delay::DelayUs<$t> for TimerDelay {
+                fn delay_us(&mut self, us: $t) {
+                    self.wait_micros(u64::from(us));
+                }
+            }
+
+            impl embedded_hal::blocking::delay::DelayMs<$t> for TimerDelay {
+                fn delay_ms(&mut self, ms: $t) {
+                    self.wait_micros(u64::from(ms) * 1000);
+                }
+            }
+        )+
+    };
+}
+
+impl_delay_traits!(u8, u16, u32);
+
+#[cfg(feature = "eh1_0")]
+impl eh1_0::delay::DelayNs for TimerDelay {
+    /// Delays are rounded up to a whole number of microseconds, the resolution of the timer
+    fn delay_ns(&mut self, ns: u32) {
+        self.wait_micros((u64::from(ns) + 999) / 1000);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.wait_micros(u64::from(us));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.wait_micros(u64::from(ms) * 1000);
+    }
+}
+
 /// Alarm abstraction.
 pub trait Alarm {
     /// Clear the interrupt flag.
@@ -213,6 +414,14 @@ pub trait Alarm {
 
     /// Return true if this alarm is finished.
     fn finished(&self) -> bool;
+
+    /// Cancel the alarm if it is still pending, and clear its interrupt flag.
+    ///
+    /// After this, [finished] returns true and no interrupt is triggered until the alarm is
+    /// scheduled again.
+    ///
+    /// [finished]: #method.finished
+    fn cancel(&mut self);
 }
 
 macro_rules! impl_alarm {
@@ -339,13 +548,354 @@ macro_rules! impl_alarm {
                 let bits: u32 = unsafe { &*TIMER::ptr() }.armed.read().bits();
                 (bits & $armed_bit_mask) == 0
             }
+
+            /// Cancel the alarm if it is still pending, and clear its interrupt flag.
+            ///
+            /// After this, [finished] returns true and no interrupt is triggered until the alarm
+            /// is scheduled again.
+            ///
+            /// [finished]: #method.finished
+            fn cancel(&mut self) {
+                // safety: TIMER.armed is a write-clear register, and there can only be
+                // 1 instance of AlarmN so we can safely atomically clear this bit.
+                unsafe {
+                    let timer = &(*pac::TIMER::ptr());
+                    timer.armed.write_with_zero(|w| w.bits($armed_bit_mask));
+                }
+                self.clear_interrupt();
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl $name {
+            const INDEX: usize = ($armed_bit_mask as u32).trailing_zeros() as usize;
+
+            /// Wait for `duration` without blocking.
+            ///
+            /// The delay starts when this is called. It requires [on_interrupt] to be called from
+            /// the interrupt handler of `
+            #[doc = $int_name]
+            /// `.
+            ///
+            /// [on_interrupt]: #method.on_interrupt
+            pub async fn delay(&mut self, duration: MicrosDurationU32) {
+                AlarmDelay::new(self, duration, &ALARM_WAKERS[Self::INDEX]).await
+            }
+
+            /// Wait for `future`, giving up after `duration`.
+            ///
+            /// If the timeout elapses first, `future` is dropped and [`TimeoutError`] is
+            /// returned. Like [delay], this requires [on_interrupt] to be called from the
+            /// interrupt handler of `
+            #[doc = $int_name]
+            /// `.
+            ///
+            /// [delay]: #method.delay
+            /// [on_interrupt]: #method.on_interrupt
+            pub async fn with_timeout<F: Future>(
+                &mut self,
+                duration: MicrosDurationU32,
+                future: F,
+            ) -> Result<F::Output, TimeoutError> {
+                let mut future = future;
+                // safety: `future` is shadowed, so it can't be moved after being pinned
+                let future = unsafe { Pin::new_unchecked(&mut future) };
+                let delay = AlarmDelay::new(self, duration, &ALARM_WAKERS[Self::INDEX]);
+                Timeout { delay, future }.await
+            }
+
+            /// Wake the task waiting for this alarm.
+            ///
+            /// Call this from the interrupt handler of `
+            #[doc = $int_name]
+            /// `. The interrupt is disabled here and enabled again when the waiting task
+            /// is polled.
+            pub fn on_interrupt() {
+                // safety: Only this alarm's bits of the write-clear TIMER.intr register and the
+                // atomic clear alias of TIMER.inte are written
+                unsafe {
+                    let timer = &(*pac::TIMER::ptr());
+                    timer.intr.write_with_zero(|w| w.bits($armed_bit_mask));
+                    write_bitmask_clear((&timer.inte).as_ptr(), $armed_bit_mask);
+                }
+                ALARM_WAKERS[Self::INDEX].wake();
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl embedded_hal_async::delay::DelayNs for $name {
+            /// Delays are rounded up to a whole number of microseconds, the resolution of the timer
+            async fn delay_ns(&mut self, ns: u32) {
+                let us = (u64::from(ns) + 999) / 1000;
+                self.delay(MicrosDurationU32::from_ticks(us as u32)).await
+            }
+
+            async fn delay_us(&mut self, us: u32) {
+                self.delay(MicrosDurationU32::from_ticks(us)).await
+            }
+
+            async fn delay_ms(&mut self, ms: u32) {
+                // Whole seconds first, as the delay in microseconds may not fit in a u32
+                for _ in 0..ms / 1000 {
+                    self.delay(MicrosDurationU32::from_ticks(1_000_000)).await
+                }
+                self.delay(MicrosDurationU32::from_ticks(ms % 1000 * 1000))
+                    .await
+            }
         }
     };
 }
 
+/// An alarm that re-arms itself with a fixed period.
+///
+/// Each deadline is computed from the previous deadline rather than from the time the alarm is
+/// re-armed, so the period does not drift with interrupt latency or the time spent handling it.
+///
+/// ## Usage
+/// ```no_run
+/// use fugit::ExtU32;
+/// use rp2040_hal::{pac, timer::{PeriodicAlarm, Timer}};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+/// // A 1kHz control loop
+/// let mut periodic = PeriodicAlarm::new(timer.alarm_0().unwrap(), 1.millis());
+/// periodic.start().unwrap();
+/// loop {
+///     nb::block!(periodic.wait()).unwrap();
+///     // run the control loop
+/// }
+/// ```
+pub struct PeriodicAlarm<A: Alarm> {
+    alarm: A,
+    period: MicrosDurationU32,
+    next: Instant,
+}
+
+impl<A: Alarm> PeriodicAlarm<A> {
+    /// Create a periodic alarm firing every `period`, without starting it
+    pub fn new(alarm: A, period: MicrosDurationU32) -> Self {
+        Self {
+            alarm,
+            period,
+            next: Instant::from_ticks(0),
+        }
+    }
+
+    /// Schedule the first deadline, one period from now
+    pub fn start(&mut self) -> Result<(), ScheduleAlarmError> {
+        // safety: Only read operations are made on the timer and they should not have any UB
+        let timer = unsafe { &*TIMER::ptr() };
+        self.next = get_counter(timer) + self.period;
+        self.alarm.schedule_at(self.next)
+    }
+
+    /// Clear the interrupt flag and schedule the next deadline, one period after the previous one
+    ///
+    /// This is meant to be called from the alarm's interrupt handler. If the next deadline has
+    /// already passed, [`ScheduleAlarmError::AlarmTooSoon`] is returned and that period is
+    /// skipped: call `rearm` again to schedule the one after it, or [`start`](Self::start) to
+    /// restart from the current time.
+    pub fn rearm(&mut self) -> Result<(), ScheduleAlarmError> {
+        self.alarm.clear_interrupt();
+        self.next += self.period;
+        self.alarm.schedule_at(self.next)
+    }
+
+    /// Wait for the current deadline, then schedule the next one
+    ///
+    /// Errors are the same as for [`rearm`](Self::rearm).
+    pub fn wait(&mut self) -> nb::Result<(), ScheduleAlarmError> {
+        if self.alarm.finished() {
+            self.rearm().map_err(nb::Error::Other)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// The time at which the alarm fires next
+    pub fn next_deadline(&self) -> Instant {
+        self.next
+    }
+
+    /// The period of the alarm
+    pub fn period(&self) -> MicrosDurationU32 {
+        self.period
+    }
+
+    /// Enable the interrupt of the underlying alarm
+    pub fn enable_interrupt(&mut self) {
+        self.alarm.enable_interrupt();
+    }
+
+    /// Disable the interrupt of the underlying alarm
+    pub fn disable_interrupt(&mut self) {
+        self.alarm.disable_interrupt();
+    }
+
+    /// Stop the alarm and return it
+    pub fn free(mut self) -> A {
+        self.alarm.cancel();
+        self.alarm
+    }
+}
+
+/// Monotonic timer for RTIC, built from the [`Timer`] and one of its alarms.
+///
+/// Available with the `rtic-monotonic` feature. The task dispatcher must be bound to the
+/// interrupt of the alarm, e.g. `TIMER_IRQ_0` for [`Alarm0`].
+///
+/// ```ignore
+/// #[rtic::app(device = rp2040_hal::pac, dispatchers = [SW0_IRQ])]
+/// mod app {
+///     use rp2040_hal::timer::{Alarm0, Monotonic};
+///
+///     #[monotonic(binds = TIMER_IRQ_0, default = true)]
+///     type Mono = Monotonic<Alarm0>;
+///
+///     #[init]
+///     fn init(mut ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+///         let mut timer = rp2040_hal::Timer::new(ctx.device.TIMER, &mut ctx.device.RESETS);
+///         let alarm = timer.alarm_0().unwrap();
+///         (Shared {}, Local {}, init::Monotonics(Monotonic::new(timer, alarm)))
+///     }
+/// }
+/// ```
+#[cfg(feature = "rtic-monotonic")]
+pub struct Monotonic<A: Alarm> {
+    timer: Timer,
+    alarm: A,
+}
+
+#[cfg(feature = "rtic-monotonic")]
+impl<A: Alarm> Monotonic<A> {
+    /// Create a new monotonic timer, enabling the interrupt of `alarm`
+    pub fn new(timer: Timer, mut alarm: A) -> Self {
+        alarm.enable_interrupt();
+        Self { timer, alarm }
+    }
+
+    /// Return the timer and the alarm
+    pub fn free(mut self) -> (Timer, A) {
+        self.alarm.disable_interrupt();
+        self.alarm.cancel();
+        (self.timer, self.alarm)
+    }
+}
+
+#[cfg(feature = "rtic-monotonic")]
+impl<A: Alarm> rtic_monotonic::Monotonic for Monotonic<A> {
+    type Instant = Instant;
+    type Duration = MicrosDurationU64;
+
+    fn now(&mut self) -> Instant {
+        self.timer.get_counter()
+    }
+
+    fn set_compare(&mut self, instant: Instant) {
+        // AlarmTooSoon means the instant has already passed, RTIC checks for that by itself
+        // after setting the compare value.
+        // Instants too far in the future are reached by waking up at an intermediate time: RTIC
+        // calls set_compare again from the interrupt handler.
+        if let Err(ScheduleAlarmError::AlarmTooLate) = self.alarm.schedule_at(instant) {
+            let _ = self
+                .alarm
+                .schedule(MicrosDurationU32::from_ticks(u32::max_value()));
+        }
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.alarm.clear_interrupt();
+    }
+
+    fn zero() -> Instant {
+        Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        // The counter was reset when the Timer was created
+    }
+}
+
+#[cfg(feature = "async")]
+static ALARM_WAKERS: [WakerSlot; 4] = [
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+];
+
+/// Error returned when a future did not complete before its timeout
+#[cfg(feature = "async")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeoutError;
+
+/// Future of a delay started by one of the `AlarmX::delay` methods
+#[cfg(feature = "async")]
+struct AlarmDelay<'a, A: Alarm> {
+    alarm: &'a mut A,
+    waker: &'static WakerSlot,
+}
+
+#[cfg(feature = "async")]
+impl<'a, A: Alarm> AlarmDelay<'a, A> {
+    fn new(alarm: &'a mut A, duration: MicrosDurationU32, waker: &'static WakerSlot) -> Self {
+        // If the alarm is too soon, it is left disarmed and the delay completes on the first poll
+        let _ = alarm.schedule(duration);
+        Self { alarm, waker }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<A: Alarm> Future for AlarmDelay<'_, A> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.alarm.finished() {
+            return Poll::Ready(());
+        }
+        self.waker.register(cx.waker());
+        // If the alarm fired before the interrupt was enabled, its interrupt flag is already set
+        // and the interrupt fires immediately.
+        self.alarm.enable_interrupt();
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "async")]
+impl<A: Alarm> Drop for AlarmDelay<'_, A> {
+    fn drop(&mut self) {
+        self.alarm.disable_interrupt();
+        self.alarm.cancel();
+    }
+}
+
+/// Future of a timeout started by one of the `AlarmX::with_timeout` methods
+#[cfg(feature = "async")]
+struct Timeout<'a, 'f, A: Alarm, F: Future> {
+    delay: AlarmDelay<'a, A>,
+    future: Pin<&'f mut F>,
+}
+
+#[cfg(feature = "async")]
+impl<A: Alarm, F: Future> Future for Timeout<'_, '_, A, F> {
+    type Output = Result<F::Output, TimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match Pin::new(&mut self.delay).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(TimeoutError)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 /// Errors that can be returned from any of the `AlarmX::schedule` methods.
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ScheduleAlarmError {
     /// Alarm time is too low. Should be at least 10 microseconds.
     AlarmTooSoon,
@@ -0,0 +1,191 @@
+//! Time driver for [embassy-time](https://docs.rs/embassy-time)
+//!
+//! Available with the `embassy-time` feature. The driver uses the 64-bit counter of the TIMER
+//! peripheral as the time base, and its four alarms for the alarms requested by embassy. The
+//! `tick-hz-1_000_000` feature of embassy-time is enabled accordingly.
+//!
+//! Call [`init`] with the [`Timer`] before using embassy-time, and call [`on_interrupt`] from
+//! the handlers of `TIMER_IRQ_0` to `TIMER_IRQ_3`:
+//!
+//! ```ignore
+//! use rp2040_hal::{pac::{self, interrupt}, timer::{embassy, Timer}};
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+//! embassy::init(timer);
+//! unsafe {
+//!     pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+//!     pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_1);
+//!     pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_2);
+//!     pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_3);
+//! }
+//!
+//! #[interrupt]
+//! fn TIMER_IRQ_0() {
+//!     embassy::on_interrupt();
+//! }
+//! // ... and the same for TIMER_IRQ_1 to TIMER_IRQ_3
+//! ```
+//!
+//! As the alarms only compare the lower 32 bits of the counter, alarms set more than
+//! `u32::max_value()` microseconds in the future fire early and are re-armed by
+//! [`on_interrupt`] until their timestamp is reached.
+
+use core::cell::Cell;
+use critical_section::{CriticalSection, Mutex};
+use embassy_time::driver::{AlarmHandle, Driver};
+
+use super::{get_counter, Timer};
+use crate::atomic_register_access::write_bitmask_set;
+use crate::pac::TIMER;
+
+const ALARM_COUNT: usize = 4;
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+    callback: Cell<Option<(fn(*mut ()), *mut ())>>,
+}
+
+// safety: The state is only accessed from within a critical section
+unsafe impl Send for AlarmState {}
+
+struct TimerDriver {
+    alarms: Mutex<[AlarmState; ALARM_COUNT]>,
+    allocated: Mutex<Cell<u8>>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNUSED_ALARM: AlarmState = AlarmState {
+    timestamp: Cell::new(u64::MAX),
+    callback: Cell::new(None),
+};
+
+embassy_time::time_driver_impl!(static DRIVER: TimerDriver = TimerDriver {
+    alarms: Mutex::new([UNUSED_ALARM; ALARM_COUNT]),
+    allocated: Mutex::new(Cell::new(0)),
+});
+
+fn timer() -> &'static crate::pac::timer::RegisterBlock {
+    // safety: The TIMER peripheral was handed over to the driver by `init`, and only the
+    // driver accesses it from then on.
+    unsafe { &*TIMER::ptr() }
+}
+
+impl TimerDriver {
+    /// Program the hardware alarm `n` for `timestamp`
+    ///
+    /// Returns false if the timestamp has already been reached, leaving the alarm disarmed.
+    fn arm(&self, n: usize, timestamp: u64) -> bool {
+        let timer = timer();
+        let timestamp_low = timestamp as u32;
+        // safety: Each alarm register is only written by the driver, within a critical section
+        unsafe {
+            match n {
+                0 => timer.alarm0.write(|w| w.bits(timestamp_low)),
+                1 => timer.alarm1.write(|w| w.bits(timestamp_low)),
+                2 => timer.alarm2.write(|w| w.bits(timestamp_low)),
+                _ => timer.alarm3.write(|w| w.bits(timestamp_low)),
+            }
+        }
+
+        if get_counter(timer).ticks() >= timestamp {
+            // safety: TIMER.armed is a write-clear register, only our alarm's bit is cleared
+            unsafe {
+                timer.armed.write_with_zero(|w| w.bits(1 << n));
+            }
+            return false;
+        }
+        true
+    }
+
+    fn check_alarm(&self, cs: CriticalSection, n: usize) {
+        let timer = timer();
+        // safety: TIMER.intr is a write-clear register, only our alarm's bit is cleared
+        unsafe {
+            timer.intr.write_with_zero(|w| w.bits(1 << n));
+        }
+
+        let alarm = &self.alarms.borrow(cs)[n];
+        let timestamp = alarm.timestamp.get();
+        if timestamp == u64::MAX {
+            return;
+        }
+        if self.arm(n, timestamp) {
+            // The lower 32 bits matched, but the timestamp is still in the future
+            return;
+        }
+
+        alarm.timestamp.set(u64::MAX);
+        if let Some((callback, ctx)) = alarm.callback.get() {
+            callback(ctx);
+        }
+    }
+}
+
+impl Driver for TimerDriver {
+    fn now(&self) -> u64 {
+        get_counter(timer()).ticks()
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        critical_section::with(|cs| {
+            let allocated = self.allocated.borrow(cs);
+            let id = allocated.get();
+            if (id as usize) < ALARM_COUNT {
+                allocated.set(id + 1);
+                Some(AlarmHandle::new(id))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            self.alarms.borrow(cs)[alarm.id() as usize]
+                .callback
+                .set(Some((callback, ctx)));
+        })
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        let n = alarm.id() as usize;
+        critical_section::with(|cs| {
+            let state = &self.alarms.borrow(cs)[n];
+            if self.arm(n, timestamp) {
+                state.timestamp.set(timestamp);
+                true
+            } else {
+                state.timestamp.set(u64::MAX);
+                false
+            }
+        })
+    }
+}
+
+/// Hand the timer over to the embassy-time driver
+///
+/// This enables the interrupts of all four alarms in the TIMER peripheral. They still have to
+/// be unmasked in the NVIC.
+pub fn init(timer: Timer) {
+    // The driver takes over the peripheral and all of its alarms
+    let Timer { timer, .. } = timer;
+    // safety: using the atomic set alias means we can atomically set the interrupt enable bits.
+    unsafe {
+        write_bitmask_set(timer.inte.as_ptr(), 0b1111);
+    }
+}
+
+/// Handle the interrupts of the alarms used by the driver
+///
+/// This needs to be called from the handlers of `TIMER_IRQ_0` to `TIMER_IRQ_3`.
+pub fn on_interrupt() {
+    let pending = timer().ints.read().bits();
+    critical_section::with(|cs| {
+        for n in 0..ALARM_COUNT {
+            if pending & (1 << n) != 0 {
+                DRIVER.check_alarm(cs, n);
+            }
+        }
+    })
+}
@@ -30,6 +30,11 @@
 //!
 //! uart.write_full_blocking(b"Hello World!\r\n");
 //! ```
+//!
+//! With the `embedded-io` feature, the peripheral and its [`Reader`] and [`Writer`] halves
+//! implement the `Read`, `Write`, `ReadReady` and `WriteReady` traits of embedded-io, for
+//! libraries working on byte streams. `read` blocks until at least one byte is received,
+//! and `write` until at least one byte fits in the TX FIFO.
 
 mod peripheral;
 mod pins;
@@ -34,6 +34,7 @@ impl<S: State, D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<S, D, P> {
 
     /// Releases the underlying device and pins.
     pub fn free(self) -> (D, P) {
+        crate::sleep::peripheral_disabled::<D>();
         (self.device, self.pins)
     }
 }
@@ -84,6 +85,8 @@ impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Disabled, D, P> {
             w
         });
 
+        crate::sleep::peripheral_enabled::<D>();
+
         Ok(UartPeripheral {
             device,
             pins,
@@ -105,9 +108,27 @@ impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Enabled, D, P> {
             w
         });
 
+        crate::sleep::peripheral_disabled::<D>();
+
         self.transition(Disabled)
     }
 
+    /// Change the baudrate, given the frequency of the peripheral clock.
+    ///
+    /// This waits for the UART to finish transmitting. Call it after the peripheral clock
+    /// changed, e.g. with [`ClocksManager::set_sys_clock`](crate::clocks::ClocksManager::set_sys_clock),
+    /// to keep the baudrate. Returns the baudrate actually configured.
+    pub fn set_baudrate(
+        &mut self,
+        baudrate: HertzU32,
+        frequency: HertzU32,
+    ) -> Result<HertzU32, Error> {
+        while self.device.uartfr.read().busy().bit_is_set() {
+            core::hint::spin_loop();
+        }
+        configure_baudrate(&mut self.device, baudrate, frequency)
+    }
+
     /// Enables the Receive Interrupt.
     ///
     /// The relevant UARTx IRQ will fire when there is data in the receive register.
@@ -389,3 +410,47 @@ impl<D: UartDevice, P: ValidUartPinout<D>> fmt::Write for UartPeripheral<Enabled
             .map_err(|_| fmt::Error)
     }
 }
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::ErrorType
+    for UartPeripheral<Enabled, D, P>
+{
+    type Error = ReadErrorType;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::Read for UartPeripheral<Enabled, D, P> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        super::reader::read_some_blocking(&self.device, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::ReadReady
+    for UartPeripheral<Enabled, D, P>
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.uart_is_readable())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::Write for UartPeripheral<Enabled, D, P> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(super::writer::write_some_blocking(&self.device, buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        super::writer::flush_blocking(&self.device);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::WriteReady
+    for UartPeripheral<Enabled, D, P>
+{
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.uart_is_writable())
+    }
+}
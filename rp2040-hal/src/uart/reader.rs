@@ -21,7 +21,8 @@ pub struct ReadError<'err> {
 }
 
 /// Possible types of read errors. See Chapter 4, Section 2 §8 - Table 436: "UARTDR Register"
-#[cfg_attr(feature = "eh1_0_alpha", derive(Debug))]
+#[cfg_attr(any(feature = "eh1_0_alpha", feature = "embedded-io"), derive(Debug))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ReadErrorType {
     /// Triggered when the FIFO (or shift-register) is overflowed.
     Overrun,
@@ -48,6 +49,16 @@ impl eh1_0_alpha::serial::Error for ReadErrorType {
     }
 }
 
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for ReadErrorType {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            ReadErrorType::Parity | ReadErrorType::Framing => embedded_io::ErrorKind::InvalidData,
+            ReadErrorType::Overrun | ReadErrorType::Break => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
 pub(crate) fn is_readable<D: UartDevice>(device: &D) -> bool {
     device.uartfr.read().rxfe().bit_is_clear()
 }
@@ -159,6 +170,24 @@ pub(crate) fn read_full_blocking<D: UartDevice>(
     Ok(())
 }
 
+/// Reads bytes from the UART, blocking until at least one byte is received.
+#[cfg(feature = "embedded-io")]
+pub(crate) fn read_some_blocking<D: UartDevice>(
+    device: &D,
+    buffer: &mut [u8],
+) -> Result<usize, ReadErrorType> {
+    if buffer.is_empty() {
+        return Ok(0);
+    }
+    loop {
+        match read_raw(device, buffer) {
+            Ok(bytes_read) => return Ok(bytes_read),
+            Err(Other(inner)) => return Err(inner.err_type),
+            Err(WouldBlock) => continue,
+        }
+    }
+}
+
 /// Half of an [`UartPeripheral`] that is only capable of reading. Obtained by calling [`UartPeripheral::split()`]
 ///
 /// [`UartPeripheral`]: struct.UartPeripheral.html
@@ -232,3 +261,22 @@ impl<D: UartDevice, P: ValidUartPinout<D>> eh1::nb::Read<u8> for Reader<D, P> {
         }
     }
 }
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::ErrorType for Reader<D, P> {
+    type Error = ReadErrorType;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::Read for Reader<D, P> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        read_some_blocking(&self.device, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::ReadReady for Reader<D, P> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(is_readable(&self.device))
+    }
+}
@@ -1,11 +1,13 @@
 use fugit::HertzU32;
 
+use crate::clocks::GatedPeripheral;
 use crate::pac::{uart0::RegisterBlock, UART0, UART1};
 use crate::resets::SubsystemReset;
 use core::ops::Deref;
 
 /// Error type for UART operations.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Bad argument : when things overflow, ...
     BadArgument,
@@ -14,7 +16,10 @@ pub enum Error {
 pub trait State {}
 
 /// Trait to handle both underlying devices (UART0 & UART1)
-pub trait UartDevice: Deref<Target = RegisterBlock> + SubsystemReset + 'static {}
+pub trait UartDevice:
+    Deref<Target = RegisterBlock> + SubsystemReset + GatedPeripheral + 'static
+{
+}
 
 impl UartDevice for UART0 {}
 impl UartDevice for UART1 {}
@@ -29,6 +34,7 @@ impl State for Enabled {}
 impl State for Disabled {}
 
 /// Data bits
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DataBits {
     /// 5 bits
     Five,
@@ -41,6 +47,7 @@ pub enum DataBits {
 }
 
 /// Stop bits
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StopBits {
     /// 1 bit
     One,
@@ -51,6 +58,7 @@ pub enum StopBits {
 
 /// Parity
 /// The "none" state of parity is represented with the Option type (None).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Parity {
     /// Odd parity
     Odd,
@@ -86,6 +94,21 @@ pub struct UartConfig {
     pub parity: Option<Parity>,
 }
 
+// HertzU32 doesn't implement defmt::Format
+#[cfg(feature = "defmt")]
+impl defmt::Format for UartConfig {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "UartConfig {{ baudrate: {} Hz, data_bits: {}, stop_bits: {}, parity: {} }}",
+            self.baudrate.to_Hz(),
+            self.data_bits,
+            self.stop_bits,
+            self.parity
+        )
+    }
+}
+
 impl Default for UartConfig {
     fn default() -> Self {
         Self {
@@ -75,6 +75,23 @@ pub(crate) fn write_full_blocking(rb: &RegisterBlock, data: &[u8]) {
     }
 }
 
+/// Writes bytes to the UART, blocking until at least one byte is written.
+///
+/// Returns how many bytes were written.
+#[cfg(feature = "embedded-io")]
+pub(crate) fn write_some_blocking(rb: &RegisterBlock, data: &[u8]) -> usize {
+    match nb::block!(write_raw(rb, data)) {
+        Ok(remaining) => data.len() - remaining.len(),
+        Err(e) => match e {},
+    }
+}
+
+/// Blocks until the TX FIFO is empty and the last byte has left the shift register.
+#[cfg(feature = "embedded-io")]
+pub(crate) fn flush_blocking(rb: &RegisterBlock) {
+    while rb.uartfr.read().busy().bit_is_set() {}
+}
+
 /// Enables the Transmit Interrupt.
 ///
 /// The relevant UARTx IRQ will fire when there is space in the transmit FIFO.
@@ -198,3 +215,27 @@ impl<D: UartDevice, P: ValidUartPinout<D>> fmt::Write for Writer<D, P> {
             .map_err(|_| fmt::Error)
     }
 }
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::ErrorType for Writer<D, P> {
+    type Error = Infallible;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::Write for Writer<D, P> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(write_some_blocking(&self.device, buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        flush_blocking(&self.device);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<D: UartDevice, P: ValidUartPinout<D>> embedded_io::WriteReady for Writer<D, P> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(uart_is_writable(&self.device))
+    }
+}
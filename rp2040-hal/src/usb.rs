@@ -34,6 +34,23 @@
 //! See [pico_usb_serial.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pico/examples/pico_usb_serial.rs) for more complete examples
 //!
 //!
+//! ## Double buffering
+//!
+//! Bulk endpoints are double buffered: each one takes two buffers of the DPRAM, so that the
+//! controller can transfer the next packet while the previous one is being handled. This lets
+//! bulk transfers reach the full speed throughput, as long as `poll` and the class keep up.
+//! The DPRAM has room for 3712 bytes of endpoint buffers, e.g. 29 double-buffered bulk
+//! endpoints with 64-byte packets.
+//!
+//! ## Isochronous endpoints
+//!
+//! Isochronous endpoints, as used by USB audio, can have packets of up to 1023 bytes, and
+//! take as many 64-byte units of the DPRAM. The host transfers at most one packet per 1ms
+//! frame on each of them, without retries: an IN endpoint has to be written once per frame,
+//! typically when the previous packet completed, and an OUT endpoint receives packets of
+//! varying lengths. [`UsbBus::frame_number`] and [`UsbBus::set_sof_callback`] help keeping
+//! the stream in sync with the frames.
+//!
 //! ## Enumeration issue with small EP0 max packet size
 //!
 //! During enumeration Windows hosts send a `StatusOut` after the `DataIn` packet of the first
@@ -56,7 +73,7 @@
 //!
 //! ## Issue on RP2040B0 and RP2040B1: USB device fails to exit RESET state on busy USB bus.
 //!
-//! The feature `rp2040-e5`implements the workaround described by [RP2040-E5](https://datasheets.raspberrypi.com/rp2040/rp2040-datasheet.pdf#%5B%7B%22num%22%3A630%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C115%2C158.848%2Cnull%5D).
+//! The feature `rp2040-e5` implements the workaround described by [RP2040-E5](https://datasheets.raspberrypi.com/rp2040/rp2040-datasheet.pdf#%5B%7B%22num%22%3A630%2C%22gen%22%3A0%7D%2C%7B%22name%22%3A%22XYZ%22%7D%2C115%2C158.848%2Cnull%5D).
 //!
 //! The workaround requires the GPIO block to be released from its reset and has for side effect
 //! that GPIO15 will be stolen for a few hundred microseconds each time a Reset is detected on the
@@ -67,6 +84,9 @@
 //! A user will lose control of the pin's output and reading from it may not reflect the actual state
 //! of the external pin.
 //!
+//! The workaround is only applied on the affected revisions of the chip, which is detected at
+//! runtime, and can be turned off with `UsbBus::set_errata5_workaround`.
+//!
 //! ```no_run
 //! # use rp2040_hal::{clocks::init_clocks_and_plls, pac, usb::UsbBus, watchdog::Watchdog};
 //! # use usb_device::class_prelude::UsbBusAllocator;
@@ -107,7 +127,7 @@
 
 use core::cell::RefCell;
 
-use crate::clocks::UsbClock;
+use crate::clocks::{StoppableClock, UsbClock};
 use crate::pac::RESETS;
 use crate::pac::USBCTRL_DPRAM;
 use crate::pac::USBCTRL_REGS;
@@ -123,15 +143,66 @@ use usb_device::{
 
 #[cfg(feature = "rp2040-e5")]
 mod errata5;
+pub mod host;
+#[cfg(feature = "usb-logger")]
+pub mod logger;
+pub mod reset_interface;
 
 fn ep_addr_to_ep_buf_ctrl_idx(ep_addr: EndpointAddress) -> usize {
     ep_addr.index() * 2 + (if ep_addr.is_in() { 0 } else { 1 })
 }
+
+// Bits of each half of a buffer control register
+const BUF_LENGTH: u16 = 0x3ff;
+const BUF_AVAILABLE: u16 = 1 << 10;
+const BUF_STALL: u16 = 1 << 11;
+const BUF_RESET: u16 = 1 << 12;
+const BUF_PID: u16 = 1 << 13;
+const BUF_FULL: u16 = 1 << 15;
+
+/// Write the half of a buffer control register for buffer `half`, leaving the other one alone
+///
+/// In double-buffered mode, the controller updates the other buffer concurrently, which a
+/// read-modify-write of the whole register could undo.
+fn write_buf_control_half(buf_control: *mut u32, half: u8, value: u16) {
+    // Safety: the DPRAM supports 16-bit accesses, and the register belongs to our endpoint
+    unsafe {
+        (buf_control as *mut u16)
+            .add(half.into())
+            .write_volatile(value)
+    }
+}
+
+/// Start a double-buffered endpoint again from the first buffer, on DATA0
+///
+/// The buffers of an OUT endpoint are given to the controller, to receive the next packets.
+fn reset_double_buffered(ep: &mut Endpoint, buf_control: *mut u32, is_out: bool) {
+    ep.next_buffer = 0;
+    ep.next_pid = false;
+    // Safety: the register belongs to the endpoint
+    unsafe { buf_control.write_volatile(BUF_RESET.into()) };
+    if is_out {
+        // the buffers alternate, so each one always receives the same PID
+        let buf0 = ep.max_packet_size;
+        let buf1 = ep.max_packet_size | BUF_PID;
+        write_buf_control_half(buf_control, 0, buf0);
+        write_buf_control_half(buf_control, 1, buf1);
+        cortex_m::asm::delay(12);
+        write_buf_control_half(buf_control, 0, buf0 | BUF_AVAILABLE);
+        write_buf_control_half(buf_control, 1, buf1 | BUF_AVAILABLE);
+    }
+}
+
 #[derive(Debug)]
 struct Endpoint {
     ep_type: EndpointType,
     max_packet_size: u16,
     buffer_offset: u16,
+    double_buffered: bool,
+    // Buffer to be handled next by the CPU, when double buffered
+    next_buffer: u8,
+    // PID of the next packet sent from a double-buffered IN endpoint, true for DATA1
+    next_pid: bool,
 }
 impl Endpoint {
     unsafe fn get_buf_parts(&self) -> (*mut u8, usize) {
@@ -139,8 +210,10 @@ impl Endpoint {
         if self.ep_type == EndpointType::Control {
             (DPRAM_BASE.offset(0x100), self.max_packet_size as usize)
         } else {
+            // The second buffer follows the first one
+            let buffer_offset = self.buffer_offset + u16::from(self.next_buffer);
             (
-                DPRAM_BASE.offset(0x180 + (self.buffer_offset * 64) as isize),
+                DPRAM_BASE.offset(0x180 + (buffer_offset * 64) as isize),
                 self.max_packet_size as usize,
             )
         }
@@ -167,11 +240,22 @@ struct Inner {
     out_endpoints: [Option<Endpoint>; 16],
     next_offset: u16,
     read_setup: bool,
+    suspended: bool,
+    event_callback: Option<fn(BusEvent)>,
+    sof_callback: Option<fn(u16)>,
+    // Events and frame number recorded while the bus is borrowed, for the callbacks
+    pending_event: Option<BusEvent>,
+    pending_sof: Option<u16>,
+    usb_clock: UsbClock,
+    enabled: bool,
+    vbus_present: bool,
     #[cfg(feature = "rp2040-e5")]
     errata5_state: Option<errata5::Errata5State>,
+    #[cfg(feature = "rp2040-e5")]
+    errata5_enabled: bool,
 }
 impl Inner {
-    fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM) -> Self {
+    fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM, usb_clock: UsbClock) -> Self {
         Self {
             ctrl_reg,
             ctrl_dpram,
@@ -179,8 +263,113 @@ impl Inner {
             out_endpoints: Default::default(),
             next_offset: 0,
             read_setup: false,
+            suspended: false,
+            event_callback: None,
+            sof_callback: None,
+            pending_event: None,
+            pending_sof: None,
+            usb_clock,
+            enabled: false,
+            vbus_present: true,
             #[cfg(feature = "rp2040-e5")]
             errata5_state: None,
+            #[cfg(feature = "rp2040-e5")]
+            errata5_enabled: errata5::is_affected(),
+        }
+    }
+
+    /// Record `event`, for its callback to be called once the bus isn't borrowed anymore
+    fn notify(&mut self, event: BusEvent) {
+        self.pending_event = Some(event);
+    }
+
+    fn poll(&mut self) -> PollResult {
+        #[cfg(feature = "rp2040-e5")]
+        if let Some(state) = self.errata5_state.take() {
+            unsafe {
+                self.errata5_state = state.update();
+            }
+            return if self.errata5_state.is_some() {
+                PollResult::None
+            } else {
+                PollResult::Reset
+            };
+        }
+
+        if self.ctrl_reg.intr.read().dev_sof().bit_is_set() {
+            // reading the frame number clears the interrupt
+            let frame_number = self.ctrl_reg.sof_rd.read().count().bits();
+            self.pending_sof = Some(frame_number);
+        }
+
+        // check for bus reset and/or suspended states.
+        let sie_status = self.ctrl_reg.sie_status.read();
+        let (out_pending, out_status_bits) = self.double_buffered_out_pending();
+        let mut buff_status = self.ctrl_reg.buff_status.read().bits() & !out_status_bits;
+
+        if sie_status.bus_reset().bit_is_set() {
+            self.suspended = false;
+            self.notify(BusEvent::Reset);
+            #[cfg(feature = "rp2040-e5")]
+            if self.errata5_enabled && sie_status.connected().bit_is_clear() {
+                self.errata5_state = Some(errata5::Errata5State::start());
+                return PollResult::None;
+            } else {
+                return PollResult::Reset;
+            }
+
+            #[cfg(not(feature = "rp2040-e5"))]
+            return PollResult::Reset;
+        } else if buff_status == 0 && out_pending == 0 && sie_status.setup_rec().bit_is_clear() {
+            if sie_status.suspended().bit_is_set() {
+                self.ctrl_reg.sie_status.write(|w| w.suspended().set_bit());
+                self.suspended = true;
+                self.notify(BusEvent::Suspend);
+                return PollResult::Suspend;
+            } else if sie_status.resume().bit_is_set() {
+                self.ctrl_reg.sie_status.write(|w| w.resume().set_bit());
+                self.suspended = false;
+                self.notify(BusEvent::Resume);
+                return PollResult::Resume;
+            }
+            return PollResult::None;
+        }
+
+        let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) = (out_pending, 0, 0);
+
+        // IN Complete shall only be reported once.
+        self.ctrl_reg
+            .buff_status
+            .write(|w| unsafe { w.bits(0x5555_5555) });
+
+        for i in 0..32u32 {
+            if buff_status == 0 {
+                break;
+            } else if (buff_status & 1) == 1 {
+                let is_in = (i & 1) == 0;
+                let ep_idx = i / 2;
+                if is_in {
+                    ep_in_complete |= 1 << ep_idx;
+                } else {
+                    ep_out |= 1 << ep_idx;
+                }
+            }
+            buff_status >>= 1;
+        }
+
+        // check for setup request
+        if sie_status.setup_rec().bit_is_set() {
+            // Small max_packet_size_ep0 Work-Around
+            self.ctrl_dpram.ep_buffer_control[0].modify(|_, w| w.available_0().clear_bit());
+
+            ep_setup |= 1;
+            self.read_setup = true;
+        }
+
+        PollResult::Data {
+            ep_out,
+            ep_in_complete,
+            ep_setup,
         }
     }
 
@@ -241,13 +430,23 @@ impl Inner {
                 ep_type,
                 max_packet_size,
                 buffer_offset: 0, // not used on CTRL ep
+                double_buffered: false,
+                next_buffer: 0,
+                next_pid: false,
             });
         } else {
+            // Bulk endpoints get two buffers, so that a packet can be transferred while the
+            // other one is handled by the CPU.
+            let double_buffered = ep_type == EndpointType::Bulk;
             // size in 64bytes units.
             // NOTE: the compiler is smart enough to recognize /64 as a 6bit right shift so let's
             // keep the division here for the sake of clarity
-            let aligned_sized = (max_packet_size + 63) / 64;
-            if (self.next_offset + aligned_sized) > (4096 / 64) {
+            let mut aligned_sized = (max_packet_size + 63) / 64;
+            if double_buffered {
+                aligned_sized *= 2;
+            }
+            // the buffers start at 0x180 in the 4kB of DPRAM
+            if (self.next_offset + aligned_sized) > ((4096 - 0x180) / 64) {
                 return Err(UsbError::EndpointMemoryOverflow);
             }
 
@@ -258,11 +457,47 @@ impl Inner {
                 ep_type,
                 max_packet_size,
                 buffer_offset,
+                double_buffered,
+                next_buffer: 0,
+                next_pid: false,
             });
         }
         Ok(ep_addr)
     }
 
+    /// Returns the double-buffered OUT endpoints with a full buffer to be read, and their bits
+    /// in BUFF_STATUS
+    ///
+    /// The bit of an endpoint is set once when both of its buffers complete, so it's only
+    /// cleared here when no buffer is left for the CPU.
+    fn double_buffered_out_pending(&self) -> (u16, u32) {
+        let mut pending = 0;
+        let mut status_bits = 0;
+        for (index, ep) in self.out_endpoints.iter().enumerate() {
+            let ep = match ep {
+                Some(ep) if ep.double_buffered => ep,
+                _ => continue,
+            };
+            let status_bit = 1 << (index * 2 + 1);
+            status_bits |= status_bit;
+
+            let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2 + 1];
+            let is_full =
+                || (buf_control.read().bits() >> (16 * ep.next_buffer)) as u16 & BUF_FULL != 0;
+            if !is_full() {
+                self.ctrl_reg
+                    .buff_status
+                    .write(|w| unsafe { w.bits(status_bit) });
+                // a buffer may have completed before the bit was cleared
+                if !is_full() {
+                    continue;
+                }
+            }
+            pending |= 1 << index;
+        }
+        (pending, status_bits)
+    }
+
     fn ep_reset_all(&mut self) {
         self.ctrl_reg
             .sie_ctrl
@@ -274,11 +509,11 @@ impl Inner {
         self.ctrl_dpram.ep_buffer_control[1].write(|w| w.available_0().set_bit());
 
         for (index, ep) in itertools::interleave(
-            self.in_endpoints.iter().skip(1),  // skip control endpoint
-            self.out_endpoints.iter().skip(1), // skip control endpoint
+            self.in_endpoints.iter_mut().skip(1),  // skip control endpoint
+            self.out_endpoints.iter_mut().skip(1), // skip control endpoint
         )
         .enumerate()
-        .filter_map(|(i, ep)| ep.as_ref().map(|ep| (i, ep)))
+        .filter_map(|(i, ep)| ep.as_mut().map(|ep| (i, ep)))
         {
             use pac::usbctrl_dpram::ep_control::ENDPOINT_TYPE_A;
             let ep_type = match ep.ep_type {
@@ -292,12 +527,15 @@ impl Inner {
             self.ctrl_dpram.ep_control[index].modify(|_, w| unsafe {
                 w.endpoint_type().variant(ep_type);
                 w.interrupt_per_buff().set_bit();
+                w.double_buffered().bit(ep.double_buffered);
                 w.enable().set_bit();
                 w.buffer_address().bits(0x180 + (ep.buffer_offset << 6))
             });
             // reset OUT ep and prepare IN ep to accept data
             let buf_control = &self.ctrl_dpram.ep_buffer_control[index + 2];
-            if (index & 1) == 0 {
+            if ep.double_buffered {
+                reset_double_buffered(ep, buf_control.as_ptr(), (index & 1) == 1);
+            } else if (index & 1) == 0 {
                 // first write occur on DATA0 so prepare the pid bit to be flipped
                 buf_control.write(|w| w.pid_0().set_bit());
             } else {
@@ -320,6 +558,34 @@ impl Inner {
             .ok_or(UsbError::InvalidEndpoint)?;
 
         let buf_control = &self.ctrl_dpram.ep_buffer_control[index * 2];
+        if ep.double_buffered {
+            let half = ep.next_buffer;
+            let current = (buf_control.read().bits() >> (16 * half)) as u16;
+            if current & BUF_AVAILABLE != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+
+            let ep_buf = ep.get_buf_mut();
+            if ep_buf.len() < buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+            ep_buf[..buf.len()].copy_from_slice(buf);
+
+            let mut value = buf.len() as u16 | BUF_FULL;
+            if ep.next_pid {
+                value |= BUF_PID;
+            }
+            if half == 0 {
+                value |= current & BUF_STALL;
+            }
+            write_buf_control_half(buf_control.as_ptr(), half, value);
+            cortex_m::asm::delay(12);
+            write_buf_control_half(buf_control.as_ptr(), half, value | BUF_AVAILABLE);
+
+            ep.next_buffer ^= 1;
+            ep.next_pid = !ep.next_pid;
+            return Ok(buf.len());
+        }
         if buf_control.read().available_0().bit_is_set() {
             return Err(UsbError::WouldBlock);
         }
@@ -330,10 +596,12 @@ impl Inner {
         }
         ep_buf[..buf.len()].copy_from_slice(buf);
 
+        // full speed isochronous packets are always sent on DATA0
+        let is_iso = ep.ep_type == EndpointType::Isochronous;
         buf_control.modify(|r, w| unsafe {
             w.length_0().bits(buf.len() as u16);
             w.full_0().set_bit();
-            w.pid_0().bit(!r.pid_0().bit())
+            w.pid_0().bit(!is_iso && !r.pid_0().bit())
         });
         cortex_m::asm::delay(12);
         buf_control.modify(|_, w| w.available_0().set_bit());
@@ -389,6 +657,31 @@ impl Inner {
 
             self.read_setup = false;
             Ok(len)
+        } else if ep.double_buffered {
+            let half = ep.next_buffer;
+            let current = (buf_control_val.bits() >> (16 * half)) as u16;
+            if current & BUF_FULL == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+            let len = usize::from(current & BUF_LENGTH);
+            if len > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            buf[..len].copy_from_slice(&ep.get_buf()[..len]);
+            // BUFF_STATUS is cleared by `poll` once both buffers are handled.
+
+            // give the buffer back to the controller, for the same PID
+            let mut value = ep.max_packet_size | (current & BUF_PID);
+            if half == 0 {
+                value |= current & BUF_STALL;
+            }
+            write_buf_control_half(buf_control.as_ptr(), half, value);
+            cortex_m::asm::delay(12);
+            write_buf_control_half(buf_control.as_ptr(), half, value | BUF_AVAILABLE);
+
+            ep.next_buffer ^= 1;
+            Ok(len)
         } else {
             if buf_control_val.full_0().bit_is_clear() {
                 return Err(UsbError::WouldBlock);
@@ -404,10 +697,11 @@ impl Inner {
                 .buff_status
                 .write(|w| unsafe { w.bits(1 << (index * 2 + 1)) });
 
+            let is_iso = ep.ep_type == EndpointType::Isochronous;
             buf_control.modify(|r, w| unsafe {
                 w.length_0().bits(ep.max_packet_size);
                 w.full_0().clear_bit();
-                w.pid_0().bit(!r.pid_0().bit())
+                w.pid_0().bit(!is_iso && !r.pid_0().bit())
             });
             if index != 0 || len == ep.max_packet_size.into() {
                 // only mark as available on the control endpoint if and only if the packet was
@@ -420,6 +714,35 @@ impl Inner {
     }
 }
 
+/// State of the bus, see [`UsbBus::state`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusState {
+    /// The device isn't connected to a host, or the host didn't reset the bus yet
+    Disconnected,
+    /// The bus is active
+    Active,
+    /// The host suspended the bus
+    Suspended,
+}
+
+/// Change of the bus state, reported to the callback set with
+/// [`UsbBus::set_event_callback`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusEvent {
+    /// The host reset the bus, the device is going to be enumerated again
+    Reset,
+    /// The host suspended the bus, the device has to reduce its consumption within 10ms
+    Suspend,
+    /// The bus is active again after a suspend
+    Resume,
+    /// VBUS was detected, see [`UsbBus::set_vbus_present`]
+    Attached,
+    /// VBUS was lost, see [`UsbBus::set_vbus_present`]
+    Detached,
+}
+
 /// Usb bus
 pub struct UsbBus {
     inner: Mutex<RefCell<Inner>>,
@@ -430,7 +753,7 @@ impl UsbBus {
     pub fn new(
         ctrl_reg: USBCTRL_REGS,
         ctrl_dpram: USBCTRL_DPRAM,
-        _pll: UsbClock,
+        usb_clock: UsbClock,
         force_vbus_detect_bit: bool,
         resets: &mut RESETS,
     ) -> Self {
@@ -464,18 +787,224 @@ impl UsbBus {
             w.controller_en().set_bit()
         });
 
+        crate::sleep::peripheral_enabled::<USBCTRL_REGS>();
+
         Self {
-            inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram))),
+            inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram, usb_clock))),
         }
     }
 
-    /// Generates a resume request on the bus.
+    /// Report whether VBUS is present, for self-powered devices
+    ///
+    /// A self-powered device must not pull D+ up while the host doesn't power the bus. Create
+    /// the bus with `force_vbus_detect_bit` set to false, and call this whenever the VBUS
+    /// sense input changes, e.g. from its GPIO interrupt. On the Pico, VBUS is sensed through a
+    /// divider on GPIO24. The device also has to report itself as self-powered, with
+    /// `UsbDeviceBuilder::self_powered`.
+    ///
+    /// The pull-up is released and clk_usb is stopped while VBUS is absent, and the callback
+    /// set with [`set_event_callback`](Self::set_event_callback) gets [`BusEvent::Attached`] or
+    /// [`BusEvent::Detached`] when the state changes. clk_usb keeps running while the bus is
+    /// suspended, as the controller needs it to notice the resume of the host.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::{gpio::{bank0::Gpio24, Pin, PullDownInput, Interrupt}, usb::UsbBus};
+    /// # use embedded_hal::digital::v2::InputPin;
+    /// fn on_vbus_edge(usb_bus: &UsbBus, vbus_sense: &mut Pin<Gpio24, PullDownInput>) {
+    ///     vbus_sense.clear_interrupt(Interrupt::EdgeHigh);
+    ///     vbus_sense.clear_interrupt(Interrupt::EdgeLow);
+    ///     usb_bus.set_vbus_present(vbus_sense.is_high().unwrap());
+    /// }
+    /// ```
+    pub fn set_vbus_present(&self, present: bool) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.ctrl_reg.usb_pwr.modify(|_, w| {
+                w.vbus_detect().bit(present);
+                w.vbus_detect_override_en().set_bit()
+            });
+            if inner.vbus_present == present {
+                return;
+            }
+            inner.vbus_present = present;
+            if present {
+                inner.usb_clock.enable();
+            }
+            if inner.enabled {
+                inner
+                    .ctrl_reg
+                    .sie_ctrl
+                    .modify(|_, w| w.pullup_en().bit(present));
+            }
+            if !present {
+                inner.suspended = false;
+                // Nothing can happen on the bus until VBUS is back
+                inner.usb_clock.disable();
+            }
+            inner.notify(if present {
+                BusEvent::Attached
+            } else {
+                BusEvent::Detached
+            });
+            drop(inner);
+            self.run_callbacks(cs);
+        });
+    }
+
+    /// Returns true if the controller detects VBUS, or it was reported with
+    /// [`set_vbus_present`](Self::set_vbus_present)
+    pub fn is_vbus_present(&self) -> bool {
+        critical_section::with(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            inner
+                .ctrl_reg
+                .sie_status
+                .read()
+                .vbus_detected()
+                .bit_is_set()
+        })
+    }
+
+    /// Enable or disable the workaround for RP2040-E5, see the
+    /// [module level documentation](crate::usb)
+    ///
+    /// The workaround is enabled by default on the affected B0 and B1 revisions of the chip
+    /// only, so that GPIO15 isn't disturbed on the B2 revision. Disabling it also gives GPIO15
+    /// back on affected chips, if the device is never used behind a busy hub.
+    #[cfg(feature = "rp2040-e5")]
+    pub fn set_errata5_workaround(&self, enabled: bool) {
+        critical_section::with(|cs| {
+            self.inner.borrow(cs).borrow_mut().errata5_enabled = enabled;
+        });
+    }
+
+    /// Returns the number of the current frame, from the last start of frame packet
+    ///
+    /// The host sends a start of frame packet every 1ms, with a frame number counting up to
+    /// 2047 and wrapping around.
+    pub fn frame_number(&self) -> u16 {
+        critical_section::with(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            inner.ctrl_reg.sof_rd.read().count().bits()
+        })
+    }
+
+    /// Call `callback` from `poll` with the frame number on each start of frame packet
+    ///
+    /// This enables the start of frame interrupt, so `poll` runs every 1ms when called from
+    /// the USBCTRL_IRQ interrupt handler. Classes whose timing follows the frames, like USB
+    /// audio or the idle rate of HID, can do their periodic work from there. `None` disables
+    /// the interrupt again.
+    ///
+    /// The callback runs in a critical section, and should be short. The bus isn't borrowed
+    /// anymore when it runs, so it can call the methods of `UsbBus`.
+    pub fn set_sof_callback(&self, callback: Option<fn(u16)>) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.sof_callback = callback;
+            inner
+                .ctrl_reg
+                .inte
+                .modify(|_, w| w.dev_sof().bit(callback.is_some()));
+        });
+    }
+
+    /// Generates a resume request on the bus, to wake up the host.
+    ///
+    /// This is only signalled while the bus is suspended, and has no effect otherwise. The
+    /// device must advertise remote wakeup with `UsbDeviceBuilder::supports_remote_wakeup`,
+    /// and the host must have enabled it, which `UsbDevice::remote_wakeup_enabled` tells. The
+    /// USB specification also requires the bus to have been suspended for at least 5ms.
+    ///
+    /// The host then drives the resume signalling, which `UsbDevice::poll` reports as a
+    /// resume as usual.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::usb::UsbBus;
+    /// # use usb_device::prelude::*;
+    /// # fn key_pressed(usb_dev: &UsbDevice<UsbBus>) {
+    /// if usb_dev.state() == UsbDeviceState::Suspend && usb_dev.remote_wakeup_enabled() {
+    ///     usb_dev.bus().remote_wakeup();
+    /// }
+    /// # }
+    /// ```
     pub fn remote_wakeup(&self) {
         critical_section::with(|cs| {
             let inner = self.inner.borrow(cs).borrow_mut();
-            inner.ctrl_reg.sie_ctrl.modify(|_, w| w.resume().set_bit());
+            if inner.suspended {
+                inner.ctrl_reg.sie_ctrl.modify(|_, w| w.resume().set_bit());
+            }
+        });
+    }
+
+    /// Returns true if the bus is suspended, as last reported by `poll`.
+    pub fn is_suspended(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow(cs).borrow().suspended)
+    }
+
+    /// Returns the state of the bus
+    ///
+    /// Suspend and resume are tracked by `poll`, which has to be called from the USBCTRL_IRQ
+    /// interrupt handler, or often enough, for the state to be current.
+    pub fn state(&self) -> BusState {
+        critical_section::with(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            if inner.suspended {
+                BusState::Suspended
+            } else if inner.ctrl_reg.sie_status.read().connected().bit_is_set() {
+                BusState::Active
+            } else {
+                BusState::Disconnected
+            }
+        })
+    }
+
+    /// Call `callback` from `poll` when the host resets, suspends or resumes the bus
+    ///
+    /// The callback runs in a critical section, and should only record the event, e.g. to
+    /// switch off LEDs or other loads while the bus is suspended. The bus isn't borrowed
+    /// anymore when it runs, so it can call the methods of `UsbBus`. The clocks of the controller
+    /// keep running in [sleep mode](crate::sleep) as long as the bus exists, so the resume of
+    /// the host wakes up a core sleeping with the USBCTRL_IRQ interrupt unmasked.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::usb::UsbBus;
+    /// use core::sync::atomic::{AtomicBool, Ordering};
+    /// use rp2040_hal::usb::BusEvent;
+    ///
+    /// static SUSPENDED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn on_bus_event(event: BusEvent) {
+    ///     SUSPENDED.store(event == BusEvent::Suspend, Ordering::Relaxed);
+    /// }
+    ///
+    /// # fn setup(usb_bus: &UsbBus) {
+    /// usb_bus.set_event_callback(Some(on_bus_event));
+    /// # }
+    /// ```
+    pub fn set_event_callback(&self, callback: Option<fn(BusEvent)>) {
+        critical_section::with(|cs| {
+            self.inner.borrow(cs).borrow_mut().event_callback = callback;
         });
     }
+
+    /// Call the callbacks of the events recorded while the bus was borrowed
+    ///
+    /// The bus isn't borrowed anymore, so the callbacks can use it, e.g. to read its state.
+    fn run_callbacks(&self, cs: critical_section::CriticalSection<'_>) {
+        let (event, sof) = {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            let event = inner.pending_event.take().zip(inner.event_callback);
+            let sof = inner.pending_sof.take().zip(inner.sof_callback);
+            (event, sof)
+        };
+        if let Some((frame_number, callback)) = sof {
+            callback(frame_number);
+        }
+        if let Some((event, callback)) = event {
+            callback(event);
+        }
+    }
 }
 
 impl UsbBusTrait for UsbBus {
@@ -496,7 +1025,7 @@ impl UsbBusTrait for UsbBus {
 
     fn enable(&mut self) {
         critical_section::with(|cs| {
-            let inner = self.inner.borrow(cs).borrow_mut();
+            let mut inner = self.inner.borrow(cs).borrow_mut();
             // at this stage ep's are expected to be in their reset state
             // TODO: is it worth having a debug_assert for that here?
 
@@ -516,11 +1045,13 @@ impl UsbBusTrait for UsbBus {
                     .set_bit()
             });
 
-            // enable pull up to let the host know we exist.
+            // enable pull up to let the host know we exist, unless it doesn't power the bus.
+            inner.enabled = true;
+            let vbus_present = inner.vbus_present;
             inner
                 .ctrl_reg
                 .sie_ctrl
-                .modify(|_, w| w.pullup_en().set_bit());
+                .modify(|_, w| w.pullup_en().bit(vbus_present));
         })
     }
     fn reset(&self) {
@@ -539,8 +1070,6 @@ impl UsbBusTrait for UsbBus {
 
             // Reset address register
             inner.ctrl_reg.addr_endp.reset();
-            // TODO: RP2040-E5: work around implementation
-            // TODO: reset all endpoints & buffer statuses
         })
     }
     fn set_device_address(&self, addr: u8) {
@@ -569,7 +1098,8 @@ impl UsbBusTrait for UsbBus {
     }
     fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
         critical_section::with(|cs| {
-            let inner = self.inner.borrow(cs).borrow_mut();
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            let inner = &mut *inner;
 
             if ep_addr.index() == 0 {
                 inner.ctrl_reg.ep_stall_arm.modify(|_, w| {
@@ -582,7 +1112,30 @@ impl UsbBusTrait for UsbBus {
             }
 
             let index = ep_addr_to_ep_buf_ctrl_idx(ep_addr);
-            inner.ctrl_dpram.ep_buffer_control[index].modify(|_, w| w.stall().bit(stalled));
+            let buf_control = inner.ctrl_dpram.ep_buffer_control[index].as_ptr();
+            let endpoints = if ep_addr.is_in() {
+                &mut inner.in_endpoints
+            } else {
+                &mut inner.out_endpoints
+            };
+            match endpoints.get_mut(ep_addr.index()).and_then(Option::as_mut) {
+                // Clearing a halt starts the endpoint again on DATA0, from the first buffer
+                Some(ep) if ep.double_buffered && !stalled => {
+                    reset_double_buffered(ep, buf_control, ep_addr.is_out())
+                }
+                _ => {
+                    // The stall bit is in the half of the first buffer, the controller may be
+                    // updating the second one
+                    // Safety: the DPRAM supports 16-bit accesses
+                    let half0 = unsafe { (buf_control as *const u16).read_volatile() };
+                    let value = if stalled {
+                        half0 | BUF_STALL
+                    } else {
+                        half0 & !BUF_STALL
+                    };
+                    write_buf_control_half(buf_control, 0, value);
+                }
+            }
         })
     }
     fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
@@ -599,83 +1152,9 @@ impl UsbBusTrait for UsbBus {
     fn resume(&self) {}
     fn poll(&self) -> PollResult {
         critical_section::with(|cs| {
-            let mut inner = self.inner.borrow(cs).borrow_mut();
-
-            #[cfg(feature = "rp2040-e5")]
-            if let Some(state) = inner.errata5_state.take() {
-                unsafe {
-                    inner.errata5_state = state.update();
-                }
-                return if inner.errata5_state.is_some() {
-                    PollResult::None
-                } else {
-                    PollResult::Reset
-                };
-            }
-
-            // check for bus reset and/or suspended states.
-            let sie_status = inner.ctrl_reg.sie_status.read();
-            let mut buff_status = inner.ctrl_reg.buff_status.read().bits();
-
-            if sie_status.bus_reset().bit_is_set() {
-                #[cfg(feature = "rp2040-e5")]
-                if sie_status.connected().bit_is_clear() {
-                    inner.errata5_state = Some(errata5::Errata5State::start());
-                    return PollResult::None;
-                } else {
-                    return PollResult::Reset;
-                }
-
-                #[cfg(not(feature = "rp2040-e5"))]
-                return PollResult::Reset;
-            } else if buff_status == 0 && sie_status.setup_rec().bit_is_clear() {
-                if sie_status.suspended().bit_is_set() {
-                    inner.ctrl_reg.sie_status.write(|w| w.suspended().set_bit());
-                    return PollResult::Suspend;
-                } else if sie_status.resume().bit_is_set() {
-                    inner.ctrl_reg.sie_status.write(|w| w.resume().set_bit());
-                    return PollResult::Resume;
-                }
-                return PollResult::None;
-            }
-
-            let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) = (0, 0, 0);
-
-            // IN Complete shall only be reported once.
-            inner
-                .ctrl_reg
-                .buff_status
-                .write(|w| unsafe { w.bits(0x5555_5555) });
-
-            for i in 0..32u32 {
-                if buff_status == 0 {
-                    break;
-                } else if (buff_status & 1) == 1 {
-                    let is_in = (i & 1) == 0;
-                    let ep_idx = i / 2;
-                    if is_in {
-                        ep_in_complete |= 1 << ep_idx;
-                    } else {
-                        ep_out |= 1 << ep_idx;
-                    }
-                }
-                buff_status >>= 1;
-            }
-
-            // check for setup request
-            if sie_status.setup_rec().bit_is_set() {
-                // Small max_packet_size_ep0 Work-Around
-                inner.ctrl_dpram.ep_buffer_control[0].modify(|_, w| w.available_0().clear_bit());
-
-                ep_setup |= 1;
-                inner.read_setup = true;
-            }
-
-            PollResult::Data {
-                ep_out,
-                ep_in_complete,
-                ep_setup,
-            }
+            let result = self.inner.borrow(cs).borrow_mut().poll();
+            self.run_callbacks(cs);
+            result
         })
     }
 
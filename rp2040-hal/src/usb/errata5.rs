@@ -18,6 +18,14 @@ pub enum Errata5State {
     ForceLineStateJ(ForceLineStateJ),
 }
 
+/// Returns true on the B0 and B1 revisions of the RP2040, which need the workaround
+pub fn is_affected() -> bool {
+    // Safety: reading the chip id has no side effect
+    let sysinfo = unsafe { &*crate::pac::SYSINFO::ptr() };
+    // B0 and B1 both report revision 1, B2 reports 2
+    sysinfo.chip_id.read().revision().bits() == 1
+}
+
 impl Errata5State {
     pub fn start() -> Self {
         Self::WaitEndOfReset
@@ -0,0 +1,538 @@
+//! USB host mode
+//!
+//! [`UsbHost`] runs the USB controller as a host for a single device connected to the port,
+//! e.g. a keyboard, a mouse or a MIDI controller. Transfers are done one packet at a time
+//! through the controller's general purpose endpoint (EPX), and block until the packet is
+//! done, except that [`UsbHost::read`] returns [`HostError::Nak`] when the device has no data
+//! yet, so interrupt endpoints can be polled. A device which keeps answering with NAK makes
+//! the other transfers fail with [`HostError::Timeout`] after a few hundred milliseconds.
+//!
+//! ```no_run
+//! use embedded_hal::blocking::delay::DelayMs;
+//! use rp2040_hal::{pac, timer::Timer, usb::host::{Pipe, TransferType, UsbHost}};
+//! # use rp2040_hal::{clocks::init_clocks_and_plls, watchdog::Watchdog};
+//! # let mut pac = pac::Peripherals::take().unwrap();
+//! # let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! # let clocks = init_clocks_and_plls(12_000_000, pac.XOSC, pac.CLOCKS, pac.PLL_SYS, pac.PLL_USB, &mut pac.RESETS, &mut watchdog).ok().unwrap();
+//! let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
+//! let mut delay = timer.delay();
+//!
+//! let mut host = UsbHost::new(pac.USBCTRL_REGS, pac.USBCTRL_DPRAM, clocks.usb_clock, &mut pac.RESETS);
+//! while host.device_speed().is_none() {}
+//! host.reset_port(&mut delay);
+//!
+//! // Enumerate the device at address 1
+//! let mut descriptor = [0; 18];
+//! host.get_descriptor(0, 8, 1, 0, &mut descriptor).unwrap();
+//! let max_packet_size0 = descriptor[7].into();
+//! host.set_address(1, max_packet_size0).unwrap();
+//! delay.delay_ms(2u32);
+//! host.get_descriptor(1, max_packet_size0, 1, 0, &mut descriptor).unwrap();
+//! // ... read the configuration descriptor to find the endpoints
+//! host.set_configuration(1, max_packet_size0, 1).unwrap();
+//!
+//! // Poll the interrupt IN endpoint 1 of a keyboard
+//! let mut keyboard = Pipe::new(1, 1, TransferType::Interrupt, 8);
+//! let mut report = [0; 8];
+//! loop {
+//!     if let Ok(len) = host.read(&mut keyboard, &mut report) {
+//!         // handle the report
+//!     }
+//! }
+//! ```
+//!
+//! Hubs, and low speed devices behind a hub, aren't supported.
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use crate::clocks::UsbClock;
+use crate::pac::{self, RESETS, USBCTRL_DPRAM, USBCTRL_REGS};
+use crate::resets::SubsystemReset;
+
+use super::{BUF_AVAILABLE, BUF_FULL, BUF_LENGTH, BUF_PID};
+
+const BUF_LAST: u16 = 1 << 14;
+
+// The EPX registers and buffer, at the same offsets as in the pico-sdk
+const EPX_CTRL_OFFSET: usize = 0x100;
+const EPX_BUFFER_OFFSET: usize = 0x180;
+const EPX_BUFFER_SIZE: usize = 64;
+
+const EP_CTRL_ENABLE: u32 = 1 << 31;
+const EP_CTRL_INTERRUPT_PER_BUFF: u32 = 1 << 29;
+const EP_CTRL_TYPE_SHIFT: u32 = 26;
+
+// The W1C flags of SIE_STATUS which report the outcome of a transaction
+const SIE_STATUS_FLAGS: u32 = 0xff0f_0000;
+const SIE_STATUS_NAK_REC: u32 = 1 << 28;
+
+// NAKs accepted before a transaction is given up. The controller retries every 16µs at most,
+// so this waits for at least 320ms, more than the 50ms allowed for the status stage of a
+// control transfer.
+const NAK_LIMIT: u32 = 20_000;
+
+const REQUEST_SET_ADDRESS: u8 = 5;
+const REQUEST_GET_DESCRIPTOR: u8 = 6;
+const REQUEST_SET_CONFIGURATION: u8 = 9;
+
+/// Speed of the device connected to the port
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeviceSpeed {
+    /// Low speed, 1.5Mbit/s
+    Low,
+    /// Full speed, 12Mbit/s
+    Full,
+}
+
+/// Error of a transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HostError {
+    /// The device has no data to send yet, try again later
+    Nak,
+    /// The device stalled the endpoint
+    Stall,
+    /// The device didn't answer in time, or kept answering with NAK
+    Timeout,
+    /// The device sent a packet with the wrong data toggle
+    DataSequence,
+    /// A packet was corrupted on the bus
+    Crc,
+    /// A packet had a bit stuffing error
+    BitStuff,
+    /// The device sent more data than expected
+    Overflow,
+    /// No device is connected
+    Disconnected,
+}
+
+/// Type of the transfers of a [`Pipe`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransferType {
+    /// Bulk transfers
+    Bulk = 2,
+    /// Interrupt transfers
+    Interrupt = 3,
+}
+
+/// An endpoint of the device, with the state of its data toggle
+#[derive(Debug)]
+pub struct Pipe {
+    address: u8,
+    endpoint: u8,
+    transfer_type: TransferType,
+    max_packet_size: u16,
+    data1: bool,
+}
+
+impl Pipe {
+    /// Create the pipe to `endpoint` of the device at `address`, from its endpoint descriptor
+    ///
+    /// `max_packet_size` is limited to 64 bytes.
+    pub fn new(
+        address: u8,
+        endpoint: u8,
+        transfer_type: TransferType,
+        max_packet_size: u16,
+    ) -> Self {
+        Self {
+            address,
+            endpoint,
+            transfer_type,
+            max_packet_size: max_packet_size.min(EPX_BUFFER_SIZE as u16),
+            data1: false,
+        }
+    }
+
+    /// Reset the data toggle, after the endpoint was cleared from a stall or the device was
+    /// configured again
+    pub fn reset_data_toggle(&mut self) {
+        self.data1 = false;
+    }
+}
+
+/// The USB controller in host mode, see the [module level documentation](self)
+pub struct UsbHost {
+    ctrl_reg: USBCTRL_REGS,
+    ctrl_dpram: USBCTRL_DPRAM,
+}
+
+impl UsbHost {
+    /// Bring up the USB controller as a host
+    ///
+    /// The board has to supply VBUS to the device.
+    pub fn new(
+        ctrl_reg: USBCTRL_REGS,
+        ctrl_dpram: USBCTRL_DPRAM,
+        _pll: UsbClock,
+        resets: &mut RESETS,
+    ) -> Self {
+        ctrl_reg.reset_bring_down(resets);
+        ctrl_reg.reset_bring_up(resets);
+
+        unsafe {
+            let raw_ctrl_pdram =
+                core::slice::from_raw_parts_mut(USBCTRL_DPRAM::ptr() as *mut u32, 1 + 0xfc / 4);
+            raw_ctrl_pdram.fill(0);
+        }
+
+        ctrl_reg.usb_muxing.modify(|_, w| {
+            w.to_phy().set_bit();
+            w.softcon().set_bit()
+        });
+        ctrl_reg.usb_pwr.modify(|_, w| {
+            w.vbus_detect().set_bit();
+            w.vbus_detect_override_en().set_bit()
+        });
+        ctrl_reg.main_ctrl.modify(|_, w| {
+            w.sim_timing().clear_bit();
+            w.host_ndevice().set_bit();
+            w.controller_en().set_bit()
+        });
+        ctrl_reg.sie_ctrl.write(|w| {
+            w.sof_en().set_bit();
+            w.keep_alive_en().set_bit();
+            w.pulldown_en().set_bit()
+        });
+
+        crate::sleep::peripheral_enabled::<USBCTRL_REGS>();
+
+        Self {
+            ctrl_reg,
+            ctrl_dpram,
+        }
+    }
+
+    /// Returns the speed of the connected device, or `None` if no device is connected
+    pub fn device_speed(&self) -> Option<DeviceSpeed> {
+        match self.ctrl_reg.sie_status.read().speed().bits() {
+            1 => Some(DeviceSpeed::Low),
+            2 => Some(DeviceSpeed::Full),
+            _ => None,
+        }
+    }
+
+    /// Reset the port, after a device was connected
+    ///
+    /// This signals a bus reset, and gives the device 50ms for the reset and another 10ms to
+    /// recover from it. The device then answers at address 0.
+    pub fn reset_port<D: DelayMs<u32>>(&mut self, delay: &mut D) {
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.reset_bus().set_bit());
+        delay.delay_ms(50);
+        delay.delay_ms(10);
+    }
+
+    /// Do a control transfer reading data from the device, and return the length read
+    ///
+    /// `setup` is the setup packet, whose length field should match `buf`, and
+    /// `max_packet_size0` the maximum packet size of endpoint 0: 8 before the device
+    /// descriptor was read.
+    pub fn control_in(
+        &mut self,
+        address: u8,
+        max_packet_size0: u16,
+        setup: &[u8; 8],
+        buf: &mut [u8],
+    ) -> Result<usize, HostError> {
+        let mut pipe = self.control_pipe(address, max_packet_size0);
+        self.send_setup(&pipe, setup)?;
+
+        let mut len = 0;
+        pipe.data1 = true;
+        while len < buf.len() {
+            let packet_len = self.transfer_in(&mut pipe, &mut buf[len..], false)?;
+            len += packet_len;
+            if packet_len < usize::from(pipe.max_packet_size) {
+                break;
+            }
+        }
+
+        // status stage
+        pipe.data1 = true;
+        self.transfer_out(&mut pipe, &[])?;
+        Ok(len)
+    }
+
+    /// Do a control transfer writing `data` to the device
+    ///
+    /// See [`control_in`](Self::control_in) for the parameters.
+    pub fn control_out(
+        &mut self,
+        address: u8,
+        max_packet_size0: u16,
+        setup: &[u8; 8],
+        data: &[u8],
+    ) -> Result<(), HostError> {
+        let mut pipe = self.control_pipe(address, max_packet_size0);
+        self.send_setup(&pipe, setup)?;
+
+        pipe.data1 = true;
+        for packet in data.chunks(pipe.max_packet_size.into()) {
+            self.transfer_out(&mut pipe, packet)?;
+        }
+
+        // status stage
+        pipe.data1 = true;
+        self.transfer_in(&mut pipe, &mut [], false)?;
+        Ok(())
+    }
+
+    /// Read the descriptor of type `descriptor_type` and `index`, e.g. 1 and 0 for the device
+    /// descriptor, or 2 and 0 for the first configuration descriptor
+    pub fn get_descriptor(
+        &mut self,
+        address: u8,
+        max_packet_size0: u16,
+        descriptor_type: u8,
+        index: u8,
+        buf: &mut [u8],
+    ) -> Result<usize, HostError> {
+        let len = buf.len().min(u16::MAX.into()) as u16;
+        let setup = setup_packet(
+            0x80,
+            REQUEST_GET_DESCRIPTOR,
+            (u16::from(descriptor_type) << 8) | u16::from(index),
+            0,
+            len,
+        );
+        self.control_in(address, max_packet_size0, &setup, buf)
+    }
+
+    /// Assign `address` to the device, which is at address 0 after a reset
+    pub fn set_address(&mut self, address: u8, max_packet_size0: u16) -> Result<(), HostError> {
+        let setup = setup_packet(0x00, REQUEST_SET_ADDRESS, address.into(), 0, 0);
+        self.control_out(0, max_packet_size0, &setup, &[])
+    }
+
+    /// Select the configuration of the device
+    pub fn set_configuration(
+        &mut self,
+        address: u8,
+        max_packet_size0: u16,
+        configuration: u8,
+    ) -> Result<(), HostError> {
+        let setup = setup_packet(0x00, REQUEST_SET_CONFIGURATION, configuration.into(), 0, 0);
+        self.control_out(address, max_packet_size0, &setup, &[])
+    }
+
+    /// Read one packet from an IN endpoint, and return its length
+    ///
+    /// Returns [`HostError::Nak`] if the device has no data yet.
+    pub fn read(&mut self, pipe: &mut Pipe, buf: &mut [u8]) -> Result<usize, HostError> {
+        self.transfer_in(pipe, buf, true)
+    }
+
+    /// Write `data` to an OUT endpoint, in packets of the maximum packet size
+    ///
+    /// The packets the device isn't ready for are retried until it accepts them.
+    pub fn write(&mut self, pipe: &mut Pipe, data: &[u8]) -> Result<(), HostError> {
+        for packet in data.chunks(pipe.max_packet_size.into()) {
+            self.transfer_out(pipe, packet)?;
+        }
+        Ok(())
+    }
+
+    /// Stop the controller, and return the peripherals
+    pub fn free(self, resets: &mut RESETS) -> (USBCTRL_REGS, USBCTRL_DPRAM) {
+        self.ctrl_reg.reset_bring_down(resets);
+        crate::sleep::peripheral_disabled::<USBCTRL_REGS>();
+        (self.ctrl_reg, self.ctrl_dpram)
+    }
+
+    fn control_pipe(&self, address: u8, max_packet_size0: u16) -> Pipe {
+        Pipe {
+            address,
+            endpoint: 0,
+            transfer_type: TransferType::Bulk,
+            max_packet_size: max_packet_size0.min(EPX_BUFFER_SIZE as u16),
+            data1: false,
+        }
+    }
+
+    fn send_setup(&mut self, pipe: &Pipe, setup: &[u8; 8]) -> Result<(), HostError> {
+        let low = u32::from_le_bytes([setup[0], setup[1], setup[2], setup[3]]);
+        let high = u32::from_le_bytes([setup[4], setup[5], setup[6], setup[7]]);
+        self.ctrl_dpram
+            .setup_packet_low
+            .write(|w| unsafe { w.bits(low) });
+        self.ctrl_dpram
+            .setup_packet_high
+            .write(|w| unsafe { w.bits(high) });
+        self.configure_epx(pipe, 0);
+        self.start_transaction(|w| w.send_setup().set_bit());
+        self.wait_transaction(false)
+    }
+
+    fn transfer_in(
+        &mut self,
+        pipe: &mut Pipe,
+        buf: &mut [u8],
+        stop_on_nak: bool,
+    ) -> Result<usize, HostError> {
+        let endpoint_type = if pipe.endpoint == 0 {
+            0
+        } else {
+            pipe.transfer_type as u32
+        };
+        self.configure_epx(pipe, endpoint_type);
+        self.arm_epx(pipe, pipe.max_packet_size, false);
+        self.start_transaction(|w| w.receive_data().set_bit());
+        self.wait_transaction(stop_on_nak)?;
+
+        let buf_control = self.ctrl_dpram.ep_buffer_control[0].read().bits() as u16;
+        let len = usize::from(buf_control & BUF_LENGTH);
+        if buf_control & BUF_FULL == 0 {
+            return Err(HostError::Timeout);
+        }
+        if len > buf.len() {
+            return Err(HostError::Overflow);
+        }
+        buf[..len].copy_from_slice(&epx_buffer()[..len]);
+        pipe.data1 = !pipe.data1;
+        Ok(len)
+    }
+
+    fn transfer_out(&mut self, pipe: &mut Pipe, data: &[u8]) -> Result<(), HostError> {
+        let endpoint_type = if pipe.endpoint == 0 {
+            0
+        } else {
+            pipe.transfer_type as u32
+        };
+        self.configure_epx(pipe, endpoint_type);
+        epx_buffer()[..data.len()].copy_from_slice(data);
+        self.arm_epx(pipe, data.len() as u16, true);
+        self.start_transaction(|w| w.send_data().set_bit());
+        self.wait_transaction(false)?;
+        pipe.data1 = !pipe.data1;
+        Ok(())
+    }
+
+    fn configure_epx(&mut self, pipe: &Pipe, endpoint_type: u32) {
+        self.ctrl_reg.addr_endp.write(|w| unsafe {
+            w.address().bits(pipe.address);
+            w.endpoint().bits(pipe.endpoint)
+        });
+        let epx_ctrl = EP_CTRL_ENABLE
+            | EP_CTRL_INTERRUPT_PER_BUFF
+            | (endpoint_type << EP_CTRL_TYPE_SHIFT)
+            | EPX_BUFFER_OFFSET as u32;
+        // Safety: EPX_CTRL is only used in host mode, and owned by us with the DPRAM
+        unsafe {
+            let dpram = USBCTRL_DPRAM::ptr() as *mut u8;
+            (dpram.add(EPX_CTRL_OFFSET) as *mut u32).write_volatile(epx_ctrl);
+        }
+    }
+
+    fn arm_epx(&mut self, pipe: &Pipe, len: u16, full: bool) {
+        let mut value = len | BUF_LAST;
+        if pipe.data1 {
+            value |= BUF_PID;
+        }
+        if full {
+            value |= BUF_FULL;
+        }
+        let buf_control = &self.ctrl_dpram.ep_buffer_control[0];
+        buf_control.write(|w| unsafe { w.bits(value.into()) });
+        cortex_m::asm::delay(12);
+        buf_control.write(|w| unsafe { w.bits((value | BUF_AVAILABLE).into()) });
+    }
+
+    fn start_transaction(
+        &mut self,
+        direction: impl Fn(&mut pac::usbctrl_regs::sie_ctrl::W) -> &mut pac::usbctrl_regs::sie_ctrl::W,
+    ) {
+        self.ctrl_reg
+            .sie_status
+            .write(|w| unsafe { w.bits(SIE_STATUS_FLAGS) });
+        self.ctrl_reg.sie_ctrl.modify(|_, w| direction(w));
+        // START_TRANS has to be set in a separate write, once the rest is settled
+        cortex_m::asm::delay(12);
+        self.ctrl_reg
+            .sie_ctrl
+            .modify(|_, w| w.start_trans().set_bit());
+    }
+
+    fn wait_transaction(&mut self, stop_on_nak: bool) -> Result<(), HostError> {
+        let mut naks = 0;
+        let result = loop {
+            let status = self.ctrl_reg.sie_status.read();
+            if status.trans_complete().bit_is_set() {
+                break Ok(());
+            } else if status.stall_rec().bit_is_set() {
+                break Err(HostError::Stall);
+            } else if status.rx_timeout().bit_is_set() {
+                break Err(HostError::Timeout);
+            } else if status.data_seq_error().bit_is_set() {
+                break Err(HostError::DataSequence);
+            } else if status.crc_error().bit_is_set() {
+                break Err(HostError::Crc);
+            } else if status.bit_stuff_error().bit_is_set() {
+                break Err(HostError::BitStuff);
+            } else if status.rx_overflow().bit_is_set() {
+                break Err(HostError::Overflow);
+            } else if status.speed().bits() == 0 {
+                break Err(HostError::Disconnected);
+            } else if status.nak_rec().bit_is_set() {
+                if stop_on_nak {
+                    break Err(HostError::Nak);
+                }
+                // The controller retries on its own, count the NAKs of a device not answering
+                self.ctrl_reg
+                    .sie_status
+                    .write(|w| unsafe { w.bits(SIE_STATUS_NAK_REC) });
+                naks += 1;
+                if naks == NAK_LIMIT {
+                    break Err(HostError::Timeout);
+                }
+            }
+        };
+
+        self.ctrl_reg.sie_ctrl.modify(|_, w| {
+            w.send_setup().clear_bit();
+            w.send_data().clear_bit();
+            w.receive_data().clear_bit();
+            if result.is_err() {
+                w.stop_trans().set_bit();
+            }
+            w
+        });
+        if result.is_err() {
+            // take the buffer back from the controller
+            self.ctrl_dpram.ep_buffer_control[0].write(|w| unsafe { w.bits(0) });
+        }
+        self.ctrl_reg
+            .sie_status
+            .write(|w| unsafe { w.bits(SIE_STATUS_FLAGS) });
+        result
+    }
+}
+
+fn epx_buffer() -> &'static mut [u8] {
+    // Safety: the EPX buffer is only accessed by the host while no transaction is in progress
+    unsafe {
+        core::slice::from_raw_parts_mut(
+            (USBCTRL_DPRAM::ptr() as *mut u8).add(EPX_BUFFER_OFFSET),
+            EPX_BUFFER_SIZE,
+        )
+    }
+}
+
+fn setup_packet(request_type: u8, request: u8, value: u16, index: u16, length: u16) -> [u8; 8] {
+    let [value_low, value_high] = value.to_le_bytes();
+    let [index_low, index_high] = index.to_le_bytes();
+    let [length_low, length_high] = length.to_le_bytes();
+    [
+        request_type,
+        request,
+        value_low,
+        value_high,
+        index_low,
+        index_high,
+        length_low,
+        length_high,
+    ]
+}
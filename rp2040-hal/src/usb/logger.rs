@@ -0,0 +1,188 @@
+//! Log output over USB serial
+//!
+//! Available with the `usb-logger` feature. The logger owns the USB device, with a single
+//! CDC-ACM serial port, and implements the [`log`] facade: log records are buffered in RAM and
+//! sent to the host from the USB interrupt, so the Pico's own USB port shows the log without a
+//! debug probe attached.
+//!
+//! ```no_run
+//! use rp2040_hal::{pac::{self, interrupt}, usb::{logger, UsbBus}};
+//! use usb_device::class_prelude::UsbBusAllocator;
+//! # use rp2040_hal::{clocks::init_clocks_and_plls, watchdog::Watchdog};
+//! # let mut pac = pac::Peripherals::take().unwrap();
+//! # let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! # let clocks = init_clocks_and_plls(12_000_000, pac.XOSC, pac.CLOCKS, pac.PLL_SYS, pac.PLL_USB, &mut pac.RESETS, &mut watchdog).ok().unwrap();
+//!
+//! let usb_bus = cortex_m::singleton!(: UsbBusAllocator<UsbBus> = UsbBusAllocator::new(UsbBus::new(
+//!     pac.USBCTRL_REGS,
+//!     pac.USBCTRL_DPRAM,
+//!     clocks.usb_clock,
+//!     true,
+//!     &mut pac.RESETS,
+//! ))).unwrap();
+//! logger::init(usb_bus, log::LevelFilter::Info).unwrap();
+//! unsafe {
+//!     pac::NVIC::unmask(pac::Interrupt::USBCTRL_IRQ);
+//! }
+//!
+//! log::info!("Hello from the Pico");
+//!
+//! #[interrupt]
+//! fn USBCTRL_IRQ() {
+//!     logger::on_interrupt();
+//! }
+//! ```
+//!
+//! Records logged while the buffer is full, or while no terminal is open on the host, are
+//! dropped rather than blocking the caller.
+//!
+//! For `defmt`, a `#[defmt::global_logger]` implemented by the application can pass the
+//! encoded frames to [`write`], to be decoded by `defmt-print` on the host.
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+use critical_section::Mutex;
+use usb_device::{class_prelude::UsbBusAllocator, prelude::*};
+use usbd_serial::SerialPort;
+
+use super::UsbBus;
+
+const BUFFER_SIZE: usize = 1024;
+
+struct Buffer {
+    data: [u8; BUFFER_SIZE],
+    start: usize,
+    len: usize,
+}
+
+impl Buffer {
+    /// Append as much of `bytes` as fits
+    fn push(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().take(BUFFER_SIZE - self.len) {
+            self.data[(self.start + self.len) % BUFFER_SIZE] = byte;
+            self.len += 1;
+        }
+    }
+
+    /// The oldest bytes of the buffer, which are stored contiguously
+    fn front(&self) -> &[u8] {
+        let end = (self.start + self.len).min(BUFFER_SIZE);
+        &self.data[self.start..end]
+    }
+
+    fn pop(&mut self, count: usize) {
+        self.start = (self.start + count) % BUFFER_SIZE;
+        self.len -= count;
+    }
+}
+
+impl Write for Buffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+struct Device {
+    device: UsbDevice<'static, UsbBus>,
+    serial: SerialPort<'static, UsbBus>,
+}
+
+// Safety: the device is only accessed within a critical section
+unsafe impl Send for Device {}
+
+static BUFFER: Mutex<RefCell<Buffer>> = Mutex::new(RefCell::new(Buffer {
+    data: [0; BUFFER_SIZE],
+    start: 0,
+    len: 0,
+}));
+static DEVICE: Mutex<RefCell<Option<Device>>> = Mutex::new(RefCell::new(None));
+
+struct UsbLogger;
+
+static LOGGER: UsbLogger = UsbLogger;
+
+impl log::Log for UsbLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            critical_section::with(|cs| {
+                let mut buffer = BUFFER.borrow(cs).borrow_mut();
+                let _ = write!(buffer, "[{}] {}\r\n", record.level(), record.args());
+            });
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Take over the USB bus, and install the logger for the `log` crate
+///
+/// This creates the USB device with a CDC-ACM serial port, using the example vendor and
+/// product IDs 16c0:27dd. It fails if a logger was already installed.
+pub fn init(
+    usb_bus: &'static UsbBusAllocator<UsbBus>,
+    level: log::LevelFilter,
+) -> Result<(), log::SetLoggerError> {
+    critical_section::with(|cs| {
+        let serial = SerialPort::new(usb_bus);
+        let device = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+            .manufacturer("rp2040-hal")
+            .product("Log")
+            .serial_number("0")
+            .device_class(usbd_serial::USB_CLASS_CDC)
+            .build();
+        DEVICE.borrow(cs).replace(Some(Device { device, serial }));
+
+        // Safety: both cores are in the critical section, so nothing logs concurrently
+        unsafe { log::set_logger_racy(&LOGGER)? };
+        log::set_max_level(level);
+        Ok(())
+    })
+}
+
+/// Append raw bytes to the log buffer, e.g. the frames of a `defmt` global logger
+pub fn write(bytes: &[u8]) {
+    critical_section::with(|cs| BUFFER.borrow(cs).borrow_mut().push(bytes));
+}
+
+/// Poll the USB device and send the buffered log to the host
+///
+/// This needs to be called from the USBCTRL_IRQ interrupt handler. Calling it periodically
+/// as well, e.g. from a timer interrupt, sends records logged while the bus is idle.
+pub fn on_interrupt() {
+    critical_section::with(|cs| {
+        let mut device = DEVICE.borrow(cs).borrow_mut();
+        let Device { device, serial } = match device.as_mut() {
+            Some(device) => device,
+            None => return,
+        };
+        device.poll(&mut [&mut *serial]);
+
+        // Discard what the host sends
+        let mut discard = [0; 64];
+        while let Ok(count) = serial.read(&mut discard) {
+            if count == 0 {
+                break;
+            }
+        }
+
+        let mut buffer = BUFFER.borrow(cs).borrow_mut();
+        if device.state() != UsbDeviceState::Configured || !serial.dtr() {
+            // Nobody is listening
+            let len = buffer.len;
+            buffer.pop(len);
+            return;
+        }
+        while buffer.len > 0 {
+            match serial.write(buffer.front()) {
+                Ok(count) if count > 0 => buffer.pop(count),
+                _ => break,
+            }
+        }
+    })
+}
@@ -0,0 +1,111 @@
+//! Reset interface compatible with picotool
+//!
+//! The pico-sdk adds a vendor interface to USB devices using `pico_stdio_usb`, through which
+//! `picotool` reboots the device, into the BOOTSEL mode to load new firmware, or back into the
+//! application. [`ResetInterface`] implements the same interface as a class of `usb-device`,
+//! so that `picotool reboot` and `picotool load -x`, or any tool relying on it, work with
+//! Rust firmware as well:
+//!
+//! ```no_run
+//! use rp2040_hal::usb::{reset_interface::ResetInterface, UsbBus};
+//! use usb_device::{class_prelude::UsbBusAllocator, prelude::*};
+//!
+//! fn run(usb_bus: &UsbBusAllocator<UsbBus>) -> ! {
+//!     let mut reset_interface = ResetInterface::new(usb_bus);
+//!     let mut usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+//!         .product("Device with a reset interface")
+//!         .build();
+//!     loop {
+//!         usb_dev.poll(&mut [&mut reset_interface]);
+//!     }
+//! }
+//! ```
+//!
+//! picotool only looks for the interface on devices with the Raspberry Pi vendor ID 0x2e8a.
+//! Unlike the pico-sdk, no Microsoft OS descriptors are provided, so on Windows a driver
+//! such as WinUSB has to be assigned to the interface manually.
+
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, RequestType};
+
+use crate::pac;
+use crate::rom_data;
+use crate::watchdog::Watchdog;
+
+const RESET_INTERFACE_SUBCLASS: u8 = 0x00;
+const RESET_INTERFACE_PROTOCOL: u8 = 0x01;
+
+const RESET_REQUEST_BOOTSEL: u8 = 0x01;
+const RESET_REQUEST_FLASH: u8 = 0x02;
+
+/// The reset interface of the pico-sdk, see the [module level documentation](self)
+pub struct ResetInterface {
+    interface: InterfaceNumber,
+    reboot_pending: bool,
+}
+
+impl ResetInterface {
+    /// Allocate the interface on the bus
+    pub fn new<B: UsbBus>(alloc: &UsbBusAllocator<B>) -> Self {
+        Self {
+            interface: alloc.interface(),
+            reboot_pending: false,
+        }
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for ResetInterface {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
+        writer.interface(
+            self.interface,
+            0xff,
+            RESET_INTERFACE_SUBCLASS,
+            RESET_INTERFACE_PROTOCOL,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.reboot_pending = false;
+    }
+
+    fn poll(&mut self) {
+        // The status stage of the request was sent by now
+        if self.reboot_pending {
+            // Safety: the chip is reset right away
+            let mut watchdog = Watchdog::new(unsafe { pac::Peripherals::steal().WATCHDOG });
+            watchdog.reboot();
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let request = xfer.request();
+        if request.request_type != RequestType::Class
+            || request.recipient != Recipient::Interface
+            || request.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        match request.request {
+            RESET_REQUEST_BOOTSEL => {
+                // As in the pico-sdk: the interfaces to disable in the low bits, and the GPIO
+                // to use as activity LED in the high bits if bit 8 is set
+                let disable_interface_mask = u32::from(request.value & 0x7f);
+                let gpio_activity_pin_mask = if request.value & 0x100 != 0 {
+                    1 << ((request.value >> 9) & 0x1f)
+                } else {
+                    0
+                };
+                let _ = xfer.accept();
+                rom_data::reset_to_usb_boot(gpio_activity_pin_mask, disable_interface_mask);
+            }
+            RESET_REQUEST_FLASH => {
+                let _ = xfer.accept();
+                self.reboot_pending = true;
+            }
+            _ => {
+                let _ = xfer.reject();
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! On-chip voltage regulator (VREG)
+//!
+//! The regulator supplies the digital core, at 1.10V by default. Raising the voltage allows
+//! running clk_sys faster than its nominal 133MHz, see
+//! [`ClocksManager::overclock_to`](crate::clocks::ClocksManager::overclock_to).
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, vreg::{self, Voltage}};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! vreg::set_voltage(&mut peripherals.VREG_AND_CHIP_RESET, Voltage::V1_15);
+//! while !vreg::is_regulated(&peripherals.VREG_AND_CHIP_RESET) {}
+//! assert_eq!(vreg::get_voltage(&peripherals.VREG_AND_CHIP_RESET), Some(Voltage::V1_15));
+//! ```
+//!
+//! Lowering the voltage saves power in designs running clk_sys well below its nominal
+//! frequency. The chip is only characterised at the default voltage, so the margin of an
+//! undervolted design, as of an overclocked one, has to be checked on the actual hardware and
+//! at the extremes of its temperature range.
+//!
+//! ## Brownout detection
+//!
+//! The brownout detector resets the chip when the core supply drops below a threshold, 0.86V
+//! by default, so that it doesn't run with an unreliable supply:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, vreg::{self, BrownoutThreshold}};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! vreg::set_brownout_detection(&mut peripherals.VREG_AND_CHIP_RESET, Some(BrownoutThreshold::V0_946));
+//! ```
+//!
+//! A brownout resets the chip like the power-on reset, and the hardware doesn't tell them
+//! apart: both are reported as [`ResetReason::PowerOn`](crate::watchdog::ResetReason::PowerOn).
+//! To not loop through resets as a battery runs down, firmware can check the supply, e.g. with
+//! the ADC, after such a reset and before turning on heavy loads.
+//!
+//! See [Chapter 2 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use pac::VREG_AND_CHIP_RESET;
+
+/// Output voltage of the regulator
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Voltage {
+    /// 0.85V
+    V0_85 = 0b0110,
+    /// 0.90V
+    V0_90 = 0b0111,
+    /// 0.95V
+    V0_95 = 0b1000,
+    /// 1.00V
+    V1_00 = 0b1001,
+    /// 1.05V
+    V1_05 = 0b1010,
+    /// 1.10V, the default
+    V1_10 = 0b1011,
+    /// 1.15V
+    V1_15 = 0b1100,
+    /// 1.20V
+    V1_20 = 0b1101,
+    /// 1.25V
+    V1_25 = 0b1110,
+    /// 1.30V
+    V1_30 = 0b1111,
+}
+
+/// Supply voltage below which the brownout detector resets the chip
+///
+/// The thresholds have a tolerance of about 3%.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BrownoutThreshold {
+    /// 0.473V
+    V0_473 = 0b0000,
+    /// 0.516V
+    V0_516 = 0b0001,
+    /// 0.559V
+    V0_559 = 0b0010,
+    /// 0.602V
+    V0_602 = 0b0011,
+    /// 0.645V
+    V0_645 = 0b0100,
+    /// 0.688V
+    V0_688 = 0b0101,
+    /// 0.731V
+    V0_731 = 0b0110,
+    /// 0.774V
+    V0_774 = 0b0111,
+    /// 0.817V
+    V0_817 = 0b1000,
+    /// 0.860V, the default
+    V0_860 = 0b1001,
+    /// 0.903V
+    V0_903 = 0b1010,
+    /// 0.946V
+    V0_946 = 0b1011,
+    /// 0.989V
+    V0_989 = 0b1100,
+    /// 1.032V
+    V1_032 = 0b1101,
+    /// 1.075V
+    V1_075 = 0b1110,
+    /// 1.118V
+    V1_118 = 0b1111,
+}
+
+impl BrownoutThreshold {
+    /// Returns the threshold in millivolts
+    pub fn millivolts(self) -> u16 {
+        473 + 43 * u16::from(self as u8)
+    }
+}
+
+impl Voltage {
+    /// Returns the voltage in millivolts
+    pub fn millivolts(self) -> u16 {
+        850 + 50 * u16::from(self as u8 - Voltage::V0_85 as u8)
+    }
+
+    fn from_vsel(vsel: u8) -> Option<Self> {
+        Some(match vsel {
+            0b0110 => Voltage::V0_85,
+            0b0111 => Voltage::V0_90,
+            0b1000 => Voltage::V0_95,
+            0b1001 => Voltage::V1_00,
+            0b1010 => Voltage::V1_05,
+            0b1011 => Voltage::V1_10,
+            0b1100 => Voltage::V1_15,
+            0b1101 => Voltage::V1_20,
+            0b1110 => Voltage::V1_25,
+            0b1111 => Voltage::V1_30,
+            _ => return None,
+        })
+    }
+}
+
+/// Returns the output voltage the regulator is set to
+///
+/// This is `None` if it was set below 0.85V, to the 0.80V of the lowest settings.
+pub fn get_voltage(vreg: &VREG_AND_CHIP_RESET) -> Option<Voltage> {
+    Voltage::from_vsel(vreg.vreg.read().vsel().bits())
+}
+
+/// Returns true if the regulator output is in regulation
+///
+/// This is false while the output settles after a change, or if the load on it is too high.
+pub fn is_regulated(vreg: &VREG_AND_CHIP_RESET) -> bool {
+    vreg.vreg.read().rok().bit_is_set()
+}
+
+/// Put the regulator in high impedance mode, or back in normal mode
+///
+/// In high impedance mode, the output draws almost no current and the core supply has to be
+/// provided externally on DVDD.
+pub fn set_high_impedance(vreg: &mut VREG_AND_CHIP_RESET, high_impedance: bool) {
+    vreg.vreg.modify(|_, w| w.hiz().bit(high_impedance));
+}
+
+/// Enable the brownout detector with a threshold, or disable it with `None`
+///
+/// The threshold has to be below the output voltage of the regulator, or the chip resets right
+/// away.
+pub fn set_brownout_detection(
+    vreg: &mut VREG_AND_CHIP_RESET,
+    threshold: Option<BrownoutThreshold>,
+) {
+    vreg.bod.modify(|_, w| match threshold {
+        Some(threshold) => unsafe { w.vsel().bits(threshold as u8).en().set_bit() },
+        None => w.en().clear_bit(),
+    });
+}
+
+/// Returns the threshold of the brownout detector, or `None` if it's disabled
+pub fn brownout_detection(vreg: &VREG_AND_CHIP_RESET) -> Option<BrownoutThreshold> {
+    use BrownoutThreshold::*;
+    const THRESHOLDS: [BrownoutThreshold; 16] = [
+        V0_473, V0_516, V0_559, V0_602, V0_645, V0_688, V0_731, V0_774, V0_817, V0_860, V0_903,
+        V0_946, V0_989, V1_032, V1_075, V1_118,
+    ];
+    let bod = vreg.bod.read();
+    if bod.en().bit_is_set() {
+        Some(THRESHOLDS[usize::from(bod.vsel().bits())])
+    } else {
+        None
+    }
+}
+
+/// Set the output voltage of the regulator
+///
+/// The new voltage takes a moment to settle: wait for [`is_regulated`] before running clk_sys
+/// faster.
+pub fn set_voltage(vreg: &mut VREG_AND_CHIP_RESET, voltage: Voltage) {
+    vreg.vreg
+        .modify(|_, w| unsafe { w.vsel().bits(voltage as u8) });
+}
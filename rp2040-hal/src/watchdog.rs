@@ -33,10 +33,108 @@
 //! loop {}
 //! ```
 //! See [examples/watchdog.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/watchdog.rs) for a more complete example
+//!
+//! ## Scratch registers
+//!
+//! The [scratch registers](ScratchRegister) survive a watchdog reset:
+//! ```no_run
+//! use rp2040_hal::{pac, watchdog::{ScratchRegister, Watchdog}};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! let crashes = watchdog.read_scratch(ScratchRegister::Scratch0);
+//! watchdog.write_scratch(ScratchRegister::Scratch0, crashes + 1);
+//! ```
+//!
+//! ## Watchdog service
+//!
+//! Feeding the watchdog from a timer interrupt keeps it fed even when the main loop is stuck.
+//! [`WatchdogService`] feeds it from an alarm interrupt, but only if the main loop checked in
+//! with a [`Heartbeat`] recently:
+//! ```ignore
+//! static HEARTBEAT: Heartbeat = Heartbeat::new();
+//! let service = WatchdogService::new(watchdog, alarm, &HEARTBEAT, 1.secs(), 200.millis()).unwrap();
+//! // Move the service to the TIMER_IRQ_0 handler, which calls service.on_interrupt()
+//! loop {
+//!     HEARTBEAT.check_in();
+//!     // do the actual work, each iteration has to be shorter than 200ms
+//! }
+//! ```
+//!
+//! ## Tick generation
+//!
+//! The watchdog also generates the 1µs tick that drives the watchdog counter and the
+//! [`Timer`](crate::timer::Timer). The tick is derived from clk_ref, so whenever clk_ref is
+//! reconfigured, the tick has to be reconfigured for the new frequency, otherwise all timer
+//! based delays are off:
+//! ```no_run
+//! use fugit::RateExtU32;
+//! use rp2040_hal::{pac, watchdog::Watchdog};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! // clk_ref runs from a 12MHz crystal
+//! watchdog.set_tick_source_freq(12.MHz()).unwrap();
+//! assert!(watchdog.is_tick_running());
+//! ```
 
 use crate::pac::WATCHDOG;
+use crate::timer::{Alarm, ScheduleAlarmError};
+use core::sync::atomic::{AtomicU32, Ordering};
 use embedded_hal::watchdog;
-use fugit::MicrosDurationU32;
+use fugit::{HertzU32, MicrosDurationU32};
+
+/// Error returned by [`Watchdog::set_tick_source_freq`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TickError {
+    /// A 1µs tick can only be generated from a whole, non-zero number of MHz
+    FrequencyNotWholeMegahertz,
+    /// The frequency is above the 511MHz the tick divider supports
+    FrequencyTooHigh,
+}
+
+/// One of the watchdog's scratch registers
+///
+/// The scratch registers keep their value through a watchdog reset and a soft reset, so they
+/// can be used to pass small amounts of state to the next boot, like the reason for a reboot.
+/// They are cleared on power-on, brownout and by the RUN pin.
+///
+/// The bootrom uses [`Scratch4`](ScratchRegister::Scratch4) to
+/// [`Scratch7`](ScratchRegister::Scratch7): if `Scratch4` contains the magic value `0xb007c0d3`
+/// after a watchdog reset, the bootrom jumps to the entry point stored in `Scratch5` to
+/// `Scratch7` instead of booting normally. Prefer `Scratch0` to `Scratch3` for other uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum ScratchRegister {
+    Scratch0,
+    Scratch1,
+    Scratch2,
+    Scratch3,
+    Scratch4,
+    Scratch5,
+    Scratch6,
+    Scratch7,
+}
+
+/// Cause of the last reset, returned by [`Watchdog::reset_reason`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetReason {
+    /// Power-on reset, or a reset by the brownout detector
+    ///
+    /// The hardware doesn't distinguish a brownout from a cold boot.
+    PowerOn,
+    /// The RUN pin was pulled low
+    RunPin,
+    /// A debugger reset the chip through the rescue or debug port
+    Debugger,
+    /// The watchdog timer was not fed in time
+    WatchdogTimeout,
+    /// Software triggered a reset through the watchdog
+    WatchdogForced,
+    /// None of the reset sources is flagged
+    Unknown,
+}
 
 /// Watchdog peripheral
 pub struct Watchdog {
@@ -59,6 +157,9 @@ impl Watchdog {
     ///
     /// * `cycles` - Total number of tick cycles before the next tick is generated.
     ///   It is expected to be the frequency in MHz of clk_ref.
+    ///
+    /// See [`set_tick_source_freq`](Self::set_tick_source_freq) to configure the tick from the
+    /// frequency of clk_ref instead.
     pub fn enable_tick_generation(&mut self, cycles: u8) {
         const WATCHDOG_TICK_ENABLE_BITS: u32 = 0x200;
 
@@ -67,6 +168,160 @@ impl Watchdog {
             .write(|w| unsafe { w.bits(WATCHDOG_TICK_ENABLE_BITS | cycles as u32) })
     }
 
+    /// Generate a 1µs tick from clk_ref running at `clk_ref_freq`.
+    ///
+    /// This needs to be called again whenever the frequency of clk_ref changes.
+    pub fn set_tick_source_freq(&mut self, clk_ref_freq: HertzU32) -> Result<(), TickError> {
+        const MHZ: u32 = 1_000_000;
+        const MAX_CYCLES: u32 = 0x1ff;
+
+        let hz = clk_ref_freq.to_Hz();
+        let cycles = hz / MHZ;
+        if cycles == 0 || hz % MHZ != 0 {
+            return Err(TickError::FrequencyNotWholeMegahertz);
+        }
+        if cycles > MAX_CYCLES {
+            return Err(TickError::FrequencyTooHigh);
+        }
+
+        self.watchdog
+            .tick
+            .write(|w| unsafe { w.cycles().bits(cycles as u16).enable().set_bit() });
+        Ok(())
+    }
+
+    /// Stop tick generation.
+    ///
+    /// This stops the watchdog counter and the [`Timer`](crate::timer::Timer).
+    pub fn disable_tick_generation(&mut self) {
+        self.watchdog.tick.modify(|_, w| w.enable().clear_bit())
+    }
+
+    /// Returns true if ticks are being generated.
+    pub fn is_tick_running(&self) -> bool {
+        self.watchdog.tick.read().running().bit_is_set()
+    }
+
+    /// Number of clk_ref cycles per tick.
+    pub fn tick_cycles(&self) -> u16 {
+        self.watchdog.tick.read().cycles().bits()
+    }
+
+    /// Cause of the last reset.
+    ///
+    /// Watchdog resets take precedence, as the chip level reset flags are only updated by chip
+    /// level resets.
+    pub fn reset_reason(&self) -> ResetReason {
+        let reason = self.watchdog.reason.read();
+        if reason.timer().bit_is_set() {
+            return ResetReason::WatchdogTimeout;
+        }
+        if reason.force().bit_is_set() {
+            return ResetReason::WatchdogForced;
+        }
+
+        // Safety: read-only access to a status register
+        let chip_reset = unsafe { &*pac::VREG_AND_CHIP_RESET::ptr() }
+            .chip_reset
+            .read();
+        if chip_reset.had_psm_restart().bit_is_set() {
+            ResetReason::Debugger
+        } else if chip_reset.had_run().bit_is_set() {
+            ResetReason::RunPin
+        } else if chip_reset.had_por().bit_is_set() {
+            ResetReason::PowerOn
+        } else {
+            ResetReason::Unknown
+        }
+    }
+
+    /// Read one of the scratch registers.
+    pub fn read_scratch(&self, reg: ScratchRegister) -> u32 {
+        let watchdog = &self.watchdog;
+        match reg {
+            ScratchRegister::Scratch0 => watchdog.scratch0.read().bits(),
+            ScratchRegister::Scratch1 => watchdog.scratch1.read().bits(),
+            ScratchRegister::Scratch2 => watchdog.scratch2.read().bits(),
+            ScratchRegister::Scratch3 => watchdog.scratch3.read().bits(),
+            ScratchRegister::Scratch4 => watchdog.scratch4.read().bits(),
+            ScratchRegister::Scratch5 => watchdog.scratch5.read().bits(),
+            ScratchRegister::Scratch6 => watchdog.scratch6.read().bits(),
+            ScratchRegister::Scratch7 => watchdog.scratch7.read().bits(),
+        }
+    }
+
+    /// Write one of the scratch registers.
+    pub fn write_scratch(&mut self, reg: ScratchRegister, value: u32) {
+        let watchdog = &self.watchdog;
+        // Safety: the scratch registers are plain storage, any value is valid
+        unsafe {
+            match reg {
+                ScratchRegister::Scratch0 => watchdog.scratch0.write(|w| w.bits(value)),
+                ScratchRegister::Scratch1 => watchdog.scratch1.write(|w| w.bits(value)),
+                ScratchRegister::Scratch2 => watchdog.scratch2.write(|w| w.bits(value)),
+                ScratchRegister::Scratch3 => watchdog.scratch3.write(|w| w.bits(value)),
+                ScratchRegister::Scratch4 => watchdog.scratch4.write(|w| w.bits(value)),
+                ScratchRegister::Scratch5 => watchdog.scratch5.write(|w| w.bits(value)),
+                ScratchRegister::Scratch6 => watchdog.scratch6.write(|w| w.bits(value)),
+                ScratchRegister::Scratch7 => watchdog.scratch7.write(|w| w.bits(value)),
+            }
+        }
+    }
+
+    /// Reset the chip through the watchdog, booting normally.
+    pub fn reboot(&mut self) -> ! {
+        self.write_scratch(ScratchRegister::Scratch4, 0);
+        self.trigger_reset()
+    }
+
+    /// Reset the chip through the watchdog, making the bootrom jump to `entry` with the stack
+    /// pointer set to `stack_pointer`, instead of booting from flash.
+    ///
+    /// This uses the protocol of the bootrom: the entry point, stack pointer and a magic value are
+    /// stored in scratch registers 4 to 7 before the reset.
+    ///
+    /// # Safety
+    ///
+    /// `entry` has to be the address of valid code (the Thumb bit is set here), and
+    /// `stack_pointer` has to point to the top of a valid stack. Both have to survive the reset,
+    /// e.g. code in flash or in RAM which is not overwritten by the boot sequence.
+    pub unsafe fn reboot_to(&mut self, entry: u32, stack_pointer: u32) -> ! {
+        const BOOT_MAGIC: u32 = 0xb007c0d3;
+        let entry = entry | 1;
+        self.write_scratch(ScratchRegister::Scratch4, BOOT_MAGIC);
+        self.write_scratch(ScratchRegister::Scratch5, entry ^ BOOT_MAGIC.wrapping_neg());
+        self.write_scratch(ScratchRegister::Scratch6, stack_pointer);
+        self.write_scratch(ScratchRegister::Scratch7, entry);
+        self.trigger_reset()
+    }
+
+    /// Reset the chip through the watchdog, then start the application whose vector table is at
+    /// `vector_table`.
+    ///
+    /// The initial stack pointer and the reset vector are read from the first two entries of the
+    /// table. Note that the table is not installed in VTOR, the application's reset handler has
+    /// to do that.
+    ///
+    /// # Safety
+    ///
+    /// `vector_table` has to point to a valid vector table, see [`reboot_to`](Self::reboot_to).
+    pub unsafe fn reboot_to_vector_table(&mut self, vector_table: *const u32) -> ! {
+        let stack_pointer = vector_table.read_volatile();
+        let entry = vector_table.add(1).read_volatile();
+        self.reboot_to(entry, stack_pointer)
+    }
+
+    fn trigger_reset(&mut self) -> ! {
+        // Safety: nothing else uses PSM
+        unsafe {
+            self.configure_wdog_reset_triggers();
+        }
+        self.watchdog.ctrl.write(|w| w.trigger().set_bit());
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
     /// Defines whether or not the watchdog timer should be paused when processor(s) are in debug mode
     /// or when JTAG is accessing bus fabric
     ///
@@ -74,13 +329,22 @@ impl Watchdog {
     ///
     /// * `pause` - If true, watchdog timer will be paused
     pub fn pause_on_debug(&mut self, pause: bool) {
-        self.watchdog.ctrl.write(|w| {
+        self.set_debug_pause(pause, pause, pause)
+    }
+
+    /// Choose individually whether the watchdog timer is paused while core 0 or core 1 is halted
+    /// by a debugger, or while the JTAG interface accesses the bus fabric.
+    ///
+    /// All three are enabled after reset. The setting is kept when the watchdog is started or
+    /// stopped.
+    pub fn set_debug_pause(&mut self, core0: bool, core1: bool, jtag: bool) {
+        self.watchdog.ctrl.modify(|_, w| {
             w.pause_dbg0()
-                .bit(pause)
+                .bit(core0)
                 .pause_dbg1()
-                .bit(pause)
+                .bit(core1)
                 .pause_jtag()
-                .bit(pause)
+                .bit(jtag)
         })
     }
 
@@ -89,7 +353,8 @@ impl Watchdog {
     }
 
     fn enable(&self, bit: bool) {
-        self.watchdog.ctrl.write(|w| w.enable().bit(bit))
+        // Modify to keep the pause settings
+        self.watchdog.ctrl.modify(|_, w| w.enable().bit(bit))
     }
 
     /// Configure which hardware will be reset by the watchdog
@@ -142,3 +407,102 @@ impl watchdog::WatchdogDisable for Watchdog {
         self.enable(false)
     }
 }
+
+fn timer_low() -> u32 {
+    // Safety: read-only access to the counter
+    unsafe { &*pac::TIMER::ptr() }.timerawl.read().bits()
+}
+
+/// Check-in point for the main loop, used by [`WatchdogService`]
+pub struct Heartbeat {
+    last_check_in: AtomicU32,
+}
+
+impl Heartbeat {
+    /// Create a new heartbeat, usually stored in a `static`
+    pub const fn new() -> Self {
+        Self {
+            last_check_in: AtomicU32::new(0),
+        }
+    }
+
+    /// Signal that the main loop is still running
+    pub fn check_in(&self) {
+        self.last_check_in.store(timer_low(), Ordering::Relaxed);
+    }
+
+    fn elapsed_micros(&self) -> u32 {
+        timer_low().wrapping_sub(self.last_check_in.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Watchdog fed from a timer alarm, as long as the main loop checks in
+///
+/// The alarm interrupt feeds the watchdog every quarter of its timeout, but only if the
+/// [`Heartbeat`] was checked in within the last `window`. If the main loop stops checking in,
+/// the watchdog is no longer fed and resets the chip, even though interrupts keep running.
+///
+/// The service relies on the [`Timer`](crate::timer::Timer) running.
+pub struct WatchdogService<A: Alarm> {
+    watchdog: Watchdog,
+    alarm: A,
+    heartbeat: &'static Heartbeat,
+    feed_interval: MicrosDurationU32,
+    window: MicrosDurationU32,
+}
+
+impl<A: Alarm> WatchdogService<A> {
+    /// Start the watchdog with `timeout`, and the alarm feeding it
+    ///
+    /// The interrupt of `alarm` is enabled, its handler has to call
+    /// [`on_interrupt`](Self::on_interrupt). The main loop has to check in with `heartbeat`
+    /// at least once per `window`.
+    pub fn new(
+        mut watchdog: Watchdog,
+        mut alarm: A,
+        heartbeat: &'static Heartbeat,
+        timeout: MicrosDurationU32,
+        window: MicrosDurationU32,
+    ) -> Result<Self, ScheduleAlarmError> {
+        let feed_interval = MicrosDurationU32::from_ticks(timeout.ticks() / 4);
+        heartbeat.check_in();
+        alarm.schedule(feed_interval)?;
+        alarm.enable_interrupt();
+        watchdog::WatchdogEnable::start(&mut watchdog, timeout);
+        Ok(Self {
+            watchdog,
+            alarm,
+            heartbeat,
+            feed_interval,
+            window,
+        })
+    }
+
+    /// Feed the watchdog if the main loop checked in, and schedule the next feed
+    ///
+    /// Call this from the interrupt handler of the alarm. Returns true if the watchdog was fed.
+    pub fn on_interrupt(&mut self) -> bool {
+        self.alarm.clear_interrupt();
+        let alive = self.heartbeat.elapsed_micros() <= self.window.ticks();
+        if alive {
+            watchdog::Watchdog::feed(&mut self.watchdog);
+        }
+        // The interval is at least a few ms in practice, it can't be too soon
+        let _ = self.alarm.schedule(self.feed_interval);
+        alive
+    }
+
+    /// Stop the watchdog and the alarm, and return them
+    pub fn free(mut self) -> (Watchdog, A) {
+        watchdog::WatchdogDisable::disable(&mut self.watchdog);
+        self.alarm.disable_interrupt();
+        self.alarm.cancel();
+        (self.watchdog, self.alarm)
+    }
+}
@@ -0,0 +1,258 @@
+//! Execute-in-place (XIP) cache
+//!
+//! Reads from the external flash go through a 16kB cache, which can be flushed, disabled or
+//! powered down. Its performance counters count the accesses to the cached XIP window and the
+//! hits among them, to measure how well the cache holds the code and data in use:
+//!
+//! ```no_run
+//! use rp2040_hal::{pac, xip::XipCache};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let mut cache = XipCache::new(pac.XIP_CTRL);
+//! cache.clear_counters();
+//! // run the code to measure
+//! let hit_rate = cache.hits() as f32 / cache.accesses() as f32;
+//! ```
+//!
+//! Critical code can be [pinned](XipCache::pin) in the cache, so it's never evicted by other
+//! reads and always runs at the speed of the cache. Once the cache is disabled, it can instead
+//! be used as [16kB of extra SRAM](XipCache::into_sram), at the cost of every read from flash
+//! going to the chip.
+//!
+//! ## Streaming
+//!
+//! Large assets such as images or audio can be read from flash by DMA, through the stream
+//! FIFO of the XIP interface: it bypasses the cache, so the reads don't evict the code in use,
+//! and the flash interface is shared fairly with the code running from flash.
+//!
+//! ```no_run
+//! use rp2040_hal::{dma::DMAExt, pac, xip::XipCache};
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let dma = pac.DMA.split(&mut pac.RESETS);
+//! let cache = XipCache::new(pac.XIP_CTRL);
+//!
+//! static mut BUFFER: [u32; 1024] = [0; 1024];
+//! // Safety: the buffer is only used by the stream
+//! let buffer = unsafe { &mut BUFFER };
+//! // Read 4kB from 1MB into the flash
+//! let stream = cache.stream(dma.ch0, 0x10_0000, buffer).unwrap();
+//! let (cache, ch0, buffer) = stream.wait();
+//! ```
+//!
+//! To feed a peripheral, the stream can fill one buffer while another DMA channel writes the
+//! previous one to the peripheral, paced by its DREQ.
+//!
+//! See [Chapter 2 Section 6.3](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+
+use crate::dma::{
+    Channel as DmaChannel, ChannelConfig, ChannelIndex, TransferSize, DREQ_XIP_STREAM,
+};
+use crate::flash::{Error, MAX_FLASH_SIZE, XIP_BASE};
+use crate::pac::XIP_CTRL;
+
+/// Address of the cache memory, when used as SRAM
+pub const XIP_SRAM_BASE: u32 = 0x1500_0000;
+/// Size of the cache, 16kB
+pub const CACHE_SIZE: usize = 16 * 1024;
+/// The cache is made of lines of 8 bytes
+const LINE_SIZE: u32 = 8;
+/// Alias of the stream FIFO on the fast bus, for DMA
+const XIP_AUX_BASE: u32 = 0x5040_0000;
+/// Largest number of words of a stream
+const MAX_STREAM_LEN: usize = (1 << 22) - 1;
+
+/// The XIP cache, see the [module level documentation](self)
+pub struct XipCache {
+    device: XIP_CTRL,
+}
+
+impl XipCache {
+    /// Take ownership of the XIP control registers
+    pub fn new(device: XIP_CTRL) -> Self {
+        Self { device }
+    }
+
+    /// Release the XIP control registers
+    pub fn free(self) -> XIP_CTRL {
+        self.device
+    }
+
+    /// Flush the cache, and wait for the flush to complete
+    ///
+    /// This invalidates all lines, and unpins those pinned.
+    pub fn flush(&mut self) {
+        // Safety: any value triggers a flush
+        self.device.flush.write(|w| unsafe { w.bits(1) });
+        // Reading stalls until the flush is done
+        let _ = self.device.flush.read();
+    }
+
+    /// Enable the cache
+    ///
+    /// This has no effect while the cache is powered down.
+    pub fn enable(&mut self) {
+        self.device.ctrl.modify(|_, w| w.en().set_bit());
+    }
+
+    /// Disable the cache
+    ///
+    /// Reads from the cached window then go straight to the flash, which is much slower. The
+    /// functions of the [`flash`](crate::flash) module flush the cache, but leave it disabled.
+    pub fn disable(&mut self) {
+        self.device.ctrl.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Returns true if the cache is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.device.ctrl.read().en().bit_is_set()
+    }
+
+    /// Power the cache memories down, or up again
+    ///
+    /// They keep their contents while powered down, but can't be accessed: this also disables
+    /// the cache, which has to be enabled again after powering up.
+    pub fn set_power_down(&mut self, power_down: bool) {
+        self.device
+            .ctrl
+            .modify(|_, w| w.power_down().bit(power_down));
+    }
+
+    /// Returns the number of cache hits since the counters were cleared
+    pub fn hits(&self) -> u32 {
+        self.device.ctr_hit.read().bits()
+    }
+
+    /// Returns the number of accesses to the cached window since the counters were cleared
+    ///
+    /// The counters don't wrap, they saturate.
+    pub fn accesses(&self) -> u32 {
+        self.device.ctr_acc.read().bits()
+    }
+
+    /// Clear the hit and access counters
+    pub fn clear_counters(&mut self) {
+        // Safety: any value clears the counters
+        self.device.ctr_hit.write(|w| unsafe { w.bits(0) });
+        self.device.ctr_acc.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Load `len` bytes of flash from `offset` into the cache, and pin them there
+    ///
+    /// Each line is read, and written back to the cached window, which pins it: pinned lines
+    /// aren't evicted, and keep their contents until the cache is flushed. Each set of the
+    /// cache has two lines, so pinning more than 8kB of contiguous flash leaves no room to
+    /// cache anything else. This has no effect while the cache is disabled.
+    ///
+    /// # Safety
+    ///
+    /// The range must not be erased or programmed while pinned, as the cache would keep
+    /// returning the old contents. The functions of the [`flash`](crate::flash) module flush
+    /// the cache, which unpins all lines.
+    pub unsafe fn pin(&mut self, offset: u32, len: u32) {
+        let start = offset & !(LINE_SIZE - 1);
+        let end = offset + len;
+        let mut address = (XIP_BASE + start) as *mut u32;
+        while (address as u32) < XIP_BASE + end {
+            address.write_volatile(address.read_volatile());
+            address = address.add(1);
+        }
+    }
+
+    /// Disable the cache, and use its memory as 16kB of SRAM
+    ///
+    /// The memory is mapped at [`XIP_SRAM_BASE`], and its initial contents are undefined. All
+    /// reads from flash are uncached from then on, so this suits programs which mostly run
+    /// from RAM. The functions of the [`flash`](crate::flash) module keep the cache disabled,
+    /// and its flush leaves the contents of the memory as they are.
+    pub fn into_sram(self) -> &'static mut [u32; CACHE_SIZE / 4] {
+        self.device
+            .ctrl
+            .modify(|_, w| w.en().clear_bit().power_down().clear_bit());
+        // Safety: the cache is disabled and powered, and self is consumed so the memory is only
+        // handed out once
+        unsafe { &mut *(XIP_SRAM_BASE as *mut [u32; CACHE_SIZE / 4]) }
+    }
+
+    /// Start streaming `buffer.len()` words of flash from `offset` into `buffer`, by DMA
+    ///
+    /// `offset` has to be a multiple of 4. The cache is given back, along with the channel and
+    /// the buffer, once the stream is done.
+    pub fn stream<CH: ChannelIndex>(
+        self,
+        mut channel: DmaChannel<CH>,
+        offset: u32,
+        buffer: &'static mut [u32],
+    ) -> Result<XipStream<CH>, Error> {
+        if offset % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+        if buffer.len() > MAX_STREAM_LEN
+            || offset as usize + buffer.len() * 4 > MAX_FLASH_SIZE as usize
+        {
+            return Err(Error::OutOfBounds);
+        }
+
+        // Stop any previous stream, and drop what it left in the FIFO
+        // Safety: 0 is a valid count
+        self.device.stream_ctr.write(|w| unsafe { w.bits(0) });
+        while self.device.stat.read().fifo_empty().bit_is_clear() {
+            let _ = self.device.stream_fifo.read();
+        }
+
+        let config = ChannelConfig {
+            size: TransferSize::Word,
+            incr_read: false,
+            incr_write: true,
+            treq: DREQ_XIP_STREAM,
+            chain_to: None,
+            read_ring: 0,
+        };
+        // Safety: the buffer is owned by the stream, and the FIFO is always valid
+        unsafe {
+            channel.configure(
+                &config,
+                XIP_AUX_BASE,
+                buffer.as_mut_ptr() as u32,
+                buffer.len() as u32,
+            );
+        }
+        channel.clear_complete();
+        channel.start();
+        // Safety: the address is in flash and aligned, and the count fits in the register
+        self.device
+            .stream_addr
+            .write(|w| unsafe { w.bits(XIP_BASE + offset) });
+        self.device
+            .stream_ctr
+            .write(|w| unsafe { w.bits(buffer.len() as u32) });
+
+        Ok(XipStream {
+            cache: self,
+            channel,
+            buffer,
+        })
+    }
+}
+
+/// A stream of flash into RAM in progress, started with [`XipCache::stream`]
+pub struct XipStream<CH: ChannelIndex> {
+    cache: XipCache,
+    channel: DmaChannel<CH>,
+    buffer: &'static mut [u32],
+}
+
+impl<CH: ChannelIndex> XipStream<CH> {
+    /// Returns true if the whole buffer was filled
+    pub fn is_done(&self) -> bool {
+        self.channel.is_complete()
+    }
+
+    /// Wait for the end of the stream, and return the cache, the channel and the buffer
+    pub fn wait(mut self) -> (XipCache, DmaChannel<CH>, &'static mut [u32]) {
+        while !self.is_done() {
+            cortex_m::asm::nop();
+        }
+        self.channel.clear_complete();
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        (self.cache, self.channel, self.buffer)
+    }
+}
@@ -2,6 +2,7 @@
 // See [Chapter 2 Section 16](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
 
 use core::convert::TryInto;
+use core::sync::atomic::Ordering;
 use core::{convert::Infallible, ops::RangeInclusive};
 
 use fugit::HertzU32;
@@ -32,24 +33,54 @@ impl State for Stable {}
 impl State for Dormant {}
 
 /// Possible errors when initializing the CrystalOscillator
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Frequency is out of the 1-15MHz range (see datasheet)
     FrequencyOutOfRange,
 
     /// Argument is bad : overflows, ...
     BadArgument,
+
+    /// The XOSC didn't become stable in time: no crystal is fitted, or it needs a longer
+    /// startup delay
+    Timeout,
 }
 
 /// Blocking helper method to setup the XOSC without going through all the steps.
+///
+/// Returns [`Error::Timeout`] if the XOSC isn't stable after 10 times its startup delay.
 pub fn setup_xosc_blocking(
     xosc_dev: rp2040_pac::XOSC,
     frequency: HertzU32,
 ) -> Result<CrystalOscillator<Stable>, Error> {
-    let initialized_xosc = CrystalOscillator::new(xosc_dev).initialize(frequency)?;
+    setup_xosc_blocking_with_delay_multiplier(xosc_dev, frequency, 1)
+}
+
+/// Same as [`setup_xosc_blocking`], with a startup delay `delay_multiplier` times longer
+///
+/// Slow starting crystals need a longer delay than the default of about 1ms, like the 64
+/// used by the C SDK on some boards. Returns [`Error::BadArgument`] if `delay_multiplier` is 0.
+pub fn setup_xosc_blocking_with_delay_multiplier(
+    xosc_dev: rp2040_pac::XOSC,
+    frequency: HertzU32,
+    delay_multiplier: u32,
+) -> Result<CrystalOscillator<Stable>, Error> {
+    // Poll every 100µs, with a tenfold margin over the startup delay of about 1ms
+    const POLLS_PER_DELAY: u32 = 10 * 10;
+
+    let initialized_xosc = CrystalOscillator::new(xosc_dev)
+        .initialize_with_delay_multiplier(frequency, delay_multiplier)?;
 
-    let stable_xosc_token = nb::block!(initialized_xosc.await_stabilization()).unwrap();
+    // clk_sys is tracked by the clocks, it runs from the ROSC at a lower frequency out of reset
+    let poll_cycles = crate::clocks::SYSTEM_CLOCK_FREQ.load(Ordering::Relaxed) / 10_000;
+    for _ in 0..POLLS_PER_DELAY.saturating_mul(delay_multiplier) {
+        if let Ok(token) = initialized_xosc.await_stabilization() {
+            return Ok(initialized_xosc.get_stable(token));
+        }
+        cortex_m::asm::delay(poll_cycles);
+    }
 
-    Ok(initialized_xosc.get_stable(stable_xosc_token))
+    Err(Error::Timeout)
 }
 
 /// A Crystal Oscillator.
@@ -84,6 +115,19 @@ impl CrystalOscillator<Disabled> {
 
     /// Initializes the XOSC : frequency range is set, startup delay is calculated and set.
     pub fn initialize(self, frequency: HertzU32) -> Result<CrystalOscillator<Initialized>, Error> {
+        self.initialize_with_delay_multiplier(frequency, 1)
+    }
+
+    /// Initializes the XOSC with a startup delay `delay_multiplier` times longer than the
+    /// default of about 1ms, for crystals which are slow to start.
+    ///
+    /// Returns [`Error::BadArgument`] if `delay_multiplier` is 0, as the XOSC could then be
+    /// reported stable before the crystal has settled.
+    pub fn initialize_with_delay_multiplier(
+        self,
+        frequency: HertzU32,
+        delay_multiplier: u32,
+    ) -> Result<CrystalOscillator<Initialized>, Error> {
         const ALLOWED_FREQUENCY_RANGE: RangeInclusive<HertzU32> =
             HertzU32::MHz(1)..=HertzU32::MHz(15);
         //1 ms = 10e-3 sec and Freq = 1/T where T is in seconds so 1ms converts to 1000Hz
@@ -93,6 +137,9 @@ impl CrystalOscillator<Disabled> {
         if !ALLOWED_FREQUENCY_RANGE.contains(&frequency) {
             return Err(Error::FrequencyOutOfRange);
         }
+        if delay_multiplier == 0 {
+            return Err(Error::BadArgument);
+        }
 
         self.device.ctrl.write(|w| {
             w.freq_range()._1_15mhz();
@@ -103,9 +150,14 @@ impl CrystalOscillator<Disabled> {
         //              = freq_hz / (delay_to_hz * 256)
         //See Chapter 2, Section 16, §3)
         //We do the calculation first.
-        let startup_delay = frequency.to_Hz() / (STABLE_DELAY_AS_HZ.to_Hz() * DIVIDER);
+        let startup_delay = (frequency.to_Hz() / (STABLE_DELAY_AS_HZ.to_Hz() * DIVIDER))
+            .checked_mul(delay_multiplier)
+            .ok_or(Error::BadArgument)?;
 
-        //Then we check if it fits into an u16.
+        //Then we check if it fits into the 14 bits of the register.
+        if startup_delay >= 1 << 14 {
+            return Err(Error::BadArgument);
+        }
         let startup_delay: u16 = startup_delay.try_into().map_err(|_| Error::BadArgument)?;
 
         self.device.startup.write(|w| unsafe {